@@ -18,7 +18,10 @@ use serde::Deserialize;
 use serde::Serialize;
 use serde_json::json;
 use spreadsheet::update::Request;
+use spreadsheet::DimensionRange;
 use spreadsheet::GridCoordinate;
+use spreadsheet::GridProperties;
+use spreadsheet::GridRange;
 use spreadsheet::SheetProperties;
 use spreadsheet::Spreadsheet;
 use std::collections::HashMap;
@@ -63,8 +66,10 @@ pub async fn create_or_write_spreadsheet(
 pub async fn create_spreadsheet(
     creds: &Token,
     nickname: SheetNickname,
-    spreadsheet: Spreadsheet,
+    mut spreadsheet: Spreadsheet,
 ) -> Result<String, TryWithCredentialsError> {
+    let follow_up_requests = prepare_new_spreadsheet(&mut spreadsheet);
+
     let url = reqwest::Url::parse(ENDPOINT_SPREADSHEETS).expect("hardcoded URL should be valid");
     let client = reqwest::Client::new();
     trace!("Sending request to create sheet");
@@ -100,6 +105,10 @@ pub async fn create_spreadsheet(
     let ApiResponse { spreadsheet_id, spreadsheet_url } =
         response.json().await.map_err(anyhow::Error::from)?;
 
+    if !follow_up_requests.is_empty() {
+        send_batch_update(creds, &spreadsheet_id, follow_up_requests, false).await?;
+    }
+
     debug!(
         "Saving the spreadsheet under the nickname {}",
         serde_json::to_string(&nickname).expect("should work")
@@ -112,6 +121,57 @@ pub async fn create_spreadsheet(
     Ok(spreadsheet_url)
 }
 
+/// Reads the values in the specified range (in A1 notation, e.g. "Targets" for
+/// an entire sheet) of a spreadsheet, formatted as they are displayed in the
+/// sheet. Trailing empty rows and cells are omitted.
+pub async fn read_values(
+    creds: &Token,
+    spreadsheet_id: &str,
+    range: &str,
+) -> Result<Vec<Vec<String>>, TryWithCredentialsError> {
+    let mut url =
+        reqwest::Url::parse(ENDPOINT_SPREADSHEETS).expect("hardcoded URL should be valid");
+    url.path_segments_mut().expect("hardcoded URL should be a base").extend([
+        spreadsheet_id,
+        "values",
+        range,
+    ]);
+    let client = reqwest::Client::new();
+    trace!("Sending request to read values");
+    let response = client
+        .get(url)
+        .bearer_auth(creds.access_token().secret())
+        .send()
+        .await
+        .map_err(anyhow::Error::from)?;
+
+    if !response.status().is_success() {
+        // a token obtained before this app could read spreadsheets lacks the
+        // scope to do so, which results in FORBIDDEN
+        if response.status() == StatusCode::UNAUTHORIZED
+            || response.status() == StatusCode::FORBIDDEN
+        {
+            return Err(TryWithCredentialsError::Unauthorized(anyhow!(
+                "Request to read values was unauthorized with status code: {}",
+                response.status()
+            )));
+        } else {
+            return Err(TryWithCredentialsError::Other(anyhow!(
+                "Request to read values failed with status code: {}",
+                response.status()
+            )));
+        }
+    }
+
+    #[derive(Deserialize)]
+    struct ApiResponse {
+        #[serde(default)]
+        values: Vec<Vec<String>>,
+    }
+    let ApiResponse { values } = response.json().await.map_err(anyhow::Error::from)?;
+    Ok(values)
+}
+
 async fn update_spreadsheet(
     creds: &Token,
     spreadsheet_id: &str,
@@ -147,19 +207,116 @@ async fn update_spreadsheet(
         response.json().await.map_err(anyhow::Error::from)?
     };
 
+    let requests = batch_update_requests(existing_spreadsheet, spreadsheet);
+    let updated_spreadsheet = send_batch_update(creds, spreadsheet_id, requests, true).await?;
+
+    let url = 'url: {
+        if let Some(updated_spreadsheet) = updated_spreadsheet {
+            if let Some(spreadsheet_url) = updated_spreadsheet.spreadsheet_url {
+                break 'url spreadsheet_url;
+            }
+        }
+        warn!("No URL returned in response to updating sheet. Inferring URL from spreadsheet ID and a hardcoded pattern");
+        format!(
+            "https://docs.google.com/spreadsheets/d/{spreadsheet_id}/edit",
+            spreadsheet_id = spreadsheet_id
+        )
+    };
+    info!("Updated Google Sheet at {}", url);
+    Ok(url)
+}
+
+/// Sends the requests to the `batchUpdate` endpoint of the spreadsheet. Returns
+/// the updated spreadsheet (without grid data) if `include_spreadsheet` is
+/// set and the response includes it.
+async fn send_batch_update(
+    creds: &Token,
+    spreadsheet_id: &str,
+    requests: Vec<Request>,
+    include_spreadsheet: bool,
+) -> Result<Option<Spreadsheet>, TryWithCredentialsError> {
+    let client = reqwest::Client::new();
+
+    // construct the final request body
+    let request_body = json!({
+        "requests": requests,
+        "includeSpreadsheetInResponse": include_spreadsheet,
+        "responseIncludeGridData": false,
+    });
+
+    let url = reqwest::Url::parse(&format!("{ENDPOINT_SPREADSHEETS}/{spreadsheet_id}:batchUpdate"))
+        .map_err(anyhow::Error::from)?;
+    let request = client
+        .post(url)
+        .bearer_auth(creds.access_token().secret())
+        .json(&request_body)
+        .build()
+        .map_err(anyhow::Error::from)?;
+    let response = client.execute(request).await.map_err(anyhow::Error::from)?;
+    if !response.status().is_success() {
+        if response.status() == StatusCode::UNAUTHORIZED {
+            return Err(TryWithCredentialsError::Unauthorized(anyhow!(
+                "Request to update spreadsheet was unauthorized with status code: {}",
+                response.status()
+            )));
+        } else {
+            return Err(TryWithCredentialsError::Other(anyhow!(
+                "Request to update spreadsheet failed with status code: {}",
+                response.status()
+            )));
+        }
+    }
+
+    #[derive(Deserialize)]
+    struct ApiResponse {
+        replies: serde_json::Value,
+        #[serde(rename = "updatedSpreadsheet")]
+        updated_spreadsheet: Option<Spreadsheet>,
+    }
+    let response_content: ApiResponse = response.json().await.map_err(anyhow::Error::from)?;
+    trace!("Received replies to updating sheet: {}", response_content.replies);
+    Ok(response_content.updated_spreadsheet)
+}
+
+/// A HashMap of known sheets, where the key is some string, and the value is
+/// the spreadsheet ID.
+type KnownSheets<'a> = HashMap<SheetNickname, Cow<'a, str>>;
+
+/// Determines the requests that make the existing spreadsheet match the new
+/// one: sheets are matched by title, sheets with new titles are added, and
+/// sheets that are no longer present are deleted.
+fn batch_update_requests(
+    existing_spreadsheet: Spreadsheet,
+    spreadsheet: Spreadsheet,
+) -> Vec<Request> {
     // keep track of existing sheet IDs so we can update existing sheets, as
     // as well as delete sheets that we don't care about, as well as assign
     // sheet ids to new sheets without conflicts
     let mut title_to_sheet_id = HashMap::new();
     let mut existing_sheet_ids = HashSet::new();
+    // the number of conditional format rules, the banding IDs, and the chart
+    // IDs of each existing sheet, which are replaced rather than added to
+    let mut existing_formatting = HashMap::new();
     if let Some(sheets) = existing_spreadsheet.sheets {
         for sheet in sheets {
-            let SheetProperties { sheet_id, title } = sheet.properties;
+            let SheetProperties { sheet_id, title, .. } = sheet.properties;
             if let (Some(sheet_id), Some(title)) = (sheet_id, title) {
                 title_to_sheet_id.insert(title, sheet_id);
             }
             if let Some(sheet_id) = sheet_id {
                 existing_sheet_ids.insert(sheet_id);
+                let num_conditional_formats =
+                    sheet.conditional_formats.map(|rules| rules.len()).unwrap_or(0);
+                let banding_ids: Vec<u64> = sheet
+                    .banded_ranges
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|banded_range| banded_range.banded_range_id)
+                    .collect();
+                let chart_ids: Vec<u64> =
+                    sheet.charts.into_iter().flatten().filter_map(|chart| chart.chart_id).collect();
+                existing_formatting
+                    .insert(sheet_id, (num_conditional_formats, banding_ids, chart_ids));
             }
         }
     }
@@ -182,14 +339,33 @@ async fn update_spreadsheet(
             if sheet.properties.sheet_id.is_some() {
                 warn!("sheet ID is ignored when updating a spreadsheet; use the title instead");
             }
+            let frozen_row_count = sheet
+                .properties
+                .grid_properties
+                .as_ref()
+                .and_then(|grid_properties| grid_properties.frozen_row_count)
+                .unwrap_or(0);
             let sheet_id = 'sheet_id: {
                 if let Some(title) = &sheet.properties.title {
                     if let Some(sheet_id) = title_to_sheet_id.remove(title) {
-                        // we would push a request to update the sheet
-                        // properties here, but there are none to update, since
-                        // sheet_id and title are the only fields we currently
-                        // support and they are already known to match at this
-                        // point
+                        // the grid properties of the sheet are updated along
+                        // with its content below, and the title is already
+                        // known to match at this point
+
+                        // remove the old formatting and charts, since adding
+                        // them again would duplicate them
+                        let (num_conditional_formats, banding_ids, chart_ids) =
+                            existing_formatting.remove(&sheet_id).unwrap_or_default();
+                        for _ in 0..num_conditional_formats {
+                            requests
+                                .push(Request::DeleteConditionalFormatRule { sheet_id, index: 0 });
+                        }
+                        for banded_range_id in banding_ids {
+                            requests.push(Request::DeleteBanding { banded_range_id });
+                        }
+                        for object_id in chart_ids {
+                            requests.push(Request::DeleteEmbeddedObject { object_id });
+                        }
 
                         break 'sheet_id sheet_id;
                     }
@@ -212,10 +388,36 @@ async fn update_spreadsheet(
             };
 
             if let Some(grid_data) = sheet.data {
+                // clear the previous content of the sheet, then resize the
+                // sheet to fit the new content exactly, so that no stale rows
+                // or columns are left over from a larger previous report
+                requests.push(Request::ClearCells {
+                    range: GridRange { sheet_id, ..Default::default() },
+                    fields: "userEnteredValue,userEnteredFormat",
+                });
+                let row_count = grid_data.start_row + grid_data.row_data.len() as u64;
+                let column_count = grid_data.start_column
+                    + grid_data.row_data.iter().map(|row| row.values.len()).max().unwrap_or(0)
+                        as u64;
+                requests.push(Request::UpdateSheetProperties {
+                    properties: SheetProperties {
+                        sheet_id: Some(sheet_id),
+                        grid_properties: Some(GridProperties {
+                            // a sheet must have at least one row and column,
+                            // and at least one row that isn't frozen
+                            row_count: Some(row_count.max(frozen_row_count + 1)),
+                            column_count: Some(column_count.max(1)),
+                            frozen_row_count: Some(frozen_row_count),
+                        }),
+                        ..Default::default()
+                    },
+                    fields: "gridProperties(rowCount,columnCount,frozenRowCount)",
+                });
+
                 // push a request to update the content of the sheet
                 requests.push(Request::UpdateCells {
                     rows: grid_data.row_data,
-                    fields: "userEnteredValue",
+                    fields: "userEnteredValue,userEnteredFormat",
                     start: GridCoordinate {
                         sheet_id,
                         row_index: grid_data.start_row,
@@ -223,6 +425,26 @@ async fn update_spreadsheet(
                     },
                 })
             }
+
+            for (index, mut rule) in sheet.conditional_formats.into_iter().flatten().enumerate() {
+                for range in &mut rule.ranges {
+                    range.sheet_id = sheet_id;
+                }
+                requests.push(Request::AddConditionalFormatRule { rule, index });
+            }
+            for mut banded_range in sheet.banded_ranges.into_iter().flatten() {
+                banded_range.banded_range_id = None;
+                banded_range.range.sheet_id = sheet_id;
+                requests.push(Request::AddBanding { banded_range });
+            }
+            if sheet.auto_resize {
+                requests.push(auto_resize_request(sheet_id));
+            }
+            for mut chart in sheet.charts.into_iter().flatten() {
+                chart.chart_id = None;
+                chart.set_sheet_id(sheet_id);
+                requests.push(Request::AddChart { chart });
+            }
         }
     }
 
@@ -231,65 +453,50 @@ async fn update_spreadsheet(
         requests.push(Request::DeleteSheet { sheet_id });
     }
 
-    // construct the final request body
-    let request_body = json!({
-        "requests": requests,
-        "includeSpreadsheetInResponse": true,
-        "responseIncludeGridData": false,
-    });
-
-    let url = reqwest::Url::parse(&format!("{ENDPOINT_SPREADSHEETS}/{spreadsheet_id}:batchUpdate"))
-        .map_err(anyhow::Error::from)?;
-    let request = client
-        .post(url)
-        .bearer_auth(creds.access_token().secret())
-        .json(&request_body)
-        .build()
-        .map_err(anyhow::Error::from)?;
-    let response = client.execute(request).await.map_err(anyhow::Error::from)?;
-    if !response.status().is_success() {
-        if response.status() == StatusCode::UNAUTHORIZED {
-            return Err(TryWithCredentialsError::Unauthorized(anyhow!(
-                "Request to update spreadsheet was unauthorized with status code: {}",
-                response.status()
-            )));
-        } else {
-            return Err(TryWithCredentialsError::Other(anyhow!(
-                "Request to update spreadsheet failed with status code: {}",
-                response.status()
-            )));
-        }
-    }
+    requests
+}
 
-    #[derive(Deserialize)]
-    struct ApiResponse {
-        replies: serde_json::Value,
-        #[serde(rename = "updatedSpreadsheet")]
-        updated_spreadsheet: Option<Spreadsheet>,
+fn auto_resize_request(sheet_id: u64) -> Request {
+    Request::AutoResizeDimensions {
+        dimensions: DimensionRange {
+            sheet_id,
+            dimension: "COLUMNS",
+            start_index: None,
+            end_index: None,
+        },
     }
-    let response_content: ApiResponse = response.json().await.map_err(anyhow::Error::from)?;
-    trace!("Received replies to updating sheet: {}", response_content.replies);
+}
 
-    let url = 'url: {
-        if let Some(updated_spreadsheet) = response_content.updated_spreadsheet {
-            if let Some(spreadsheet_url) = updated_spreadsheet.spreadsheet_url {
-                break 'url spreadsheet_url;
+/// Assigns IDs to the sheets of a spreadsheet that is about to be created, so
+/// that the ranges in their formatting refer to the right sheets. Returns the
+/// requests that have to be sent after the spreadsheet is created, since they
+/// are not part of the spreadsheet resource.
+fn prepare_new_spreadsheet(spreadsheet: &mut Spreadsheet) -> Vec<Request> {
+    let mut requests = Vec::new();
+    for (sheet_id, sheet) in spreadsheet.sheets.iter_mut().flatten().enumerate() {
+        let sheet_id = sheet_id as u64;
+        sheet.properties.sheet_id = Some(sheet_id);
+        for rule in sheet.conditional_formats.iter_mut().flatten() {
+            for range in &mut rule.ranges {
+                range.sheet_id = sheet_id;
             }
         }
-        warn!("No URL returned in response to updating sheet. Inferring URL from spreadsheet ID and a hardcoded pattern");
-        format!(
-            "https://docs.google.com/spreadsheets/d/{spreadsheet_id}/edit",
-            spreadsheet_id = spreadsheet_id
-        )
-    };
-    info!("Updated Google Sheet at {}", url);
-    Ok(url)
+        for banded_range in sheet.banded_ranges.iter_mut().flatten() {
+            banded_range.banded_range_id = None;
+            banded_range.range.sheet_id = sheet_id;
+        }
+        if sheet.auto_resize {
+            requests.push(auto_resize_request(sheet_id));
+        }
+        for mut chart in sheet.charts.take().into_iter().flatten() {
+            chart.chart_id = None;
+            chart.set_sheet_id(sheet_id);
+            requests.push(Request::AddChart { chart });
+        }
+    }
+    requests
 }
 
-/// A HashMap of known sheets, where the key is some string, and the value is
-/// the spreadsheet ID.
-type KnownSheets<'a> = HashMap<SheetNickname, Cow<'a, str>>;
-
 fn update_known_sheets_file(nickname: SheetNickname, spreadsheet_id: &str) -> std::io::Result<()> {
     let path = Path::new(KNOWN_SHEETS_FILE);
 
@@ -338,4 +545,244 @@ fn read_known_sheets_file(nickname: SheetNickname) -> std::io::Result<Option<Str
 pub enum SheetNickname {
     AccReceivable,
     Kpi,
+    Pipeline,
+    Forecast,
+    Losses,
+    Commission,
+    Leaderboard,
+    Audit,
+    Stale,
+    Reps,
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+    use spreadsheet::{
+        BandedRange, CellData, Color, ConditionalFormatRule, EmbeddedChart, ExtendedValue,
+        GridData, RowData, Sheet, SpreadsheetProperties,
+    };
+
+    fn sheet(sheet_id: Option<u64>, title: &str, rows: Option<Vec<Vec<&str>>>) -> Sheet {
+        Sheet {
+            properties: SheetProperties {
+                sheet_id,
+                title: Some(title.to_owned()),
+                ..Default::default()
+            },
+            data: rows.map(|rows| GridData {
+                start_row: 0,
+                start_column: 0,
+                row_data: rows
+                    .into_iter()
+                    .map(|row| RowData {
+                        values: row
+                            .into_iter()
+                            .map(|cell| CellData {
+                                user_entered_value: Some(ExtendedValue::StringValue(
+                                    cell.to_owned(),
+                                )),
+                                ..Default::default()
+                            })
+                            .collect(),
+                    })
+                    .collect(),
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn update_clears_and_resizes_sheets() {
+        let existing = Spreadsheet {
+            sheets: Some(vec![sheet(Some(0), "Old", None), sheet(Some(3), "Data", None)]),
+            ..Default::default()
+        };
+        let spreadsheet = Spreadsheet {
+            properties: SpreadsheetProperties { title: Some("Report".to_owned()) },
+            sheets: Some(vec![
+                sheet(None, "Data", Some(vec![vec!["a", "b", "c"], vec!["d"]])),
+                sheet(None, "New", Some(vec![vec!["e"]])),
+            ]),
+            ..Default::default()
+        };
+
+        let requests = batch_update_requests(existing, spreadsheet);
+        let cell = |value: &str| json!({ "userEnteredValue": { "stringValue": value } });
+        assert_eq!(
+            serde_json::to_value(requests).unwrap(),
+            json!([
+                {
+                    "updateSpreadsheetProperties": {
+                        "properties": { "title": "Report" },
+                        "fields": "title",
+                    }
+                },
+                {
+                    "updateCells": {
+                        "range": { "sheetId": 3 },
+                        "fields": "userEnteredValue,userEnteredFormat",
+                    }
+                },
+                {
+                    "updateSheetProperties": {
+                        "properties": {
+                            "sheetId": 3,
+                            "gridProperties": { "rowCount": 2, "columnCount": 3, "frozenRowCount": 0 },
+                        },
+                        "fields": "gridProperties(rowCount,columnCount,frozenRowCount)",
+                    }
+                },
+                {
+                    "updateCells": {
+                        "rows": [
+                            { "values": [cell("a"), cell("b"), cell("c")] },
+                            { "values": [cell("d")] },
+                        ],
+                        "fields": "userEnteredValue,userEnteredFormat",
+                        "start": { "sheetId": 3, "rowIndex": 0, "columnIndex": 0 },
+                    }
+                },
+                { "addSheet": { "properties": { "sheetId": 1, "title": "New" } } },
+                {
+                    "updateCells": {
+                        "range": { "sheetId": 1 },
+                        "fields": "userEnteredValue,userEnteredFormat",
+                    }
+                },
+                {
+                    "updateSheetProperties": {
+                        "properties": {
+                            "sheetId": 1,
+                            "gridProperties": { "rowCount": 1, "columnCount": 1, "frozenRowCount": 0 },
+                        },
+                        "fields": "gridProperties(rowCount,columnCount,frozenRowCount)",
+                    }
+                },
+                {
+                    "updateCells": {
+                        "rows": [{ "values": [cell("e")] }],
+                        "fields": "userEnteredValue,userEnteredFormat",
+                        "start": { "sheetId": 1, "rowIndex": 0, "columnIndex": 0 },
+                    }
+                },
+                { "deleteSheet": { "sheetId": 0 } },
+            ])
+        );
+    }
+
+    #[test]
+    fn update_replaces_formatting() {
+        let existing = Spreadsheet {
+            sheets: Some(vec![Sheet {
+                conditional_formats: Some(vec![Default::default(), Default::default()]),
+                banded_ranges: Some(vec![BandedRange {
+                    banded_range_id: Some(7),
+                    ..Default::default()
+                }]),
+                ..sheet(Some(2), "Data", None)
+            }]),
+            ..Default::default()
+        };
+        let mut data = sheet(None, "Data", Some(vec![vec!["Rate"], vec!["0.5"]]));
+        data.properties.grid_properties =
+            Some(GridProperties { frozen_row_count: Some(1), ..Default::default() });
+        data.conditional_formats = Some(vec![ConditionalFormatRule::number_less(
+            GridRange { start_row_index: Some(1), ..Default::default() },
+            0.25,
+            Color::LIGHT_RED,
+        )]);
+        data.banded_ranges = Some(vec![BandedRange::rows(GridRange::default())]);
+        data.auto_resize = true;
+        let spreadsheet = Spreadsheet { sheets: Some(vec![data]), ..Default::default() };
+
+        let requests = serde_json::to_value(batch_update_requests(existing, spreadsheet)).unwrap();
+        let kinds: Vec<_> = requests
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|request| request.as_object().unwrap().keys().next().unwrap().as_str())
+            .collect();
+        assert_eq!(
+            kinds,
+            [
+                "deleteConditionalFormatRule",
+                "deleteConditionalFormatRule",
+                "deleteBanding",
+                "updateCells",
+                "updateSheetProperties",
+                "updateCells",
+                "addConditionalFormatRule",
+                "addBanding",
+                "autoResizeDimensions",
+            ]
+        );
+        assert_eq!(
+            requests[0],
+            json!({ "deleteConditionalFormatRule": { "sheetId": 2, "index": 0 } })
+        );
+        assert_eq!(requests[2], json!({ "deleteBanding": { "bandedRangeId": 7 } }));
+        assert_eq!(
+            requests[4]["updateSheetProperties"]["properties"]["gridProperties"]["frozenRowCount"],
+            1
+        );
+        assert_eq!(
+            requests[6]["addConditionalFormatRule"]["rule"]["ranges"],
+            json!([{ "sheetId": 2, "startRowIndex": 1 }])
+        );
+        assert_eq!(requests[7]["addBanding"]["bandedRange"]["range"], json!({ "sheetId": 2 }));
+        assert_eq!(
+            requests[8],
+            json!({ "autoResizeDimensions": { "dimensions": { "sheetId": 2, "dimension": "COLUMNS" } } })
+        );
+    }
+
+    #[test]
+    fn charts_are_replaced() {
+        let chart = || {
+            EmbeddedChart::pie(
+                "Chart",
+                GridRange { start_row_index: Some(1), ..Default::default() },
+                GridRange { start_column_index: Some(1), ..Default::default() },
+                GridCoordinate { sheet_id: 0, row_index: 0, column_index: 3 },
+            )
+        };
+        let mut data = sheet(None, "Data", Some(vec![vec!["a", "1"]]));
+        data.charts = Some(vec![chart()]);
+
+        // a new spreadsheet gets its charts after it is created
+        let mut spreadsheet = Spreadsheet {
+            sheets: Some(vec![sheet(None, "Other", None), data]),
+            ..Default::default()
+        };
+        let requests = serde_json::to_value(prepare_new_spreadsheet(&mut spreadsheet)).unwrap();
+        assert!(spreadsheet.sheets.as_ref().unwrap()[1].charts.is_none());
+        let pie_chart = &requests[0]["addChart"]["chart"]["spec"]["pieChart"];
+        assert_eq!(pie_chart["domain"]["sourceRange"]["sources"][0]["sheetId"], 1);
+        assert_eq!(pie_chart["series"]["sourceRange"]["sources"][0]["sheetId"], 1);
+
+        // an existing chart is deleted before the chart is added again
+        let existing = Spreadsheet {
+            sheets: Some(vec![Sheet {
+                charts: Some(vec![EmbeddedChart { chart_id: Some(9), ..chart() }]),
+                ..sheet(Some(4), "Data", None)
+            }]),
+            ..Default::default()
+        };
+        let mut data = sheet(None, "Data", Some(vec![vec!["a", "1"]]));
+        data.charts = Some(vec![chart()]);
+        let spreadsheet = Spreadsheet { sheets: Some(vec![data]), ..Default::default() };
+        let requests = serde_json::to_value(batch_update_requests(existing, spreadsheet)).unwrap();
+        let requests = requests.as_array().unwrap();
+        assert_eq!(requests[0], json!({ "deleteEmbeddedObject": { "objectId": 9 } }));
+        let added = &requests.last().unwrap()["addChart"]["chart"];
+        assert!(added.get("chartId").is_none());
+        assert_eq!(added["position"]["overlayPosition"]["anchorCell"]["sheetId"], 4);
+        assert_eq!(
+            added["spec"]["pieChart"]["domain"]["sourceRange"]["sources"][0],
+            json!({ "sheetId": 4, "startRowIndex": 1 })
+        );
+    }
 }
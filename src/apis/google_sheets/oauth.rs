@@ -37,6 +37,7 @@ const CLIENT_SECRET: &str = "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";
 const AUTH_URL: &str = "https://accounts.google.com/o/oauth2/v2/auth";
 const TOKEN_URL: &str = "https://oauth2.googleapis.com/token";
 const SCOPE_DRIVE_FILE: &str = "https://www.googleapis.com/auth/drive.file";
+const SCOPE_SPREADSHEETS_READONLY: &str = "https://www.googleapis.com/auth/spreadsheets.readonly";
 
 #[derive(Debug, Serialize, Deserialize)]
 struct TokenWithExpiration {
@@ -248,6 +249,9 @@ async fn get_fresh_credentials() -> anyhow::Result<TokenWithExpiration> {
     let (auth_url, csrf_token) = client
         .authorize_url(CsrfToken::new_random)
         .add_scope(Scope::new(SCOPE_DRIVE_FILE.to_string()))
+        // needed to read spreadsheets that were not created by this app, such
+        // as the sheet of sales targets
+        .add_scope(Scope::new(SCOPE_SPREADSHEETS_READONLY.to_string()))
         .set_pkce_challenge(pkce_challenge)
         .url();
 
@@ -22,6 +22,24 @@ pub struct Sheet {
     pub properties: SheetProperties,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub data: Option<GridData>,
+    /// The ranges in these rules may use any sheet ID; it is replaced with
+    /// the ID of this sheet.
+    #[serde(rename = "conditionalFormats", skip_serializing_if = "Option::is_none")]
+    pub conditional_formats: Option<Vec<ConditionalFormatRule>>,
+    /// The ranges in these bandings may use any sheet ID; it is replaced with
+    /// the ID of this sheet.
+    #[serde(rename = "bandedRanges", skip_serializing_if = "Option::is_none")]
+    pub banded_ranges: Option<Vec<BandedRange>>,
+    /// The ranges and anchor cells of these charts may use any sheet ID; it is
+    /// replaced with the ID of this sheet. Charts are added with separate
+    /// requests after the sheet is written.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub charts: Option<Vec<EmbeddedChart>>,
+    /// Whether to resize the columns to fit their contents after writing the
+    /// sheet. This is not part of the Google Sheets API's sheet resource, so
+    /// it is sent as a separate request.
+    #[serde(skip)]
+    pub auto_resize: bool,
 }
 
 #[derive(Serialize, Deserialize, Default, Debug, Clone)]
@@ -31,6 +49,18 @@ pub struct SheetProperties {
     pub sheet_id: Option<u64>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub title: Option<String>,
+    #[serde(rename = "gridProperties", skip_serializing_if = "Option::is_none")]
+    pub grid_properties: Option<GridProperties>,
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq, Eq)]
+pub struct GridProperties {
+    #[serde(rename = "rowCount", skip_serializing_if = "Option::is_none")]
+    pub row_count: Option<u64>,
+    #[serde(rename = "columnCount", skip_serializing_if = "Option::is_none")]
+    pub column_count: Option<u64>,
+    #[serde(rename = "frozenRowCount", skip_serializing_if = "Option::is_none")]
+    pub frozen_row_count: Option<u64>,
 }
 
 #[derive(Serialize, Deserialize, Default, Debug, Clone)]
@@ -52,6 +82,390 @@ pub struct RowData {
 pub struct CellData {
     #[serde(rename = "userEnteredValue")]
     pub user_entered_value: Option<ExtendedValue>,
+    #[serde(rename = "userEnteredFormat", skip_serializing_if = "Option::is_none")]
+    pub user_entered_format: Option<CellFormat>,
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
+pub struct CellFormat {
+    #[serde(rename = "numberFormat", skip_serializing_if = "Option::is_none")]
+    pub number_format: Option<NumberFormat>,
+    #[serde(rename = "backgroundColor", skip_serializing_if = "Option::is_none")]
+    pub background_color: Option<Color>,
+    #[serde(rename = "textFormat", skip_serializing_if = "Option::is_none")]
+    pub text_format: Option<TextFormat>,
+}
+
+impl CellFormat {
+    pub fn bold() -> Self {
+        CellFormat { text_format: Some(TextFormat { bold: Some(true) }), ..Default::default() }
+    }
+
+    /// Formats numbers as US dollars, e.g. "$1,234.50".
+    pub fn currency() -> Self {
+        CellFormat {
+            number_format: Some(NumberFormat {
+                kind: NumberFormatType::Currency,
+                pattern: Some("$#,##0.00".to_owned()),
+            }),
+            ..Default::default()
+        }
+    }
+
+    /// Formats fractions as percentages, e.g. 0.425 as "42.50%".
+    pub fn percent() -> Self {
+        CellFormat {
+            number_format: Some(NumberFormat {
+                kind: NumberFormatType::Percent,
+                pattern: Some("0.00%".to_owned()),
+            }),
+            ..Default::default()
+        }
+    }
+
+    /// Formats numbers with the specified number of decimal places.
+    pub fn decimal(places: usize) -> Self {
+        let pattern =
+            if places == 0 { "0".to_owned() } else { format!("0.{}", "0".repeat(places)) };
+        CellFormat {
+            number_format: Some(NumberFormat {
+                kind: NumberFormatType::Number,
+                pattern: Some(pattern),
+            }),
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct NumberFormat {
+    #[serde(rename = "type")]
+    pub kind: NumberFormatType,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pattern: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum NumberFormatType {
+    Text,
+    Number,
+    Percent,
+    Currency,
+    Date,
+    Time,
+    DateTime,
+    Scientific,
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
+pub struct TextFormat {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bold: Option<bool>,
+}
+
+/// A color with components between 0 and 1. Components that are 0 are omitted
+/// by the Google Sheets API.
+#[derive(Serialize, Deserialize, Default, Debug, Clone, Copy, PartialEq)]
+#[serde(default)]
+pub struct Color {
+    pub red: f64,
+    pub green: f64,
+    pub blue: f64,
+}
+
+impl Color {
+    pub const LIGHT_RED: Color = Color { red: 0.96, green: 0.8, blue: 0.8 };
+    pub const LIGHT_GRAY: Color = Color { red: 0.95, green: 0.95, blue: 0.95 };
+    pub const HEADER_GRAY: Color = Color { red: 0.85, green: 0.85, blue: 0.85 };
+    pub const WHITE: Color = Color { red: 1.0, green: 1.0, blue: 1.0 };
+}
+
+/// A rule that formats the cells in its ranges when a condition holds.
+#[derive(Serialize, Deserialize, Default, Debug, Clone)]
+pub struct ConditionalFormatRule {
+    #[serde(default)]
+    pub ranges: Vec<GridRange>,
+    /// `None` for kinds of rules that aren't supported here, e.g. gradients.
+    #[serde(rename = "booleanRule", skip_serializing_if = "Option::is_none")]
+    pub boolean_rule: Option<BooleanRule>,
+}
+
+impl ConditionalFormatRule {
+    /// Fills the cells in the range with the color when their number is less
+    /// than the threshold. Empty cells are not colored.
+    pub fn number_less(range: GridRange, threshold: f64, color: Color) -> Self {
+        Self::number_condition(range, "NUMBER_LESS", threshold, color)
+    }
+
+    /// Fills the cells in the range with the color when their number is
+    /// greater than the threshold.
+    pub fn number_greater(range: GridRange, threshold: f64, color: Color) -> Self {
+        Self::number_condition(range, "NUMBER_GREATER", threshold, color)
+    }
+
+    fn number_condition(range: GridRange, kind: &str, threshold: f64, color: Color) -> Self {
+        ConditionalFormatRule {
+            ranges: vec![range],
+            boolean_rule: Some(BooleanRule {
+                condition: BooleanCondition {
+                    kind: kind.to_owned(),
+                    values: vec![ConditionValue { user_entered_value: threshold.to_string() }],
+                },
+                format: CellFormat { background_color: Some(color), ..Default::default() },
+            }),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, Clone)]
+pub struct BooleanRule {
+    pub condition: BooleanCondition,
+    pub format: CellFormat,
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, Clone)]
+pub struct BooleanCondition {
+    /// The kind of condition, e.g. "NUMBER_LESS".
+    #[serde(rename = "type")]
+    pub kind: String,
+    #[serde(default)]
+    pub values: Vec<ConditionValue>,
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, Clone)]
+pub struct ConditionValue {
+    #[serde(rename = "userEnteredValue", default)]
+    pub user_entered_value: String,
+}
+
+/// Alternating colors for the rows of a range.
+#[derive(Serialize, Deserialize, Default, Debug, Clone)]
+pub struct BandedRange {
+    /// Assigned by the Google Sheets API; leave `None` when adding a banding.
+    #[serde(rename = "bandedRangeId", skip_serializing_if = "Option::is_none")]
+    pub banded_range_id: Option<u64>,
+    #[serde(default)]
+    pub range: GridRange,
+    #[serde(rename = "rowProperties", skip_serializing_if = "Option::is_none")]
+    pub row_properties: Option<BandingProperties>,
+}
+
+impl BandedRange {
+    /// Bands the rows of the range in white and light gray, with a gray
+    /// header row.
+    pub fn rows(range: GridRange) -> Self {
+        BandedRange {
+            banded_range_id: None,
+            range,
+            row_properties: Some(BandingProperties {
+                header_color: Some(Color::HEADER_GRAY),
+                first_band_color: Some(Color::WHITE),
+                second_band_color: Some(Color::LIGHT_GRAY),
+            }),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, Clone)]
+pub struct BandingProperties {
+    #[serde(rename = "headerColor", skip_serializing_if = "Option::is_none")]
+    pub header_color: Option<Color>,
+    #[serde(rename = "firstBandColor", skip_serializing_if = "Option::is_none")]
+    pub first_band_color: Option<Color>,
+    #[serde(rename = "secondBandColor", skip_serializing_if = "Option::is_none")]
+    pub second_band_color: Option<Color>,
+}
+
+/// A chart that floats over the cells of a sheet.
+#[derive(Serialize, Deserialize, Default, Debug, Clone)]
+pub struct EmbeddedChart {
+    /// Assigned by the Google Sheets API; leave `None` when adding a chart.
+    #[serde(rename = "chartId", skip_serializing_if = "Option::is_none")]
+    pub chart_id: Option<u64>,
+    #[serde(default)]
+    pub spec: ChartSpec,
+    #[serde(default)]
+    pub position: EmbeddedObjectPosition,
+}
+
+impl EmbeddedChart {
+    /// A chart of the series in `series`, labeled by the cells in `domain`,
+    /// with its top left corner at `anchor_cell`. `chart_type` is the kind of
+    /// basic chart, e.g. "BAR" or "COLUMN".
+    pub fn basic(
+        title: impl Into<String>,
+        chart_type: &str,
+        domain: GridRange,
+        series: impl IntoIterator<Item = GridRange>,
+        anchor_cell: GridCoordinate,
+    ) -> Self {
+        EmbeddedChart {
+            chart_id: None,
+            spec: ChartSpec {
+                title: Some(title.into()),
+                basic_chart: Some(BasicChartSpec {
+                    chart_type: chart_type.to_owned(),
+                    legend_position: Some("NO_LEGEND".to_owned()),
+                    domains: vec![BasicChartDomain { domain: ChartData::range(domain) }],
+                    series: series
+                        .into_iter()
+                        .map(|series| BasicChartSeries { series: ChartData::range(series) })
+                        .collect(),
+                    header_count: Some(0),
+                }),
+                pie_chart: None,
+            },
+            position: EmbeddedObjectPosition::overlay(anchor_cell),
+        }
+    }
+
+    /// A pie chart of the numbers in `series`, labeled by the cells in
+    /// `domain`, with its top left corner at `anchor_cell`.
+    pub fn pie(
+        title: impl Into<String>,
+        domain: GridRange,
+        series: GridRange,
+        anchor_cell: GridCoordinate,
+    ) -> Self {
+        EmbeddedChart {
+            chart_id: None,
+            spec: ChartSpec {
+                title: Some(title.into()),
+                basic_chart: None,
+                pie_chart: Some(PieChartSpec {
+                    legend_position: Some("RIGHT_LEGEND".to_owned()),
+                    domain: ChartData::range(domain),
+                    series: ChartData::range(series),
+                }),
+            },
+            position: EmbeddedObjectPosition::overlay(anchor_cell),
+        }
+    }
+
+    /// Makes the data ranges and the anchor cell of the chart refer to the
+    /// sheet.
+    pub fn set_sheet_id(&mut self, sheet_id: u64) {
+        let basic_data = self.spec.basic_chart.iter_mut().flat_map(|basic_chart| {
+            let domains = basic_chart.domains.iter_mut().map(|domain| &mut domain.domain);
+            domains.chain(basic_chart.series.iter_mut().map(|series| &mut series.series))
+        });
+        let pie_data = self
+            .spec
+            .pie_chart
+            .iter_mut()
+            .flat_map(|pie_chart| [&mut pie_chart.domain, &mut pie_chart.series]);
+        for data in basic_data.chain(pie_data) {
+            for range in data.source_range.iter_mut().flat_map(|range| &mut range.sources) {
+                range.sheet_id = sheet_id;
+            }
+        }
+        if let Some(overlay_position) = &mut self.position.overlay_position {
+            overlay_position.anchor_cell.sheet_id = sheet_id;
+        }
+    }
+}
+
+/// What a chart shows. Kinds of charts that aren't supported here have
+/// neither a basic chart nor a pie chart.
+#[derive(Serialize, Deserialize, Default, Debug, Clone)]
+pub struct ChartSpec {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(rename = "basicChart", skip_serializing_if = "Option::is_none")]
+    pub basic_chart: Option<BasicChartSpec>,
+    #[serde(rename = "pieChart", skip_serializing_if = "Option::is_none")]
+    pub pie_chart: Option<PieChartSpec>,
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, Clone)]
+pub struct BasicChartSpec {
+    /// The kind of chart, e.g. "BAR".
+    #[serde(rename = "chartType", default)]
+    pub chart_type: String,
+    /// Where the legend goes, e.g. "NO_LEGEND".
+    #[serde(rename = "legendPosition", skip_serializing_if = "Option::is_none")]
+    pub legend_position: Option<String>,
+    #[serde(default)]
+    pub domains: Vec<BasicChartDomain>,
+    #[serde(default)]
+    pub series: Vec<BasicChartSeries>,
+    /// The number of rows in the data that are headers.
+    #[serde(rename = "headerCount", skip_serializing_if = "Option::is_none")]
+    pub header_count: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, Clone)]
+pub struct BasicChartDomain {
+    #[serde(default)]
+    pub domain: ChartData,
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, Clone)]
+pub struct BasicChartSeries {
+    #[serde(default)]
+    pub series: ChartData,
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, Clone)]
+pub struct PieChartSpec {
+    /// Where the legend goes, e.g. "RIGHT_LEGEND".
+    #[serde(rename = "legendPosition", skip_serializing_if = "Option::is_none")]
+    pub legend_position: Option<String>,
+    #[serde(default)]
+    pub domain: ChartData,
+    #[serde(default)]
+    pub series: ChartData,
+}
+
+/// The cells that a chart takes its data from.
+#[derive(Serialize, Deserialize, Default, Debug, Clone)]
+pub struct ChartData {
+    /// `None` for charts that take their data from somewhere other than the
+    /// cells of the spreadsheet.
+    #[serde(rename = "sourceRange", skip_serializing_if = "Option::is_none")]
+    pub source_range: Option<ChartSourceRange>,
+}
+
+impl ChartData {
+    pub fn range(range: GridRange) -> Self {
+        ChartData { source_range: Some(ChartSourceRange { sources: vec![range] }) }
+    }
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, Clone)]
+pub struct ChartSourceRange {
+    #[serde(default)]
+    pub sources: Vec<GridRange>,
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, Clone)]
+pub struct EmbeddedObjectPosition {
+    /// `None` for charts on their own sheet.
+    #[serde(rename = "overlayPosition", skip_serializing_if = "Option::is_none")]
+    pub overlay_position: Option<OverlayPosition>,
+}
+
+impl EmbeddedObjectPosition {
+    pub fn overlay(anchor_cell: GridCoordinate) -> Self {
+        EmbeddedObjectPosition {
+            overlay_position: Some(OverlayPosition {
+                anchor_cell,
+                width_pixels: None,
+                height_pixels: None,
+            }),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, Clone)]
+pub struct OverlayPosition {
+    #[serde(rename = "anchorCell", default)]
+    pub anchor_cell: GridCoordinate,
+    #[serde(rename = "widthPixels", skip_serializing_if = "Option::is_none")]
+    pub width_pixels: Option<u64>,
+    #[serde(rename = "heightPixels", skip_serializing_if = "Option::is_none")]
+    pub height_pixels: Option<u64>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -66,7 +480,7 @@ pub enum ExtendedValue {
     FormulaValue(String),
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Default, Debug, Clone)]
 pub struct GridCoordinate {
     #[serde(rename = "sheetId")]
     pub sheet_id: u64,
@@ -76,6 +490,35 @@ pub struct GridCoordinate {
     pub column_index: u64,
 }
 
+/// A range of cells in a sheet. Unspecified indexes are unbounded, so a range
+/// with only a sheet ID covers the whole sheet. End indexes are exclusive.
+#[derive(Serialize, Deserialize, Default, Debug, Clone)]
+pub struct GridRange {
+    #[serde(rename = "sheetId")]
+    pub sheet_id: u64,
+    #[serde(rename = "startRowIndex", skip_serializing_if = "Option::is_none")]
+    pub start_row_index: Option<u64>,
+    #[serde(rename = "endRowIndex", skip_serializing_if = "Option::is_none")]
+    pub end_row_index: Option<u64>,
+    #[serde(rename = "startColumnIndex", skip_serializing_if = "Option::is_none")]
+    pub start_column_index: Option<u64>,
+    #[serde(rename = "endColumnIndex", skip_serializing_if = "Option::is_none")]
+    pub end_column_index: Option<u64>,
+}
+
+/// A range of whole rows or columns in a sheet. End indexes are exclusive.
+#[derive(Serialize, Debug, Clone)]
+pub struct DimensionRange {
+    #[serde(rename = "sheetId")]
+    pub sheet_id: u64,
+    /// Either "ROWS" or "COLUMNS".
+    pub dimension: &'static str,
+    #[serde(rename = "startIndex", skip_serializing_if = "Option::is_none")]
+    pub start_index: Option<u64>,
+    #[serde(rename = "endIndex", skip_serializing_if = "Option::is_none")]
+    pub end_index: Option<u64>,
+}
+
 pub mod update {
     use serde::Serialize;
 
@@ -94,7 +537,43 @@ pub mod update {
             fields: &'static str,
             start: super::GridCoordinate,
         },
+        /// Clears the specified fields of every cell in the range.
+        #[serde(rename = "updateCells")]
+        ClearCells { range: super::GridRange, fields: &'static str },
+        #[serde(rename = "updateSheetProperties")]
+        UpdateSheetProperties { properties: super::SheetProperties, fields: &'static str },
         #[serde(rename = "deleteSheet")]
-        DeleteSheet { sheet_id: u64 },
+        DeleteSheet {
+            #[serde(rename = "sheetId")]
+            sheet_id: u64,
+        },
+        #[serde(rename = "autoResizeDimensions")]
+        AutoResizeDimensions { dimensions: super::DimensionRange },
+        #[serde(rename = "addConditionalFormatRule")]
+        AddConditionalFormatRule { rule: super::ConditionalFormatRule, index: usize },
+        #[serde(rename = "deleteConditionalFormatRule")]
+        DeleteConditionalFormatRule {
+            #[serde(rename = "sheetId")]
+            sheet_id: u64,
+            index: usize,
+        },
+        #[serde(rename = "addBanding")]
+        AddBanding {
+            #[serde(rename = "bandedRange")]
+            banded_range: super::BandedRange,
+        },
+        #[serde(rename = "deleteBanding")]
+        DeleteBanding {
+            #[serde(rename = "bandedRangeId")]
+            banded_range_id: u64,
+        },
+        #[serde(rename = "addChart")]
+        AddChart { chart: super::EmbeddedChart },
+        /// Deletes a chart, by its chart ID.
+        #[serde(rename = "deleteEmbeddedObject")]
+        DeleteEmbeddedObject {
+            #[serde(rename = "objectId")]
+            object_id: u64,
+        },
     }
 }
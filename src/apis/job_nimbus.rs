@@ -5,7 +5,7 @@ use reqwest::{self, blocking::Response, header::CONTENT_TYPE};
 use serde::Deserialize;
 use tracing::info;
 
-use crate::jobs::Job;
+use crate::{config::JobFields, jobs::Job};
 
 const DEFAULT_CACHE_FILE: &str = "job_nimbus_api_key.txt";
 
@@ -32,6 +32,11 @@ pub fn get_api_key(new_api_key: Option<String>) -> Result<String, GetApiKeyError
 
 const ENDPOINT_JOBS: &str = "https://app.jobnimbus.com/api1/jobs";
 
+/// The URL of the page of a job in the JobNimbus web app.
+pub fn job_url(jnid: &str) -> String {
+    format!("https://app.jobnimbus.com/job/{}", jnid)
+}
+
 fn request_from_job_nimbus(
     api_key: &str,
     num_jobs: usize,
@@ -58,7 +63,22 @@ fn request_from_job_nimbus(
 pub fn get_all_jobs_from_job_nimbus(
     api_key: &str,
     filter: Option<&str>,
+    fields: &JobFields,
 ) -> anyhow::Result<Vec<Job>> {
+    let results: Result<Vec<_>, _> = get_all_job_values_from_job_nimbus(api_key, filter)?
+        .into_iter()
+        .map(|value| Job::from_json(value, fields))
+        .collect();
+    Ok(results?)
+}
+
+/// Gets all the jobs from JobNimbus as the JSON returned by the API, without
+/// parsing them.
+// blocking
+pub fn get_all_job_values_from_job_nimbus(
+    api_key: &str,
+    filter: Option<&str>,
+) -> anyhow::Result<Vec<serde_json::Value>> {
     use serde_json::Value;
     #[derive(Deserialize)]
     struct ApiResponse {
@@ -81,6 +101,28 @@ pub fn get_all_jobs_from_job_nimbus(
     info!("recieved {} jobs from JobNimbus", response.count);
     assert_eq!(response.count as usize, count);
 
-    let results: Result<Vec<_>, _> = response.results.into_iter().map(Job::try_from).collect();
-    Ok(results?)
+    Ok(response.results)
+}
+
+/// Updates the specified fields of a job in JobNimbus, leaving the other
+/// fields unchanged.
+// blocking
+pub fn update_job(
+    api_key: &str,
+    jnid: &str,
+    fields: &serde_json::Map<String, serde_json::Value>,
+) -> anyhow::Result<()> {
+    let url = reqwest::Url::parse(&format!("{}/{}", ENDPOINT_JOBS, jnid))?;
+    let client = reqwest::blocking::Client::new();
+    let response = client
+        .put(url)
+        .bearer_auth(api_key)
+        .header(CONTENT_TYPE, "application/json")
+        .json(fields)
+        .send()?;
+    if !response.status().is_success() {
+        bail!("Updating job {} failed with status code: {}", jnid, response.status());
+    }
+    info!("updated job {} in JobNimbus", jnid);
+    Ok(())
 }
@@ -0,0 +1,458 @@
+pub mod fix;
+
+use std::{collections::HashMap, fmt::Display};
+
+use anyhow::bail;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::AuditConfig,
+    jobs::{self, Job, Milestone, Status, Strictness, Timestamp},
+};
+
+/// How serious a data-quality problem is.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Serialize,
+    Deserialize,
+    clap::ValueEnum,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Severity::Info => write!(f, "Info"),
+            Severity::Warning => write!(f, "Warning"),
+            Severity::Error => write!(f, "Error"),
+        }
+    }
+}
+
+/// A data-quality problem found in a job.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Finding<'a> {
+    pub job: &'a Job,
+    /// The ID of the rule that found the problem.
+    pub rule: &'static str,
+    pub severity: Severity,
+    pub message: String,
+}
+
+/// A check for one kind of data-quality problem.
+pub trait AuditRule {
+    /// A unique, kebab-case ID used to refer to the rule in the configuration.
+    fn id(&self) -> &'static str;
+
+    /// A short description of the problem that the rule looks for.
+    fn description(&self) -> &'static str;
+
+    fn default_severity(&self) -> Severity;
+
+    /// Checks all the jobs at once, since some problems (e.g. duplicates)
+    /// involve multiple jobs. Returns the problematic jobs along with a
+    /// message for each.
+    fn check<'a>(&self, jobs: &'a [Job], now: Timestamp) -> Vec<(&'a Job, String)>;
+}
+
+/// All the rules that the audit knows about. Analysis errors are found with
+/// the specified strictness.
+pub fn all_rules(strictness: Strictness) -> Vec<Box<dyn AuditRule>> {
+    vec![
+        Box::new(AnalysisErrors { strictness }),
+        Box::new(MissingSalesRep),
+        Box::new(FutureMilestone),
+        Box::new(InsuranceWithoutClaimNumber),
+        Box::new(StatusContradictsDates),
+        Box::new(DuplicateAddress),
+        Box::new(ZeroReceivableInCollections),
+    ]
+}
+
+/// Checks that the configuration only has overrides for rules that exist, so
+/// that a misspelled rule ID is not silently ignored.
+pub fn check_config(rules: &[Box<dyn AuditRule>], config: &AuditConfig) -> anyhow::Result<()> {
+    if let Some(unknown) =
+        config.rules.keys().find(|id| !rules.iter().any(|rule| rule.id() == id.as_str()))
+    {
+        bail!(
+            "Unknown audit rule `{}` in the configuration; use `--list-rules` to see all rules",
+            unknown
+        );
+    }
+    Ok(())
+}
+
+/// Runs every enabled rule on the jobs, using the severities from the
+/// configuration where specified. Findings are ordered by rule, in the order
+/// of [`all_rules`].
+pub fn run_audit<'a>(
+    jobs: &'a [Job],
+    rules: &[Box<dyn AuditRule>],
+    config: &AuditConfig,
+    now: Timestamp,
+) -> Vec<Finding<'a>> {
+    let mut findings = Vec::new();
+    for rule in rules {
+        let Some(severity) = configured_severity(rule.as_ref(), config) else {
+            continue;
+        };
+        for (job, message) in rule.check(jobs, now) {
+            findings.push(Finding { job, rule: rule.id(), severity, message });
+        }
+    }
+    findings
+}
+
+/// The severity of the rule's findings, from the configuration if specified.
+/// `None` if the rule is disabled in the configuration.
+pub fn configured_severity(rule: &dyn AuditRule, config: &AuditConfig) -> Option<Severity> {
+    let rule_config = config.rules.get(rule.id());
+    if rule_config.and_then(|rule_config| rule_config.enabled) == Some(false) {
+        return None;
+    }
+    Some(
+        rule_config
+            .and_then(|rule_config| rule_config.severity)
+            .unwrap_or_else(|| rule.default_severity()),
+    )
+}
+
+fn job_number(job: &Job) -> &str {
+    job.job_number.as_deref().unwrap_or(&job.jnid)
+}
+
+/// The errors found by [`jobs::analyze_job`], which are the red flags in the
+/// KPI report.
+struct AnalysisErrors {
+    strictness: Strictness,
+}
+impl AuditRule for AnalysisErrors {
+    fn id(&self) -> &'static str {
+        "analysis-errors"
+    }
+
+    fn description(&self) -> &'static str {
+        "Milestone dates that prevent the job from being analyzed correctly"
+    }
+
+    fn default_severity(&self) -> Severity {
+        Severity::Warning
+    }
+
+    fn check<'a>(&self, jobs: &'a [Job], _now: Timestamp) -> Vec<(&'a Job, String)> {
+        jobs.iter()
+            .flat_map(|job| {
+                let (_, errors) = jobs::analyze_job_with_strictness(job.clone(), self.strictness);
+                errors.into_iter().map(move |error| (job, error.to_string()))
+            })
+            .collect()
+    }
+}
+
+struct MissingSalesRep;
+impl AuditRule for MissingSalesRep {
+    fn id(&self) -> &'static str {
+        "missing-sales-rep"
+    }
+
+    fn description(&self) -> &'static str {
+        "Jobs past the incoming lead stage without a sales rep"
+    }
+
+    fn default_severity(&self) -> Severity {
+        Severity::Warning
+    }
+
+    fn check<'a>(&self, jobs: &'a [Job], _now: Timestamp) -> Vec<(&'a Job, String)> {
+        jobs.iter()
+            .filter(|job| job.sales_rep.is_none() && job.status != Status::IncomingLead)
+            .map(|job| (job, "This job has no sales rep.".to_owned()))
+            .collect()
+    }
+}
+
+struct FutureMilestone;
+impl AuditRule for FutureMilestone {
+    fn id(&self) -> &'static str {
+        "future-milestone"
+    }
+
+    fn description(&self) -> &'static str {
+        "Milestone dates in the future, which count as reached in reports"
+    }
+
+    fn default_severity(&self) -> Severity {
+        Severity::Error
+    }
+
+    fn check<'a>(&self, jobs: &'a [Job], now: Timestamp) -> Vec<(&'a Job, String)> {
+        let mut findings = Vec::new();
+        for job in jobs {
+            for milestone in Milestone::ordered_iter().skip(1) {
+                if let Some(date) = job.milestone_dates[milestone].filter(|&date| date > now) {
+                    findings.push((
+                        job,
+                        format!(
+                            "The date for {} is in the future ({}).",
+                            milestone,
+                            date.date_naive()
+                        ),
+                    ));
+                }
+            }
+            if let Some(date) = job.milestone_dates.loss_date.filter(|&date| date > now) {
+                findings.push((
+                    job,
+                    format!("The date for Job Lost is in the future ({}).", date.date_naive()),
+                ));
+            }
+        }
+        findings
+    }
+}
+
+struct InsuranceWithoutClaimNumber;
+impl AuditRule for InsuranceWithoutClaimNumber {
+    fn id(&self) -> &'static str {
+        "insurance-without-claim-number"
+    }
+
+    fn description(&self) -> &'static str {
+        "Insurance jobs without an insurance claim number"
+    }
+
+    fn default_severity(&self) -> Severity {
+        Severity::Warning
+    }
+
+    fn check<'a>(&self, jobs: &'a [Job], _now: Timestamp) -> Vec<(&'a Job, String)> {
+        jobs.iter()
+            .filter(|job| job.insurance_checkbox && job.insurance_claim_number.is_none())
+            .map(|job| (job, "This insurance job has no claim number.".to_owned()))
+            .collect()
+    }
+}
+
+struct StatusContradictsDates;
+impl AuditRule for StatusContradictsDates {
+    fn id(&self) -> &'static str {
+        "status-contradicts-dates"
+    }
+
+    fn description(&self) -> &'static str {
+        "Statuses that contradict the milestone dates, e.g. a completed job without an install date"
+    }
+
+    fn default_severity(&self) -> Severity {
+        Severity::Error
+    }
+
+    fn check<'a>(&self, jobs: &'a [Job], _now: Timestamp) -> Vec<(&'a Job, String)> {
+        let mut findings = Vec::new();
+        for job in jobs {
+            let dates = &job.milestone_dates;
+            match job.status {
+                Status::PostInstallSupplementPending
+                | Status::FinalWalkAround
+                | Status::SubmitCoc
+                | Status::PunchList
+                | Status::JobCompleted
+                    if dates.install_date.is_none() =>
+                {
+                    findings.push((
+                        job,
+                        format!("This job's status is {}, but it has no install date.", job.status),
+                    ));
+                }
+                Status::Lost if dates.loss_date.is_none() => {
+                    findings.push((job, "This job is lost, but it has no loss date.".to_owned()));
+                }
+                _ => {}
+            }
+        }
+        findings
+    }
+}
+
+struct DuplicateAddress;
+impl AuditRule for DuplicateAddress {
+    fn id(&self) -> &'static str {
+        "duplicate-address"
+    }
+
+    fn description(&self) -> &'static str {
+        "Multiple jobs at the same address, which may be duplicates"
+    }
+
+    fn default_severity(&self) -> Severity {
+        Severity::Warning
+    }
+
+    fn check<'a>(&self, jobs: &'a [Job], _now: Timestamp) -> Vec<(&'a Job, String)> {
+        // ignore differences in case, whitespace, and punctuation
+        fn normalize(address: &str) -> String {
+            address
+                .to_lowercase()
+                .split(|c: char| !c.is_alphanumeric())
+                .filter(|word| !word.is_empty())
+                .collect::<Vec<_>>()
+                .join(" ")
+        }
+
+        let mut jobs_by_address: HashMap<String, Vec<&Job>> = HashMap::new();
+        for job in jobs {
+            if let Some(address) = &job.address {
+                jobs_by_address.entry(normalize(address)).or_default().push(job);
+            }
+        }
+
+        let mut findings = Vec::new();
+        for job in jobs {
+            let Some(address) = &job.address else {
+                continue;
+            };
+            let others: Vec<_> = jobs_by_address[&normalize(address)]
+                .iter()
+                .filter(|other| other.jnid != job.jnid)
+                .map(|other| job_number(other))
+                .collect();
+            if !others.is_empty() {
+                findings.push((
+                    job,
+                    format!("This job has the same address as job(s) {}.", others.join(", ")),
+                ));
+            }
+        }
+        findings
+    }
+}
+
+struct ZeroReceivableInCollections;
+impl AuditRule for ZeroReceivableInCollections {
+    fn id(&self) -> &'static str {
+        "zero-receivable-in-collections"
+    }
+
+    fn description(&self) -> &'static str {
+        "Jobs in collections with nothing receivable"
+    }
+
+    fn default_severity(&self) -> Severity {
+        Severity::Warning
+    }
+
+    fn check<'a>(&self, jobs: &'a [Job], _now: Timestamp) -> Vec<(&'a Job, String)> {
+        jobs.iter()
+            .filter(|job| job.status == Status::Collections && job.amt_receivable == 0)
+            .map(|job| (job, "This job is in collections, but has $0 receivable.".to_owned()))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::{DateTime, TimeDelta};
+
+    use super::*;
+    use crate::jobs::MilestoneDates;
+
+    fn job(jnid: &str) -> Job {
+        Job {
+            jnid: jnid.to_owned(),
+            sales_rep: Some("Rep".to_owned()),
+            job_number: Some(jnid.to_owned()),
+            ..Job::test_default()
+        }
+    }
+
+    fn found(findings: &[Finding], rule: &str) -> Vec<String> {
+        findings
+            .iter()
+            .filter(|finding| finding.rule == rule)
+            .map(|finding| finding.job.jnid.clone())
+            .collect()
+    }
+
+    #[test]
+    fn rules_find_problems() {
+        let now = DateTime::from_timestamp(1_000_000, 0).unwrap();
+        let jobs = vec![
+            Job { sales_rep: None, ..job("no-rep") },
+            Job { sales_rep: None, status: Status::IncomingLead, ..job("new-lead") },
+            Job {
+                milestone_dates: MilestoneDates {
+                    appointment_date: Some(now + TimeDelta::days(1)),
+                    ..job("").milestone_dates
+                },
+                ..job("future")
+            },
+            Job { insurance_checkbox: true, ..job("no-claim") },
+            Job { status: Status::JobCompleted, ..job("completed") },
+            Job { address: Some("1 Main St., Springfield".to_owned()), ..job("address-1") },
+            Job { address: Some("1 main st springfield".to_owned()), ..job("address-2") },
+            Job { status: Status::Collections, ..job("collections") },
+        ];
+        let findings =
+            run_audit(&jobs, &all_rules(Strictness::Standard), &AuditConfig::default(), now);
+
+        assert_eq!(found(&findings, "missing-sales-rep"), ["no-rep"]);
+        assert_eq!(found(&findings, "future-milestone"), ["future"]);
+        assert_eq!(found(&findings, "insurance-without-claim-number"), ["no-claim"]);
+        assert_eq!(found(&findings, "status-contradicts-dates"), ["completed"]);
+        assert_eq!(found(&findings, "duplicate-address"), ["address-1", "address-2"]);
+        assert_eq!(found(&findings, "zero-receivable-in-collections"), ["collections"]);
+    }
+
+    #[test]
+    fn rules_can_be_configured() {
+        let config: AuditConfig = serde_json::from_value(serde_json::json!({
+            "rules": {
+                "missing-sales-rep": { "enabled": false },
+                "zero-receivable-in-collections": { "severity": "error" },
+            },
+        }))
+        .unwrap();
+        let jobs = vec![
+            Job { sales_rep: None, ..job("no-rep") },
+            Job { status: Status::Collections, ..job("collections") },
+        ];
+        let now = DateTime::from_timestamp(0, 0).unwrap();
+        let findings = run_audit(&jobs, &all_rules(Strictness::Standard), &config, now);
+
+        assert!(found(&findings, "missing-sales-rep").is_empty());
+        let collections: Vec<_> = findings
+            .iter()
+            .filter(|finding| finding.rule == "zero-receivable-in-collections")
+            .collect();
+        assert_eq!(collections.len(), 1);
+        assert_eq!(collections[0].severity, Severity::Error);
+        assert!(check_config(&all_rules(Strictness::Standard), &config).is_ok());
+    }
+
+    #[test]
+    fn configuring_unknown_rules_is_an_error() {
+        let config: AuditConfig = serde_json::from_value(serde_json::json!({
+            "rules": { "missing-sales-reps": { "enabled": false } },
+        }))
+        .unwrap();
+        let error = check_config(&all_rules(Strictness::Standard), &config).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Unknown audit rule `missing-sales-reps` in the configuration; use `--list-rules` to \
+             see all rules"
+        );
+    }
+}
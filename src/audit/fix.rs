@@ -0,0 +1,337 @@
+use std::{
+    collections::HashMap,
+    fs::{File, OpenOptions},
+    io::{BufRead as _, BufReader, Write},
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context as _;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::jobs::{self, Job};
+
+/// A change to one field of a job in JobNimbus.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FieldChange {
+    /// The name of the field in the JobNimbus API.
+    pub field: String,
+    pub old: Value,
+    pub new: Value,
+}
+
+/// The changes that would fix a problem in a job.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProposedFix<'a> {
+    pub job: &'a Job,
+    /// What is wrong with the job.
+    pub reason: String,
+    pub changes: Vec<FieldChange>,
+}
+
+/// Proposes fixes for a job, given the job and the JSON that it was parsed
+/// from. Returns the reason for the fix along with the changes to make.
+type Fixer = fn(&Job, &Map<String, Value>) -> Option<(String, Vec<FieldChange>)>;
+
+/// Each fixer, along with the ID of the audit rule that finds the problem it
+/// fixes. Zero timestamps don't show up in reports, so no rule finds them.
+const FIXERS: [(Option<&str>, Fixer); 3] = [
+    (Some("analysis-errors"), fix_loss_date_on_installed_job),
+    (Some("analysis-errors"), fix_claim_number_without_insurance),
+    (None, fix_zero_timestamps),
+];
+
+/// The date fields that are cleared when they hold a zero timestamp.
+const DATE_FIELDS: [&str; 5] = [
+    jobs::KEY_APPOINTMENT_DATE,
+    jobs::KEY_CONTINGENCY_DATE,
+    jobs::KEY_CONTRACT_DATE,
+    jobs::KEY_INSTALL_DATE,
+    jobs::KEY_LOSS_DATE,
+];
+
+/// Proposes fixes for all the problems that can be fixed automatically. `values`
+/// holds the JSON of each job from the JobNimbus API, by JNID. Only the fixers
+/// whose rule ID is accepted by `enabled` are used.
+pub fn propose_fixes<'a>(
+    jobs: &'a [Job],
+    values: &HashMap<String, Map<String, Value>>,
+    enabled: impl Fn(Option<&str>) -> bool,
+) -> Vec<ProposedFix<'a>> {
+    let fixers: Vec<_> =
+        FIXERS.into_iter().filter(|(rule, _)| enabled(*rule)).map(|(_, fixer)| fixer).collect();
+    let mut fixes = Vec::new();
+    for job in jobs {
+        let Some(value) = values.get(&job.jnid) else {
+            continue;
+        };
+        for fixer in &fixers {
+            if let Some((reason, changes)) = fixer(job, value) {
+                fixes.push(ProposedFix { job, reason, changes });
+            }
+        }
+    }
+    fixes
+}
+
+fn old_value(value: &Map<String, Value>, field: &str) -> Value {
+    value.get(field).cloned().unwrap_or(Value::Null)
+}
+
+/// A job can't be lost after it was installed, so the loss date is cleared.
+fn fix_loss_date_on_installed_job(
+    job: &Job,
+    value: &Map<String, Value>,
+) -> Option<(String, Vec<FieldChange>)> {
+    let dates = &job.milestone_dates;
+    if dates.install_date.is_none() || dates.loss_date.is_none() {
+        return None;
+    }
+    Some((
+        "This job has a loss date, but it has already been installed.".to_owned(),
+        vec![FieldChange {
+            field: jobs::KEY_LOSS_DATE.to_owned(),
+            old: old_value(value, jobs::KEY_LOSS_DATE),
+            new: Value::Null,
+        }],
+    ))
+}
+
+/// Only insurance jobs have claim numbers, so the insurance checkbox is
+/// checked.
+fn fix_claim_number_without_insurance(
+    job: &Job,
+    value: &Map<String, Value>,
+) -> Option<(String, Vec<FieldChange>)> {
+    if job.insurance_checkbox || job.insurance_claim_number.is_none() {
+        return None;
+    }
+    Some((
+        "This job's insurance checkbox isn't checked, but it has an insurance claim number."
+            .to_owned(),
+        vec![FieldChange {
+            field: jobs::KEY_INSURANCE_CHECKBOX.to_owned(),
+            old: old_value(value, jobs::KEY_INSURANCE_CHECKBOX),
+            new: Value::Bool(true),
+        }],
+    ))
+}
+
+/// JobNimbus sometimes stores a zero timestamp in a date that has no value,
+/// which shows up as 1970 in JobNimbus, so those dates are cleared.
+fn fix_zero_timestamps(
+    _job: &Job,
+    value: &Map<String, Value>,
+) -> Option<(String, Vec<FieldChange>)> {
+    let changes: Vec<_> = DATE_FIELDS
+        .into_iter()
+        .filter(|field| value.get(*field).and_then(Value::as_i64) == Some(0))
+        .map(|field| FieldChange {
+            field: field.to_owned(),
+            old: old_value(value, field),
+            new: Value::Null,
+        })
+        .collect();
+    if changes.is_empty() {
+        return None;
+    }
+    Some(("This job has dates with a zero timestamp.".to_owned(), changes))
+}
+
+/// Writes the fixes as a diff, one job at a time.
+pub fn print_diff(fixes: &[ProposedFix], mut writer: impl Write) -> std::io::Result<()> {
+    for fix in fixes {
+        writeln!(
+            writer,
+            "Job {} ({}): {}",
+            fix.job.job_number.as_deref().unwrap_or(&fix.job.jnid),
+            fix.job.job_name.as_deref().unwrap_or("unnamed"),
+            fix.reason
+        )?;
+        for FieldChange { field, old, new } in &fix.changes {
+            writeln!(writer, "    - \"{}\": {}", field, old)?;
+            writeln!(writer, "    + \"{}\": {}", field, new)?;
+        }
+    }
+    Ok(())
+}
+
+/// A change that was made to JobNimbus, as recorded in the fix log.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LogEntry {
+    /// When the change was made, in RFC 3339 format.
+    pub time: String,
+    pub jnid: String,
+    pub job_number: Option<String>,
+    #[serde(flatten)]
+    pub change: FieldChange,
+}
+
+/// Groups the changes by job, so that each job is updated with a single
+/// request.
+pub fn group_changes(fixes: &[ProposedFix]) -> Vec<(String, Option<String>, Vec<FieldChange>)> {
+    let mut grouped: Vec<(String, Option<String>, Vec<FieldChange>)> = Vec::new();
+    for fix in fixes {
+        match grouped.iter_mut().find(|(jnid, _, _)| *jnid == fix.job.jnid) {
+            Some((_, _, changes)) => changes.extend(fix.changes.iter().cloned()),
+            None => grouped.push((
+                fix.job.jnid.clone(),
+                fix.job.job_number.clone(),
+                fix.changes.clone(),
+            )),
+        }
+    }
+    grouped
+}
+
+/// Makes the changes to a job in JobNimbus using `update`, then appends them to
+/// the log file.
+pub fn apply_changes(
+    jnid: &str,
+    job_number: Option<&str>,
+    changes: &[FieldChange],
+    log_path: &Path,
+    update: impl FnOnce(&str, &Map<String, Value>) -> anyhow::Result<()>,
+) -> anyhow::Result<()> {
+    let fields = changes.iter().map(|change| (change.field.clone(), change.new.clone())).collect();
+    update(jnid, &fields)?;
+
+    let mut log = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(log_path)
+        .with_context(|| format!("Failed to open fix log {}", log_path.display()))?;
+    let time = Utc::now().to_rfc3339();
+    for change in changes {
+        let entry = LogEntry {
+            time: time.clone(),
+            jnid: jnid.to_owned(),
+            job_number: job_number.map(str::to_owned),
+            change: change.clone(),
+        };
+        writeln!(log, "{}", serde_json::to_string(&entry)?)?;
+    }
+    Ok(())
+}
+
+/// Reads the fix log and returns the changes that undo it, grouped by job. The
+/// changes are undone in reverse order, so that a field changed multiple times
+/// ends up with its original value.
+pub fn read_undo_changes(log_path: &Path) -> anyhow::Result<Vec<(String, Vec<FieldChange>)>> {
+    let file = File::open(log_path)
+        .with_context(|| format!("Failed to open fix log {}", log_path.display()))?;
+    let mut undo: Vec<(String, Vec<FieldChange>)> = Vec::new();
+    let mut entries = Vec::new();
+    for line in BufReader::new(file).lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let entry: LogEntry = serde_json::from_str(&line)
+            .with_context(|| format!("Invalid entry in fix log {}", log_path.display()))?;
+        entries.push(entry);
+    }
+    for LogEntry { jnid, change, .. } in entries.into_iter().rev() {
+        let change = FieldChange { field: change.field, old: change.new, new: change.old };
+        match undo.iter_mut().find(|(undo_jnid, _)| *undo_jnid == jnid) {
+            Some((_, changes)) => {
+                // only the earliest logged value of a field matters
+                changes.retain(|existing| existing.field != change.field);
+                changes.push(change);
+            }
+            None => undo.push((jnid, vec![change])),
+        }
+    }
+    Ok(undo)
+}
+
+/// Moves the fix log aside once its changes have been undone, so that undoing
+/// again doesn't replay them. Returns the new path of the log.
+pub fn retire_log(log_path: &Path) -> anyhow::Result<PathBuf> {
+    let mut retired = log_path.as_os_str().to_owned();
+    retired.push(format!(".undone-{}", Utc::now().format("%Y%m%dT%H%M%S")));
+    let retired = PathBuf::from(retired);
+    std::fs::rename(log_path, &retired)
+        .with_context(|| format!("Failed to move fix log {}", log_path.display()))?;
+    Ok(retired)
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+    use crate::{config::JobFields, jobs::Status};
+
+    fn job_from_json(value: Value) -> (Job, Map<String, Value>) {
+        let job = Job::from_json(value.clone(), &JobFields::default()).unwrap();
+        let Value::Object(map) = value else { unreachable!() };
+        (job, map)
+    }
+
+    #[test]
+    fn fixes_are_proposed_and_undone() {
+        let (job, value) = job_from_json(json!({
+            "jnid": "abc",
+            "number": "1001",
+            "status_name": "Job Completed",
+            "date_status_change": 100,
+            "Claim #": "123",
+            "Sales Appt Date": 0,
+            "Install Date": 50,
+            "Job Lost Date (Lost Status)": 60,
+        }));
+        assert_eq!(job.status, Status::JobCompleted);
+        let values = HashMap::from([(job.jnid.clone(), value)]);
+        let jobs = [job];
+        let fixes = propose_fixes(&jobs, &values, |_| true);
+        assert_eq!(propose_fixes(&jobs, &values, |rule| rule.is_none()).len(), 1);
+        let changes: Vec<_> = fixes.iter().flat_map(|fix| fix.changes.clone()).collect();
+        assert_eq!(
+            changes,
+            [
+                FieldChange {
+                    field: jobs::KEY_LOSS_DATE.to_owned(),
+                    old: json!(60),
+                    new: Value::Null,
+                },
+                FieldChange {
+                    field: jobs::KEY_INSURANCE_CHECKBOX.to_owned(),
+                    old: Value::Null,
+                    new: json!(true),
+                },
+                FieldChange {
+                    field: jobs::KEY_APPOINTMENT_DATE.to_owned(),
+                    old: json!(0),
+                    new: Value::Null,
+                },
+            ]
+        );
+
+        let log_path =
+            std::env::temp_dir().join(format!("ahitool_fix_log_test_{}.jsonl", std::process::id()));
+        let _ = std::fs::remove_file(&log_path);
+        for (jnid, job_number, changes) in group_changes(&fixes) {
+            apply_changes(&jnid, job_number.as_deref(), &changes, &log_path, |jnid, fields| {
+                assert_eq!(jnid, "abc");
+                assert_eq!(fields.len(), 3);
+                Ok(())
+            })
+            .unwrap();
+        }
+        let undo = read_undo_changes(&log_path).unwrap();
+        let retired = retire_log(&log_path).unwrap();
+        assert!(!log_path.exists());
+        std::fs::remove_file(&retired).unwrap();
+        assert_eq!(undo.len(), 1);
+        let (jnid, undo_changes) = &undo[0];
+        assert_eq!(jnid, "abc");
+        assert_eq!(undo_changes.len(), 3);
+        assert!(undo_changes.contains(&FieldChange {
+            field: jobs::KEY_LOSS_DATE.to_owned(),
+            old: Value::Null,
+            new: json!(60),
+        }));
+    }
+}
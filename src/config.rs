@@ -0,0 +1,230 @@
+use std::{collections::BTreeMap, fs::File, io::BufReader, path::Path};
+
+use anyhow::Context as _;
+use serde::Deserialize;
+use tracing::{debug, info};
+
+use crate::{audit::Severity, jobs::Strictness, reps};
+
+/// The configuration file that is used if none is specified.
+const DEFAULT_CONFIG_FILE: &str = "ahitool_config.json";
+
+/// Settings that describe how the company uses JobNimbus. Every setting has a
+/// default, so the configuration file only needs to specify the settings that
+/// differ from the defaults.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub job_fields: JobFields,
+    /// How thoroughly jobs are checked for errors, which are reported as red
+    /// flags.
+    pub strictness: Strictness,
+    /// The branches of the sales department, each made up of teams of sales
+    /// reps.
+    pub branches: Vec<Branch>,
+    /// The monthly targets of each sales rep, by the rep's name.
+    pub targets: BTreeMap<String, RepTargets>,
+    /// A Google Sheet to read more monthly targets from.
+    pub targets_sheet: Option<TargetsSheet>,
+    pub audit: AuditConfig,
+    pub notify: NotifyConfig,
+    /// The number of days that a job can stay in a status before it is
+    /// reported as stale, by the name of the status. These override the
+    /// default thresholds; a threshold of `null` disables the status.
+    pub stale_thresholds: BTreeMap<String, Option<u32>>,
+}
+
+impl Config {
+    /// Finds the sales rep with the specified name or alias, along with the
+    /// team and branch that the rep belongs to. Differences in whitespace and
+    /// case are ignored.
+    pub fn find_rep(&self, name: &str) -> Option<RepPlacement<'_>> {
+        let key = reps::name_key(name);
+        self.branches.iter().find_map(|branch| {
+            branch.teams.iter().find_map(|team| {
+                team.reps
+                    .iter()
+                    .find(|rep| {
+                        reps::name_key(&rep.name) == key
+                            || rep.aliases.iter().any(|alias| reps::name_key(alias) == key)
+                    })
+                    .map(|rep| RepPlacement { rep, team, branch })
+            })
+        })
+    }
+}
+
+/// The names of custom JobNimbus fields, which can differ between JobNimbus
+/// accounts.
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct JobFields {
+    /// The field containing the reason that a job was lost.
+    pub loss_reason: String,
+    /// The field containing the date that a job was paid in full.
+    pub paid_date: String,
+}
+
+impl Default for JobFields {
+    fn default() -> Self {
+        JobFields { loss_reason: "Lost Reason".to_owned(), paid_date: "Paid Date".to_owned() }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Branch {
+    pub name: String,
+    pub teams: Vec<Team>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Team {
+    pub name: String,
+    pub reps: Vec<Rep>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Rep {
+    /// The name of the rep as it should appear in reports.
+    pub name: String,
+    /// Other spellings of the rep's name that appear in JobNimbus.
+    #[serde(default)]
+    pub aliases: Vec<String>,
+    /// The email address that notifications for the rep are sent to.
+    #[serde(default)]
+    pub email: Option<String>,
+}
+
+/// The number of each milestone that a sales rep is expected to reach in a
+/// month. Milestones without a target are not tracked.
+#[derive(Debug, Default, Clone, Copy, Deserialize)]
+#[serde(default)]
+pub struct RepTargets {
+    pub appointments: Option<u32>,
+    pub contracts: Option<u32>,
+    pub installs: Option<u32>,
+}
+
+/// A Google Sheet containing the monthly targets of sales reps. The first row
+/// of the sheet is a header with the columns "Sales Rep", "Appointments",
+/// "Contracts", and "Installs".
+#[derive(Debug, Deserialize)]
+pub struct TargetsSheet {
+    pub spreadsheet_id: String,
+    /// The name of the sheet within the spreadsheet.
+    #[serde(default = "TargetsSheet::default_sheet_name")]
+    pub sheet_name: String,
+}
+
+impl TargetsSheet {
+    fn default_sheet_name() -> String {
+        "Targets".to_owned()
+    }
+}
+
+/// Settings for the data-quality audit.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct AuditConfig {
+    /// Overrides for individual audit rules, by the rule's ID.
+    pub rules: BTreeMap<String, RuleConfig>,
+}
+
+/// Overrides for an audit rule. Unspecified settings use the rule's defaults.
+#[derive(Debug, Default, Clone, Copy, Deserialize)]
+#[serde(default)]
+pub struct RuleConfig {
+    /// Whether the rule is checked. Rules are enabled by default.
+    pub enabled: Option<bool>,
+    pub severity: Option<Severity>,
+}
+
+/// Settings for sending notifications to sales reps.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct NotifyConfig {
+    /// The SMTP server that emails are sent through.
+    pub smtp: Option<SmtpConfig>,
+    /// The URL that webhook notifications are posted to, e.g. a Slack or
+    /// Teams incoming webhook.
+    pub webhook_url: Option<String>,
+    /// The email address that notifications are sent to for jobs without a
+    /// sales rep, or whose rep has no email address.
+    pub fallback_email: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SmtpConfig {
+    pub host: String,
+    /// The port of the SMTP server. Defaults to 587 with TLS and 25 without.
+    #[serde(default)]
+    pub port: Option<u16>,
+    /// The address that emails are sent from.
+    pub from: String,
+    #[serde(default)]
+    pub username: Option<String>,
+    /// Whether to connect with STARTTLS. Only disable this for a local test
+    /// server.
+    #[serde(default = "SmtpConfig::default_tls")]
+    pub tls: bool,
+}
+
+impl SmtpConfig {
+    fn default_tls() -> bool {
+        true
+    }
+}
+
+/// Where a sales rep is in the sales department.
+#[derive(Debug, Clone, Copy)]
+pub struct RepPlacement<'a> {
+    pub rep: &'a Rep,
+    pub team: &'a Team,
+    pub branch: &'a Branch,
+}
+
+/// Reads the configuration from the specified file. If no file is specified,
+/// reads the default configuration file if it exists, and otherwise uses the
+/// default configuration.
+pub fn load_config(path: Option<&Path>) -> anyhow::Result<Config> {
+    let path = match path {
+        Some(path) => path,
+        None => {
+            let path = Path::new(DEFAULT_CONFIG_FILE);
+            if !path.exists() {
+                debug!("No configuration file found; using the default configuration");
+                return Ok(Config::default());
+            }
+            path
+        }
+    };
+
+    info!("Reading configuration from {}", path.display());
+    let file = File::open(path)
+        .with_context(|| format!("Failed to open configuration file {}", path.display()))?;
+    let config = serde_json::from_reader(BufReader::new(file))
+        .with_context(|| format!("Invalid configuration file {}", path.display()))?;
+    Ok(config)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn finds_reps_by_name_or_alias() {
+        let config: Config = serde_json::from_str(
+            r#"{"branches": [{"name": "North", "teams": [
+                {"name": "Team A", "reps": [{"name": "Heinrich Asbury", "aliases": ["H. Asbury"]}]},
+                {"name": "Team B", "reps": [{"name": "Maria Lopez"}]}
+            ]}]}"#,
+        )
+        .unwrap();
+        let placement = config.find_rep(" h.  asbury").unwrap();
+        assert_eq!(placement.rep.name, "Heinrich Asbury");
+        assert_eq!(placement.team.name, "Team A");
+        assert_eq!(placement.branch.name, "North");
+        assert_eq!(config.find_rep("MARIA LOPEZ").unwrap().team.name, "Team B");
+        assert!(config.find_rep("Bob").is_none());
+    }
+}
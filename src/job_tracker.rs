@@ -16,6 +16,18 @@ pub struct Bucket<J> {
     pub cum_loss_time: TimeDelta,
 }
 
+impl<J> Bucket<J> {
+    /// The average time it took for a job to reach this milestone, or `None`
+    /// if no jobs have reached it.
+    pub fn average_achieve_time(&self) -> Option<TimeDelta> {
+        if self.achieved.is_empty() {
+            None
+        } else {
+            Some(self.cum_achieve_time / self.achieved.len().try_into().unwrap())
+        }
+    }
+}
+
 impl<J> Default for Bucket<J> {
     fn default() -> Self {
         Bucket {
@@ -110,9 +122,13 @@ impl<const M: usize, const N: usize, J: Clone + PartialEq> JobTracker<M, N, J> {
     }
 
     fn bucket_after(&mut self, kind: usize, milestone: usize) -> Option<&mut Bucket<J>> {
-        ((milestone + 1)..N)
-            .find(|&ms| self.buckets[kind][ms].is_some())
-            .and_then(|i| self.buckets[kind][i].as_mut())
+        self.next_milestone(kind, milestone).and_then(|i| self.buckets[kind][i].as_mut())
+    }
+
+    /// Returns the first milestone after the given milestone that applies to
+    /// the given kind of job, or `None` if there is no such milestone.
+    pub fn next_milestone(&self, kind: usize, milestone: usize) -> Option<usize> {
+        ((milestone + 1)..N).find(|&ms| self.buckets[kind][ms].is_some())
     }
 
     /// Considering the set of all the jobs of the given numerator kinds that
@@ -231,6 +247,227 @@ impl<const M: usize, const N: usize, J> Display for JobTracker<M, N, J> {
     }
 }
 
+/// Tracks the history of jobs, including jobs that have not been settled yet,
+/// for estimating conversion rates with survival analysis. Unlike
+/// [`JobTracker`], which only accounts for settled jobs, open jobs are treated
+/// as right-censored observations: we know that they have not reached the next
+/// milestone (or been lost) as of the time of censoring, but not what happens
+/// afterwards.
+#[derive(Debug)]
+pub struct SurvivalTracker<const M: usize, const N: usize, J> {
+    mask: [[bool; N]; M],
+    records: Vec<SurvivalRecord<J>>,
+}
+
+#[derive(Debug)]
+struct SurvivalRecord<J> {
+    job: J,
+    kind: usize,
+    timestamps: Vec<Option<Timestamp>>,
+    loss_timestamp: Option<Timestamp>,
+    censor_timestamp: Option<Timestamp>,
+}
+
+impl<J> SurvivalRecord<J> {
+    /// Returns the effective time at which the job reached the given milestone,
+    /// i.e. the latest known timestamp at or before that milestone. `None` if
+    /// there is no such timestamp.
+    fn effective_timestamp(&self, milestone: usize) -> Option<Timestamp> {
+        self.timestamps[..=milestone].iter().rev().find_map(|&ts| ts)
+    }
+}
+
+/// How an observation in a survival analysis ended.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Outcome {
+    /// The event of interest happened.
+    Target,
+    /// Some other event that precludes the event of interest happened.
+    Competing,
+    /// Nothing happened before the observation stopped.
+    Censored,
+}
+
+impl<const M: usize, const N: usize, J: Clone> SurvivalTracker<M, N, J> {
+    /// Creates a new tracker. The mask indicates which milestones apply to
+    /// which kinds of jobs, exactly like the mask for [`JobTracker::new`].
+    pub fn new(mask: [[bool; N]; M]) -> Self {
+        SurvivalTracker { mask, records: Vec::new() }
+    }
+
+    /// Adds a job of the specified kind to the tracker. The timestamps and the
+    /// loss timestamp have the same meaning as in [`JobTracker::add_job`],
+    /// except that the job may also be open, i.e. it neither reached all
+    /// milestones nor was lost. In that case, the censor timestamp is the time
+    /// up to which the job was observed to be open, and must not be `None`.
+    pub fn add_job(
+        &mut self,
+        job: &J,
+        kind: usize,
+        timestamps: &[Option<Timestamp>],
+        loss_timestamp: Option<Timestamp>,
+        censor_timestamp: Option<Timestamp>,
+    ) {
+        assert!(!timestamps.is_empty() && timestamps.len() <= N);
+        assert!(
+            timestamps.len() == N || loss_timestamp.is_some() || censor_timestamp.is_some(),
+            "An open job must have a censor timestamp"
+        );
+
+        self.records.push(SurvivalRecord {
+            job: job.clone(),
+            kind,
+            timestamps: timestamps.to_vec(),
+            loss_timestamp,
+            censor_timestamp,
+        });
+    }
+
+    /// Returns all the jobs of the given kinds that have reached the given
+    /// milestone.
+    pub fn achieved(&self, milestone: usize, kinds: &[usize]) -> Vec<J> {
+        self.records
+            .iter()
+            .filter(|r| kinds.contains(&r.kind) && r.timestamps.len() > milestone)
+            .map(|r| r.job.clone())
+            .collect()
+    }
+
+    /// Considering all jobs of the given kinds that reached the `from`
+    /// milestone, estimates the probability that the next milestone they
+    /// eventually reach is the `to` milestone, as well as the expected time to
+    /// reach it among the jobs that do. Losses and reaching other milestones
+    /// are treated as competing events, and open jobs are treated as
+    /// censored.
+    ///
+    /// Jobs for which the time of reaching the `from` milestone is unknown are
+    /// ignored.
+    pub fn calc_stats(&self, from: usize, to: usize, kinds: &[usize]) -> SurvivalStatsResult<J> {
+        let mut achieved = Vec::new();
+        let mut observations = Vec::new();
+        for record in self.records.iter().filter(|r| kinds.contains(&r.kind)) {
+            if record.timestamps.len() <= from {
+                continue;
+            }
+            let Some(start) = record.effective_timestamp(from) else {
+                continue;
+            };
+
+            // the next milestone that this job reached, if any
+            let next = ((from + 1)..record.timestamps.len()).find(|&ms| self.mask[record.kind][ms]);
+            let observation = match next {
+                Some(next) => {
+                    let end = record.effective_timestamp(next).unwrap_or(start);
+                    if next == to {
+                        achieved.push(record.job.clone());
+                        (end - start, Outcome::Target)
+                    } else {
+                        (end - start, Outcome::Competing)
+                    }
+                }
+                None => match (record.loss_timestamp, record.censor_timestamp) {
+                    (Some(loss_timestamp), _) => (loss_timestamp - start, Outcome::Competing),
+                    (None, Some(censor_timestamp)) => (censor_timestamp - start, Outcome::Censored),
+                    (None, None) => unreachable!("an open job must have a censor timestamp"),
+                },
+            };
+            observations.push(observation);
+        }
+
+        let (probability, expected_time_to_achieve) = cumulative_incidence(observations);
+        SurvivalStatsResult { achieved, probability, expected_time_to_achieve }
+    }
+
+    /// Considering all jobs of the given kinds that reached the `from`
+    /// milestone, estimates the probability that they are eventually lost, as
+    /// well as the expected time from the `from` milestone until the loss among
+    /// the jobs that are lost. Reaching the final milestone is treated as a
+    /// competing event, and open jobs are treated as censored.
+    pub fn calc_stats_of_loss(&self, from: usize, kinds: &[usize]) -> SurvivalStatsResult<J> {
+        let mut achieved = Vec::new();
+        let mut observations = Vec::new();
+        for record in self.records.iter().filter(|r| kinds.contains(&r.kind)) {
+            if record.timestamps.len() <= from {
+                continue;
+            }
+            let Some(start) = record.effective_timestamp(from) else {
+                continue;
+            };
+
+            let observation = if let Some(loss_timestamp) = record.loss_timestamp {
+                achieved.push(record.job.clone());
+                (loss_timestamp - start, Outcome::Target)
+            } else if record.timestamps.len() == N {
+                let end = record.effective_timestamp(N - 1).unwrap_or(start);
+                (end - start, Outcome::Competing)
+            } else {
+                let censor_timestamp =
+                    record.censor_timestamp.expect("an open job must have a censor timestamp");
+                (censor_timestamp - start, Outcome::Censored)
+            };
+            observations.push(observation);
+        }
+
+        let (probability, expected_time_to_achieve) = cumulative_incidence(observations);
+        SurvivalStatsResult { achieved, probability, expected_time_to_achieve }
+    }
+}
+
+/// Estimates the cumulative incidence of the target event using the
+/// Aalen-Johansen estimator (the generalization of Kaplan-Meier to competing
+/// events), given the duration and outcome of each observation. Returns the
+/// probability of the target event happening by the end of the observed
+/// period and the expected time until the target event, given that it happens.
+/// The probability is `None` if there are no observations, and the expected
+/// time is `None` if the target event never happened.
+fn cumulative_incidence(
+    mut observations: Vec<(TimeDelta, Outcome)>,
+) -> (Option<f64>, Option<TimeDelta>) {
+    if observations.is_empty() {
+        return (None, None);
+    }
+    observations.sort_by_key(|&(duration, _)| duration);
+
+    let mut at_risk = observations.len() as f64;
+    // the probability that no event has happened yet
+    let mut survival = 1.0;
+    let mut incidence = 0.0;
+    // the sum of each target event time weighted by its probability, in seconds
+    let mut weighted_time = 0.0;
+    for group in observations.chunk_by(|(a, _), (b, _)| a == b) {
+        let duration = group[0].0;
+        let count = |outcome| group.iter().filter(|&&(_, o)| o == outcome).count() as f64;
+        let (targets, competing, censored) =
+            (count(Outcome::Target), count(Outcome::Competing), count(Outcome::Censored));
+
+        // events are considered to happen before censoring at the same time
+        let probability = survival * targets / at_risk;
+        incidence += probability;
+        weighted_time += probability * duration.num_seconds() as f64;
+        survival *= 1.0 - (targets + competing) / at_risk;
+        at_risk -= targets + competing + censored;
+    }
+
+    let expected_time = if incidence > 0.0 {
+        Some(TimeDelta::seconds((weighted_time / incidence).round() as i64))
+    } else {
+        None
+    };
+    (Some(incidence), expected_time)
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct SurvivalStatsResult<J> {
+    /// All jobs that were observed to reach the event of interest.
+    pub achieved: Vec<J>,
+    /// The estimated probability of eventually reaching the event of interest.
+    /// This is `None` if there were no candidates for the event.
+    pub probability: Option<f64>,
+    /// The estimated average time to reach the event of interest, among the
+    /// jobs that reach it. This is `None` if no jobs were observed to reach it.
+    pub expected_time_to_achieve: Option<TimeDelta>,
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -387,4 +624,91 @@ mod test {
             ]
         );
     }
+
+    #[test]
+    fn next_milestone() {
+        let tracker: JobTracker<2, 5, ()> =
+            JobTracker::new([[true, true, true, true, true], [true, true, false, true, true]]);
+
+        assert_eq!(tracker.next_milestone(0, 1), Some(2));
+        assert_eq!(tracker.next_milestone(1, 1), Some(3));
+        assert_eq!(tracker.next_milestone(1, 3), Some(4));
+        assert_eq!(tracker.next_milestone(1, 4), None);
+    }
+
+    #[test]
+    fn cumulative_incidence_without_censoring() {
+        let day = TimeDelta::days(1);
+        let observations = vec![
+            (day * 2, Outcome::Target),
+            (day * 4, Outcome::Target),
+            (day * 1, Outcome::Competing),
+            (day * 3, Outcome::Competing),
+        ];
+
+        // without censoring, the estimate is the same as the naive conversion
+        // rate and average time
+        assert_eq!(cumulative_incidence(observations), (Some(0.5), Some(day * 3)));
+        assert_eq!(cumulative_incidence(vec![]), (None, None));
+    }
+
+    #[test]
+    fn cumulative_incidence_with_censoring() {
+        let day = TimeDelta::days(1);
+        let observations = vec![
+            (day * 1, Outcome::Target),
+            (day * 2, Outcome::Censored),
+            (day * 3, Outcome::Target),
+            (day * 4, Outcome::Competing),
+        ];
+
+        // at day 1, 1 of 4 at risk converts: 1/4
+        // at day 3, 1 of 2 at risk converts: 3/4 * 1/2 = 3/8
+        let (probability, expected_time) = cumulative_incidence(observations);
+        assert!((probability.unwrap() - 0.625).abs() < 1e-9);
+        // (1/4 * 1 day + 3/8 * 3 days) / (5/8)
+        assert_eq!(expected_time, Some(TimeDelta::seconds((2.2 * 86400.0) as i64)));
+    }
+
+    #[test]
+    fn survival_calc_stats() {
+        fn dt(days: i64) -> Timestamp {
+            Timestamp::from_timestamp(days * 86400, 0).unwrap()
+        }
+
+        let mut tracker =
+            SurvivalTracker::new([[true, true, true, true, true], [true, true, false, true, true]]);
+        // reached contingency after appointment
+        tracker.add_job(
+            &0,
+            0,
+            &[None, Some(dt(0)), Some(dt(1)), Some(dt(2)), Some(dt(3))],
+            None,
+            None,
+        );
+        // went straight to contract after appointment, and is still open
+        tracker.add_job(&1, 1, &[None, Some(dt(0)), None, Some(dt(2))], None, Some(dt(5)));
+        // lost after appointment
+        tracker.add_job(&2, 0, &[None, Some(dt(0))], Some(dt(4)), None);
+        // still open after appointment
+        tracker.add_job(&3, 0, &[None, Some(dt(0))], None, Some(dt(3)));
+
+        let result = tracker.calc_stats(1, 2, &[0, 1]);
+        assert_eq!(result.achieved, vec![0]);
+        assert_eq!(result.probability, Some(0.25));
+        assert_eq!(result.expected_time_to_achieve, Some(TimeDelta::days(1)));
+
+        // the open job is censored before the loss at day 4, so it does not
+        // count against the contract rate
+        let result = tracker.calc_stats(1, 3, &[0, 1]);
+        assert_eq!(result.achieved, vec![1]);
+        assert_eq!(result.probability, Some(0.25));
+
+        // day 3: job 0 is installed and job 3 is censored; day 4: job 2 is
+        // lost out of the 2 jobs at risk; day 5: job 1 is censored
+        let result = tracker.calc_stats_of_loss(1, &[0, 1]);
+        assert_eq!(result.achieved, vec![2]);
+        assert_eq!(result.probability, Some(0.75 * 0.5));
+        assert_eq!(result.expected_time_to_achieve, Some(TimeDelta::days(4)));
+    }
 }
@@ -2,26 +2,37 @@ use chrono::{DateTime, Utc};
 use std::{fmt::Display, ops::Index};
 use thiserror::Error;
 
+use crate::config::JobFields;
+
 const KEY_JNID: &str = "jnid";
 const KEY_SALES_REP: &str = "sales_rep_name";
-const KEY_INSURANCE_CHECKBOX: &str = "Insurance Job?";
+const KEY_LEAD_SOURCE: &str = "source_name";
+pub const KEY_INSURANCE_CHECKBOX: &str = "Insurance Job?";
 const KEY_INSURANCE_COMPANY_NAME: &str = "Insurance Company";
 const KEY_INSURANCE_CLAIM_NUMBER: &str = "Claim #";
 const KEY_JOB_NUMBER: &str = "number";
 const KEY_JOB_NAME: &str = "name";
-const KEY_APPOINTMENT_DATE: &str = "Sales Appt Date";
-const KEY_CONTINGENCY_DATE: &str = "Signed Contingency Date";
-const KEY_CONTRACT_DATE: &str = "Signed Contract Date";
-const KEY_INSTALL_DATE: &str = "Install Date";
-const KEY_LOSS_DATE: &str = "Job Lost Date (Lost Status)";
+pub const KEY_APPOINTMENT_DATE: &str = "Sales Appt Date";
+pub const KEY_CONTINGENCY_DATE: &str = "Signed Contingency Date";
+pub const KEY_CONTRACT_DATE: &str = "Signed Contract Date";
+pub const KEY_INSTALL_DATE: &str = "Install Date";
+pub const KEY_LOSS_DATE: &str = "Job Lost Date (Lost Status)";
 const KEY_AMOUNT_RECEIVABLE: &str = "approved_invoice_due";
+const KEY_CONTRACT_AMOUNT: &str = "approved_estimate_total";
 const KEY_STATUS_NAME: &str = "status_name";
 const KEY_STATUS_MOD_TIME: &str = "date_status_change";
+const KEY_CREATE_TIME: &str = "date_created";
+const KEY_ADDRESS_LINE1: &str = "address_line1";
+const KEY_ADDRESS_LINE2: &str = "address_line2";
+const KEY_CITY: &str = "city";
+const KEY_STATE: &str = "state_text";
+const KEY_ZIP: &str = "zip";
 
 pub type Timestamp = DateTime<Utc>;
 pub type TimeDelta = chrono::TimeDelta;
 
-#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
 pub enum Milestone {
     LeadAcquired,
     AppointmentMade,
@@ -65,7 +76,7 @@ impl Display for Milestone {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
 pub struct MilestoneDates {
     pub appointment_date: Option<Timestamp>,
     pub contingency_date: Option<Timestamp>,
@@ -150,7 +161,13 @@ pub struct Job {
     pub milestone_dates: MilestoneDates,
     pub status: Status,
     pub status_mod_date: Timestamp,
+    /// The date at which the job was created in JobNimbus, i.e. when the lead
+    /// was acquired.
+    pub date_created: Option<Timestamp>,
     pub sales_rep: Option<String>,
+    /// The marketing channel through which the lead was acquired (e.g. door
+    /// knocking, referral, or web).
+    pub lead_source: Option<String>,
     pub insurance_checkbox: bool,
     pub insurance_claim_number: Option<String>,
     // currently unused by anything, since having an insurance company name
@@ -158,11 +175,21 @@ pub struct Job {
     pub insurance_company_name: Option<String>,
     pub job_number: Option<String>,
     pub job_name: Option<String>,
+    /// The address of the job site, as a single line.
+    pub address: Option<String>,
+    /// The reason that the job was lost, if any was given.
+    pub loss_reason: Option<String>,
+    /// The date at which the job was paid in full.
+    pub paid_date: Option<Timestamp>,
     /// The amount receivable on this job, in cents.
     pub amt_receivable: i32,
+    /// The value of the contract for this job (i.e. the total of all approved
+    /// estimates), in cents.
+    pub amt_contract: i32,
 }
 
-#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
 pub enum JobKind {
     InsuranceWithContingency,
     InsuranceWithoutContingency,
@@ -179,6 +206,15 @@ impl JobKind {
         }
     }
 }
+impl Display for JobKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            JobKind::InsuranceWithContingency => write!(f, "Insurance (With Contingency)"),
+            JobKind::InsuranceWithoutContingency => write!(f, "Insurance (Without Contingency)"),
+            JobKind::Retail => write!(f, "Retail"),
+        }
+    }
+}
 
 #[derive(Clone, PartialEq, Eq, Debug)]
 pub struct JobAnalysis {
@@ -203,6 +239,13 @@ pub struct AnalyzedJob {
 }
 
 impl JobAnalysis {
+    /// Returns the last milestone that the job has reached.
+    pub fn current_milestone(&self) -> Milestone {
+        Milestone::ordered_iter()
+            .nth(self.timestamps.len() - 1)
+            .expect("a job cannot have reached more milestones than exist")
+    }
+
     /// Returns the date at which the job was settled, or `None` if the job is
     /// not settled.
     pub fn date_settled(&self) -> Option<Timestamp> {
@@ -220,7 +263,10 @@ impl JobAnalysis {
     }
 }
 
-#[derive(Debug, Error, PartialEq, Eq)]
+/// Serialized as an object with the `kind` of error, and the `milestone` that
+/// it concerns for the errors that have one (`null` for the loss date).
+#[derive(Debug, Error, PartialEq, Eq, serde::Serialize)]
+#[serde(tag = "kind", content = "milestone", rename_all = "snake_case")]
 pub enum JobAnalysisError {
     #[error("This job has signed a contingency form, but is not an insurance job.")]
     ContingencyWithoutInsurance,
@@ -232,11 +278,71 @@ pub enum JobAnalysisError {
     SkippedDates(Milestone),
     #[error("This job has a loss date, but it has already been installed/contracted.")]
     InvalidLoss,
+    #[error("The date for {} is in the future.", .0.map(|stage| stage.to_string()).unwrap_or("Job Lost".to_owned()))]
+    FutureDate(Option<Milestone>),
+    #[error("The date for {} is before the job was created.", .0.map(|stage| stage.to_string()).unwrap_or("Job Lost".to_owned()))]
+    DateBeforeCreation(Option<Milestone>),
+    #[error("This job has a loss date, but its status is not Lost.")]
+    LossDateWithoutLostStatus,
+    #[error("This job has been installed, but has no contract value.")]
+    InstallWithoutContractValue,
+}
+
+/// How thoroughly [`analyze_job_with_strictness`] checks a job for errors.
+/// Every level finds the errors that prevent the job from being analyzed; the
+/// stricter levels also find errors that don't affect the analysis, but
+/// suggest that the job was entered incorrectly.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Strictness {
+    /// Only finds the errors that prevent the job from being analyzed.
+    Lenient,
+    /// Also finds inconsistencies in the insurance info and in the loss date.
+    #[default]
+    Standard,
+    /// Also finds dates that are in the future or before the job was created,
+    /// loss dates on jobs that aren't lost, and installs without a contract
+    /// value.
+    Strict,
 }
 
+/// Analyzes the job with [`Strictness::Standard`].
 pub fn analyze_job(job: Job) -> (AnalyzedJob, Vec<JobAnalysisError>) {
+    analyze_job_with_strictness(job, Strictness::Standard)
+}
+
+pub fn analyze_job_with_strictness(
+    job: Job,
+    strictness: Strictness,
+) -> (AnalyzedJob, Vec<JobAnalysisError>) {
     let mut errors = Vec::new();
 
+    if strictness >= Strictness::Strict {
+        // these errors don't affect the analysis, so they are found up front
+        let now = Utc::now();
+        let dates = Milestone::ordered_iter()
+            .skip(1)
+            .map(|milestone| (Some(milestone), job.milestone_dates[milestone]))
+            .chain([(None, job.milestone_dates.loss_date)]);
+        for (milestone, date) in dates {
+            let Some(date) = date else {
+                continue;
+            };
+            if date > now {
+                errors.push(JobAnalysisError::FutureDate(milestone));
+            }
+            if job.date_created.is_some_and(|date_created| date < date_created) {
+                errors.push(JobAnalysisError::DateBeforeCreation(milestone));
+            }
+        }
+        if job.milestone_dates.loss_date.is_some() && job.status != Status::Lost {
+            errors.push(JobAnalysisError::LossDateWithoutLostStatus);
+        }
+        if job.milestone_dates.install_date.is_some() && job.amt_contract == 0 {
+            errors.push(JobAnalysisError::InstallWithoutContractValue);
+        }
+    }
+
     'analysis: {
         // determine what kind of job this is. assume that insurance jobs require
         // contingencies, but we will revise this later if we find that the
@@ -247,9 +353,9 @@ pub fn analyze_job(job: Job) -> (AnalyzedJob, Vec<JobAnalysisError>) {
             if job.insurance_claim_number.is_some() {
                 // in the case of existing insurance info but unchecked box, log the
                 // inconsistency and proceed as if it was an insurance job
-
-                // TODO we might consider this an error in the future
-                // errors.push(JobAnalysisError::InconsistentInsuranceInfo);
+                if strictness >= Strictness::Standard {
+                    errors.push(JobAnalysisError::InconsistentInsuranceInfo);
+                }
                 JobKind::InsuranceWithContingency
             } else {
                 JobKind::Retail
@@ -271,7 +377,9 @@ pub fn analyze_job(job: Job) -> (AnalyzedJob, Vec<JobAnalysisError>) {
                     // update the job kind if necessary
                     if milestone == Milestone::ContingencySigned && kind == JobKind::Retail {
                         kind = JobKind::InsuranceWithContingency;
-                        errors.push(JobAnalysisError::ContingencyWithoutInsurance);
+                        if strictness >= Strictness::Standard {
+                            errors.push(JobAnalysisError::ContingencyWithoutInsurance);
+                        }
                     }
                     if milestone > Milestone::ContingencySigned
                         && job.milestone_dates.contingency_date.is_none()
@@ -316,7 +424,8 @@ pub fn analyze_job(job: Job) -> (AnalyzedJob, Vec<JobAnalysisError>) {
 
             // the job cannot be lost after a contract has been signed or a
             // job has been installed
-            if current_milestone >= Milestone::ContractSigned {
+            if current_milestone >= Milestone::ContractSigned && strictness >= Strictness::Standard
+            {
                 errors.push(JobAnalysisError::InvalidLoss);
             }
         };
@@ -350,10 +459,13 @@ pub enum JobFromJsonError {
     StatusModTimeNotFound(serde_json::Map<String, serde_json::Value>),
 }
 
-impl TryFrom<serde_json::Value> for Job {
-    type Error = JobFromJsonError;
-
-    fn try_from(value: serde_json::Value) -> Result<Self, JobFromJsonError> {
+impl Job {
+    /// Parses a job from the JSON returned by the JobNimbus API. The names of
+    /// custom fields are taken from `fields`.
+    pub fn from_json(
+        value: serde_json::Value,
+        fields: &JobFields,
+    ) -> Result<Self, JobFromJsonError> {
         let serde_json::Value::Object(map) = value else {
             return Err(JobFromJsonError::NotJsonObject(value));
         };
@@ -370,12 +482,19 @@ impl TryFrom<serde_json::Value> for Job {
         }
 
         let sales_rep = get_owned_nonempty(&map, KEY_SALES_REP);
+        let lead_source = get_owned_nonempty(&map, KEY_LEAD_SOURCE);
         let insurance_checkbox =
             map.get(KEY_INSURANCE_CHECKBOX).and_then(|val| val.as_bool()).unwrap_or(false);
         let insurance_company_name = get_owned_nonempty(&map, KEY_INSURANCE_COMPANY_NAME);
         let insurance_claim_number = get_owned_nonempty(&map, KEY_INSURANCE_CLAIM_NUMBER);
         let job_number = get_owned_nonempty(&map, KEY_JOB_NUMBER);
         let job_name = get_owned_nonempty(&map, KEY_JOB_NAME);
+        let address = [KEY_ADDRESS_LINE1, KEY_ADDRESS_LINE2, KEY_CITY, KEY_STATE, KEY_ZIP]
+            .into_iter()
+            .filter_map(|key| get_owned_nonempty(&map, key))
+            .collect::<Vec<_>>();
+        let address = (!address.is_empty()).then(|| address.join(", "));
+        let loss_reason = get_owned_nonempty(&map, &fields.loss_reason);
 
         let status: Status = if let Some(s) = map.get(KEY_STATUS_NAME).and_then(|v| v.as_str()) {
             s.into()
@@ -388,6 +507,11 @@ impl TryFrom<serde_json::Value> for Job {
             .and_then(|val| val.as_f64())
             .map(|val| val * 100.0)
             .unwrap_or(0.0) as i32;
+        let amt_contract = map
+            .get(KEY_CONTRACT_AMOUNT)
+            .and_then(|val| val.as_f64())
+            .map(|val| val * 100.0)
+            .unwrap_or(0.0) as i32;
 
         // the JobNimbus API sometimes returns a 0 timestamp for a date that has
         // no value, so we want to filter those out as if the value did not
@@ -411,17 +535,24 @@ impl TryFrom<serde_json::Value> for Job {
         let Some(status_mod_date) = get_timestamp_nonzero(&map, KEY_STATUS_MOD_TIME) else {
             return Err(JobFromJsonError::StatusModTimeNotFound(map));
         };
+        let date_created = get_timestamp_nonzero(&map, KEY_CREATE_TIME);
+        let paid_date = get_timestamp_nonzero(&map, &fields.paid_date);
 
         Ok(Job {
             jnid,
             sales_rep,
+            lead_source,
             status,
             status_mod_date,
+            date_created,
             insurance_checkbox,
             insurance_company_name,
             insurance_claim_number,
             job_number,
             job_name,
+            address,
+            loss_reason,
+            paid_date,
             milestone_dates: MilestoneDates {
                 appointment_date,
                 contingency_date,
@@ -430,10 +561,48 @@ impl TryFrom<serde_json::Value> for Job {
                 loss_date,
             },
             amt_receivable,
+            amt_contract,
         })
     }
 }
 
+/// Helpers for building jobs in the tests of other modules.
+#[cfg(test)]
+pub mod test_util {
+    use super::*;
+
+    impl Job {
+        /// A job in progress with no dates, rep, or amounts, for tests to fill
+        /// in the fields that they care about.
+        pub fn test_default() -> Self {
+            Job {
+                jnid: String::new(),
+                milestone_dates: MilestoneDates::default(),
+                status: Status::JobsInProgress,
+                status_mod_date: DateTime::UNIX_EPOCH,
+                date_created: None,
+                sales_rep: None,
+                lead_source: None,
+                insurance_checkbox: false,
+                insurance_claim_number: None,
+                insurance_company_name: None,
+                job_number: None,
+                job_name: None,
+                address: None,
+                loss_reason: None,
+                paid_date: None,
+                amt_receivable: 0,
+                amt_contract: 0,
+            }
+        }
+    }
+
+    /// The start of the day, counting days from the Unix epoch.
+    pub fn day(day: i64) -> Option<Timestamp> {
+        DateTime::from_timestamp(day * 86400, 0)
+    }
+}
+
 #[cfg(test)]
 mod test {
     use std::vec;
@@ -456,13 +625,18 @@ mod test {
         Job {
             jnid: "0".to_owned(),
             sales_rep: None,
+            lead_source: None,
             status: Status::JobsInProgress, // arbitrary choice that shouldn't matter for tests
             status_mod_date: dt(0),
+            date_created: None,
             insurance_checkbox: insurance,
             insurance_claim_number: if insurance { Some("123".to_owned()) } else { None },
             insurance_company_name: if insurance { Some("Gekko".to_owned()) } else { None },
             job_number: None,
             job_name: None,
+            address: None,
+            loss_reason: None,
+            paid_date: None,
             milestone_dates: MilestoneDates {
                 appointment_date: date_1,
                 contingency_date: date_2,
@@ -471,6 +645,7 @@ mod test {
                 loss_date: date_5,
             },
             amt_receivable: 0,
+            amt_contract: 0,
         }
     }
 
@@ -673,13 +848,18 @@ mod test {
         let job = Job {
             jnid: "0".to_owned(),
             sales_rep: None,
+            lead_source: None,
             status: Status::JobsInProgress, // arbitrary; shouldn't affect tests
             status_mod_date: dt(0),
+            date_created: None,
             insurance_checkbox: false,
             insurance_claim_number: Some("123".to_owned()),
             insurance_company_name: Some("Gekko".to_owned()),
             job_number: None,
             job_name: None,
+            address: None,
+            loss_reason: None,
+            paid_date: None,
             milestone_dates: MilestoneDates {
                 appointment_date: Some(dt(1)),
                 contingency_date: None,
@@ -688,6 +868,7 @@ mod test {
                 loss_date: None,
             },
             amt_receivable: 0,
+            amt_contract: 0,
         };
         assert_eq!(
             analyze_job(job.clone()),
@@ -704,4 +885,41 @@ mod test {
             )
         );
     }
+
+    #[test]
+    fn job_analysis_lenient() {
+        let job = Job {
+            insurance_claim_number: Some("123".to_owned()),
+            ..make_job(false, Some(dt(1)), Some(dt(2)), Some(dt(3)), Some(dt(4)), Some(dt(5)))
+        };
+        let (analyzed, errors) = analyze_job_with_strictness(job, Strictness::Lenient);
+        assert!(analyzed.analysis.is_some());
+        assert_eq!(errors, vec![]);
+    }
+
+    #[test]
+    fn job_analysis_strict() {
+        let future = Utc::now() + chrono::TimeDelta::days(1);
+        let job = Job {
+            date_created: Some(dt(2)),
+            ..make_job(false, Some(dt(1)), None, Some(dt(3)), Some(future), None)
+        };
+        let (_, errors) = analyze_job_with_strictness(job, Strictness::Strict);
+        assert_eq!(
+            errors,
+            vec![
+                JobAnalysisError::DateBeforeCreation(Some(Milestone::AppointmentMade)),
+                JobAnalysisError::FutureDate(Some(Milestone::Installed)),
+                JobAnalysisError::InstallWithoutContractValue,
+            ]
+        );
+
+        let job = Job {
+            status: Status::JobsInProgress,
+            amt_contract: 100,
+            ..make_job(false, Some(dt(1)), None, None, None, Some(dt(2)))
+        };
+        let (_, errors) = analyze_job_with_strictness(job, Strictness::Strict);
+        assert_eq!(errors, vec![JobAnalysisError::LossDateWithoutLostStatus]);
+    }
 }
@@ -1,9 +1,15 @@
+use std::path::PathBuf;
+
 use clap::Parser;
 use subcommands::Subcommand;
 
 mod apis;
+mod audit;
+mod config;
 mod job_tracker;
 mod jobs;
+mod report;
+mod reps;
 mod subcommands;
 mod utils;
 
@@ -16,22 +22,55 @@ struct CliArgs {
     /// The JobNimbus API key. This key will be cached.
     #[arg(long, default_value = None, global = true, env)]
     jn_api_key: Option<String>,
+
+    /// The configuration file to use. If unspecified, uses
+    /// `ahitool_config.json` in the current directory if it exists.
+    #[arg(long, default_value = None, global = true, env = "AHITOOL_CONFIG")]
+    config: Option<PathBuf>,
 }
 
 fn main() -> anyhow::Result<()> {
     // set up tracing
     tracing_subscriber::fmt::init();
 
-    let CliArgs { jn_api_key, command } = CliArgs::parse();
+    let CliArgs { jn_api_key, config, command } = CliArgs::parse();
 
     let jn_api_key = apis::job_nimbus::get_api_key(jn_api_key)?;
+    let config = config::load_config(config.as_deref())?;
 
     match command {
         Subcommand::Kpi(job_kpi_args) => {
-            subcommands::kpi::main(&jn_api_key, job_kpi_args)?;
+            subcommands::kpi::main(&jn_api_key, &config, job_kpi_args)?;
         }
         Subcommand::Ar(acc_recv_args) => {
-            subcommands::acc_receivable::main(&jn_api_key, acc_recv_args)?;
+            subcommands::acc_receivable::main(&jn_api_key, &config, acc_recv_args)?;
+        }
+        Subcommand::Pipeline(pipeline_args) => {
+            subcommands::pipeline::main(&jn_api_key, &config, pipeline_args)?;
+        }
+        Subcommand::Forecast(forecast_args) => {
+            subcommands::forecast::main(&jn_api_key, &config, forecast_args)?;
+        }
+        Subcommand::Losses(losses_args) => {
+            subcommands::losses::main(&jn_api_key, &config, losses_args)?;
+        }
+        Subcommand::Reps(reps_args) => {
+            subcommands::reps::main(&jn_api_key, &config, reps_args)?;
+        }
+        Subcommand::Commission(commission_args) => {
+            subcommands::commission::main(&jn_api_key, &config, commission_args)?;
+        }
+        Subcommand::Leaderboard(leaderboard_args) => {
+            subcommands::leaderboard::main(&jn_api_key, &config, leaderboard_args)?;
+        }
+        Subcommand::Audit(audit_args) => {
+            subcommands::audit::main(&jn_api_key, &config, audit_args)?;
+        }
+        Subcommand::Notify(notify_args) => {
+            subcommands::notify::main(&jn_api_key, &config, notify_args)?;
+        }
+        Subcommand::Stale(stale_args) => {
+            subcommands::stale::main(&jn_api_key, &config, stale_args)?;
         }
         Subcommand::Update(update_args) => {
             subcommands::update::main(update_args)?;
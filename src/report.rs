@@ -0,0 +1,674 @@
+pub mod csv;
+pub mod html;
+pub mod human;
+pub mod json;
+pub mod markdown;
+pub mod pdf;
+pub mod sheets;
+pub mod xlsx;
+
+use std::{
+    fmt::Display,
+    io::{BufWriter, Write},
+    ops::Range,
+    path::Path,
+};
+
+use clap::CommandFactory as _;
+use serde::Serialize;
+
+use crate::{
+    apis::google_sheets::{
+        spreadsheet::{CellData, CellFormat, ExtendedValue},
+        SheetNickname,
+    },
+    jobs::TimeDelta,
+    CliArgs,
+};
+
+/// The formats that reports can be output in.
+#[derive(Debug, clap::ValueEnum, Clone, Copy, Eq, PartialEq)]
+pub enum OutputFormat {
+    /// Prints a human-readable report into the output file.
+    Human,
+    /// Prints CSV into the output file: a single table with all the rows of
+    /// the report, where the report has one.
+    Csv,
+    /// Outputs a Google Sheet on the user's Google Drive (requires OAuth
+    /// authorization).
+    GoogleSheets,
+    /// Prints the full results as a JSON document into the output file. See
+    /// the README for the schema.
+    Json,
+    /// Prints the full results as newline-delimited JSON into the output
+    /// file: a header line, then a line for each record.
+    Ndjson,
+    /// Writes an Excel workbook into the output file, laid out like the
+    /// Google Sheet but without needing a Google account or the network.
+    Xlsx,
+    /// Writes a single web page into the output file, with sortable tables
+    /// and charts, that doesn't load anything from the network.
+    Html,
+    /// Writes a printable PDF document into the output file, with a cover
+    /// page, the key figures, and each section starting on a new page.
+    Pdf,
+    /// Prints GitHub-flavored Markdown into the output file, with a heading
+    /// for each section and a Markdown table for each table.
+    Markdown,
+}
+
+/// Exits with an error if `--output` or `--update` don't apply to the format.
+pub fn check_output_args(format: OutputFormat, output: Option<&str>, update: bool) {
+    if format == OutputFormat::GoogleSheets && output.is_some() {
+        CliArgs::command()
+            .error(
+                clap::error::ErrorKind::ArgumentConflict,
+                "The `--output` option cannot be used with `--format google-sheets`",
+            )
+            .exit();
+    }
+    if format != OutputFormat::GoogleSheets && update {
+        CliArgs::command()
+            .error(
+                clap::error::ErrorKind::ArgumentConflict,
+                "The `--update` option can only be used with `--format google-sheets`",
+            )
+            .exit();
+    }
+}
+
+/// Writes reports in a file format.
+pub trait Renderer {
+    /// The extension of files in this format, e.g. "csv".
+    fn extension(&self) -> &'static str;
+
+    fn render(&self, report: &Report, out: &mut dyn Write) -> anyhow::Result<()>;
+
+    /// Whether each section of a report can be written into its own file.
+    /// Formats that output the records of the report rather than its tables
+    /// always write the whole report into one file.
+    fn splits_sections(&self) -> bool {
+        true
+    }
+}
+
+fn renderer(format: OutputFormat) -> Option<Box<dyn Renderer>> {
+    match format {
+        OutputFormat::Human => Some(Box::new(human::Human)),
+        OutputFormat::Csv => Some(Box::new(csv::Csv)),
+        OutputFormat::GoogleSheets => None,
+        OutputFormat::Json => Some(Box::new(json::Json)),
+        OutputFormat::Ndjson => Some(Box::new(json::Ndjson)),
+        OutputFormat::Xlsx => Some(Box::new(xlsx::Xlsx)),
+        OutputFormat::Html => Some(Box::new(html::Html)),
+        OutputFormat::Pdf => Some(Box::new(pdf::Pdf)),
+        OutputFormat::Markdown => Some(Box::new(markdown::Markdown)),
+    }
+}
+
+/// Outputs the report in the format. `output` is the file to write to, where
+/// "-" or `None` is stdout. With `--format google-sheets`, creates the Google
+/// Sheet known by the nickname, or updates it if `update` is set.
+pub fn output(
+    report: &Report,
+    format: OutputFormat,
+    output: Option<&str>,
+    update: bool,
+    nickname: SheetNickname,
+) -> anyhow::Result<()> {
+    let Some(renderer) = renderer(format) else {
+        return sheets::publish(report, nickname, update);
+    };
+    match output.filter(|path| *path != "-") {
+        Some(path) => write_file(&*renderer, report, Path::new(path)),
+        None => renderer.render(report, &mut std::io::stdout().lock()),
+    }
+}
+
+/// Like [`output`], but writes each section of the report into its own file in
+/// the directory `path`, named after the section. Formats that don't split
+/// sections write the whole report to the file `path`, like [`output`] does.
+pub fn output_sections(
+    report: &Report,
+    format: OutputFormat,
+    path: Option<&str>,
+    update: bool,
+    nickname: SheetNickname,
+) -> anyhow::Result<()> {
+    let Some(renderer) = renderer(format).filter(|renderer| renderer.splits_sections()) else {
+        return output(report, format, path, update, nickname);
+    };
+    let Some(output_dir) = path.filter(|path| *path != "-") else {
+        return output(report, format, None, update, nickname);
+    };
+    let output_dir = Path::new(output_dir);
+    std::fs::create_dir_all(output_dir)?;
+    for section in &report.sections {
+        let file_name = format!("{}.{}", section.file_stem(), renderer.extension());
+        let report = Report {
+            title: report.title.clone(),
+            sections: vec![section.clone()],
+            key_figures: report.key_figures.clone(),
+            flat: None,
+            records: Vec::new(),
+        };
+        write_file(&*renderer, &report, &output_dir.join(file_name))?;
+    }
+    Ok(())
+}
+
+fn write_file(renderer: &dyn Renderer, report: &Report, path: &Path) -> anyhow::Result<()> {
+    let mut out = BufWriter::new(std::fs::File::create(path)?);
+    renderer.render(report, &mut out)?;
+    out.flush()?;
+    Ok(())
+}
+
+/// A report, made up of sections that each hold any number of tables. Each
+/// section becomes a sheet in a spreadsheet, or a file when the sections are
+/// written into a directory.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Report {
+    pub title: String,
+    pub sections: Vec<Section>,
+    /// The headline numbers of the report, e.g. the total amount receivable.
+    /// Not every output format shows them.
+    pub key_figures: Vec<KeyFigure>,
+    /// All the rows of the report in a single table, for formats that only
+    /// hold one table, e.g. the payouts for payroll. `None` if the report
+    /// is not meant to be flattened.
+    pub flat: Option<Table>,
+    /// The full results behind the tables, for the JSON formats. Each record
+    /// is an object with a snake-case `type` field that says what it records.
+    /// Amounts are in dollars, rates are fractions, and durations are in days;
+    /// the README describes the fields of each type.
+    pub records: Vec<serde_json::Value>,
+}
+
+impl Report {
+    pub fn new(title: impl Into<String>) -> Self {
+        Report {
+            title: title.into(),
+            sections: Vec::new(),
+            key_figures: Vec::new(),
+            flat: None,
+            records: Vec::new(),
+        }
+    }
+
+    /// Adds a headline number to the end of the report's key figures.
+    pub fn key_figure(&mut self, name: impl Into<String>, value: Cell) {
+        self.key_figures.push(KeyFigure { name: name.into(), value });
+    }
+
+    /// Adds a record of the results to the end of the report.
+    pub fn record(&mut self, record: impl Serialize) {
+        let record = serde_json::to_value(record).expect("records should serialize to JSON");
+        self.records.push(record);
+    }
+
+    /// Adds an empty section to the end of the report and returns it.
+    pub fn section(&mut self, name: impl Into<String>) -> &mut Section {
+        self.sections.push(Section { name: name.into(), tables: Vec::new() });
+        self.sections.last_mut().expect("a section was just added")
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeyFigure {
+    pub name: String,
+    pub value: Cell,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Section {
+    /// The name of the section, e.g. "Red Flags". It is the title of the
+    /// section's sheet in a spreadsheet.
+    pub name: String,
+    pub tables: Vec<Table>,
+}
+
+impl Section {
+    /// Adds an empty table with the headers to the end of the section and
+    /// returns it.
+    pub fn table(&mut self, headers: impl IntoIterator<Item = impl Into<String>>) -> &mut Table {
+        self.tables.push(Table::new(headers));
+        self.tables.last_mut().expect("a table was just added")
+    }
+
+    /// The name of the section as a file name without an extension, e.g.
+    /// "red-flags".
+    pub fn file_stem(&self) -> String {
+        let stem: String = self
+            .name
+            .to_lowercase()
+            .chars()
+            .map(|c| if c.is_alphanumeric() { c } else { '-' })
+            .collect();
+        stem.split('-').filter(|part| !part.is_empty()).collect::<Vec<_>>().join("-")
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Table {
+    pub title: Option<String>,
+    pub headers: Vec<String>,
+    pub rows: Vec<Vec<Cell>>,
+    /// The cells that stand out, e.g. low conversion rates. Not every output
+    /// format shows them.
+    pub highlights: Vec<Highlight>,
+    /// Charts of the data in the table. Not every output format shows them.
+    pub charts: Vec<Chart>,
+    /// Whether the rows read better as a list of items than as a table, e.g.
+    /// red flags. Not every output format shows lists.
+    pub list: bool,
+    /// A row of totals below the rows, e.g. the total amount receivable. Not
+    /// every output format shows it.
+    pub total: Option<Vec<Cell>>,
+}
+
+impl Table {
+    /// An empty table with the headers, outside of any section.
+    pub fn new(headers: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        Table {
+            title: None,
+            headers: headers.into_iter().map(Into::into).collect(),
+            rows: Vec::new(),
+            highlights: Vec::new(),
+            charts: Vec::new(),
+            list: false,
+            total: None,
+        }
+    }
+
+    pub fn titled(&mut self, title: impl Into<String>) -> &mut Self {
+        self.title = Some(title.into());
+        self
+    }
+
+    pub fn listed(&mut self) -> &mut Self {
+        self.list = true;
+        self
+    }
+
+    pub fn totaled(&mut self, cells: impl IntoIterator<Item = Cell>) -> &mut Self {
+        self.total = Some(cells.into_iter().collect());
+        self
+    }
+
+    pub fn row(&mut self, cells: impl IntoIterator<Item = Cell>) -> &mut Self {
+        self.rows.push(cells.into_iter().collect());
+        self
+    }
+
+    /// Whether the cell at the row and column stands out, for the formats that
+    /// highlight cells themselves rather than with conditional formatting.
+    pub fn is_highlighted(&self, row: usize, column: usize) -> bool {
+        let Some(number) = self.rows.get(row).and_then(|cells| cells.get(column)?.value()) else {
+            return false;
+        };
+        self.highlights.iter().any(|highlight| {
+            highlight.column == column
+                && highlight.rows.as_ref().is_none_or(|rows| rows.contains(&row))
+                && highlight.condition.holds(number)
+        })
+    }
+}
+
+/// Cells in a column of a table whose number meets a condition.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Highlight {
+    pub column: usize,
+    /// The rows to consider, or all rows if `None`.
+    pub rows: Option<Range<usize>>,
+    pub condition: Condition,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Condition {
+    Below(f64),
+    Above(f64),
+}
+
+impl Condition {
+    pub fn holds(self, number: f64) -> bool {
+        match self {
+            Condition::Below(threshold) => number < threshold,
+            Condition::Above(threshold) => number > threshold,
+        }
+    }
+}
+
+/// A chart of a column of numbers in a table, labeled by another column.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Chart {
+    pub title: String,
+    pub kind: ChartKind,
+    /// The column with the label of each bar or slice.
+    pub labels: usize,
+    /// The column with the numbers.
+    pub values: usize,
+    /// The rows to chart, or all rows if `None`.
+    pub rows: Option<Range<usize>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChartKind {
+    /// Horizontal bars.
+    Bar,
+    /// Vertical bars.
+    Column,
+    Pie,
+}
+
+/// A value in a report. Numbers keep what kind of number they are, so that
+/// each output format can present them appropriately: human-readable output
+/// adds units, CSV files get plain numbers, and Google Sheets gets number
+/// values with a number format.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Cell {
+    /// A value that isn't available, e.g. the conversion rate of zero jobs.
+    Empty,
+    Text(String),
+    Bool(bool),
+    /// A count or any other number without a unit.
+    Number(f64),
+    /// A fraction, e.g. 0.5 for 50%.
+    Percent(f64),
+    /// An amount in dollars.
+    Currency(f64),
+    /// A duration in days.
+    Days(f64),
+    /// Text that links to a web page, e.g. a job number that links to the job
+    /// in JobNimbus. Formats without links show only the text.
+    Link {
+        text: String,
+        url: String,
+    },
+    /// Several links separated by commas, as pairs of text and URL, e.g. the
+    /// jobs behind a conversion rate. Formats without links show only the
+    /// text.
+    Links(Vec<(String, String)>),
+}
+
+impl Cell {
+    pub fn text(text: impl Into<String>) -> Self {
+        Cell::Text(text.into())
+    }
+
+    /// A percentage, or an empty cell if there is no rate.
+    pub fn percent(rate: Option<f64>) -> Self {
+        rate.map_or(Cell::Empty, Cell::Percent)
+    }
+
+    /// An amount of dollars, or an empty cell if there is no amount.
+    pub fn dollars(amount: Option<f64>) -> Self {
+        amount.map_or(Cell::Empty, Cell::Currency)
+    }
+
+    /// An amount of cents, presented in dollars.
+    pub fn cents(amount: i64) -> Self {
+        Cell::Currency(amount as f64 / 100.0)
+    }
+
+    pub fn link(text: impl Into<String>, url: impl Into<String>) -> Self {
+        Cell::Link { text: text.into(), url: url.into() }
+    }
+
+    pub fn links(links: impl IntoIterator<Item = (impl Into<String>, impl Into<String>)>) -> Self {
+        Cell::Links(links.into_iter().map(|(text, url)| (text.into(), url.into())).collect())
+    }
+
+    /// A duration, presented in days.
+    pub fn days(time: TimeDelta) -> Self {
+        Cell::Days(days(time))
+    }
+
+    /// Whether the cell holds a number, or stands in for one. Numbers are
+    /// aligned to the right.
+    pub fn is_number(&self) -> bool {
+        !matches!(self, Cell::Text(_) | Cell::Bool(_) | Cell::Link { .. } | Cell::Links(_))
+    }
+
+    /// The number in the cell, if it holds one.
+    pub fn value(&self) -> Option<f64> {
+        match self {
+            Cell::Number(number) | Cell::Percent(number) | Cell::Currency(number) => Some(*number),
+            Cell::Days(days) => Some(*days),
+            Cell::Empty | Cell::Text(_) | Cell::Bool(_) | Cell::Link { .. } | Cell::Links(_) => {
+                None
+            }
+        }
+    }
+
+    /// The cell as it is shown to a person, e.g. "42.50%" or "$3.00". Empty
+    /// cells are "N/A".
+    pub fn human(&self) -> String {
+        match self {
+            Cell::Empty => "N/A".to_owned(),
+            Cell::Text(text) | Cell::Link { text, .. } => text.clone(),
+            Cell::Links(links) => link_texts(links),
+            Cell::Bool(value) => if *value { "yes" } else { "no" }.to_owned(),
+            Cell::Number(number) if number.fract() == 0.0 => format!("{}", number),
+            Cell::Number(number) => format!("{:.2}", number),
+            Cell::Percent(rate) => format!("{:.2}%", rate * 100.0),
+            Cell::Currency(amount) => format!("${:.2}", amount),
+            Cell::Days(days) => format!("{:.1} days", days),
+        }
+    }
+
+    /// The cell in a CSV file. Numbers are written without units, so that they
+    /// can be parsed, and percentages are written as fractions. Empty cells are
+    /// empty strings.
+    pub fn csv(&self) -> String {
+        match self {
+            Cell::Empty => String::new(),
+            Cell::Text(text) | Cell::Link { text, .. } => text.clone(),
+            Cell::Links(links) => link_texts(links),
+            Cell::Bool(value) => value.to_string(),
+            Cell::Number(number) | Cell::Percent(number) | Cell::Days(number) => number.to_string(),
+            Cell::Currency(amount) => format!("{:.2}", amount),
+        }
+    }
+
+    /// The cell in a Google Sheet. Numbers are number values with a number
+    /// format, links are `HYPERLINK` formulas, and empty cells have no value.
+    pub fn sheets(&self) -> CellData {
+        let (value, format) = match self {
+            Cell::Empty => return CellData::default(),
+            Cell::Text(text) => (ExtendedValue::StringValue(text.clone()), None),
+            // a cell can only hold one `HYPERLINK` formula
+            Cell::Links(links) => (ExtendedValue::StringValue(link_texts(links)), None),
+            Cell::Bool(value) => (ExtendedValue::BoolValue(*value), None),
+            Cell::Number(number) => (ExtendedValue::NumberValue(*number), None),
+            Cell::Percent(rate) => (ExtendedValue::NumberValue(*rate), Some(CellFormat::percent())),
+            Cell::Currency(amount) => {
+                (ExtendedValue::NumberValue(*amount), Some(CellFormat::currency()))
+            }
+            Cell::Days(days) => (ExtendedValue::NumberValue(*days), Some(CellFormat::decimal(1))),
+            Cell::Link { text, url } => {
+                let quote = |text: &str| text.replace('"', "\"\"");
+                let formula = format!("=HYPERLINK(\"{}\", \"{}\")", quote(url), quote(text));
+                (ExtendedValue::FormulaValue(formula), None)
+            }
+        };
+        CellData { user_entered_value: Some(value), user_entered_format: format }
+    }
+}
+
+/// The texts of several links, separated by commas.
+fn link_texts(links: &[(String, String)]) -> String {
+    links.iter().map(|(text, _)| text.as_str()).collect::<Vec<_>>().join(", ")
+}
+
+impl Display for Cell {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.pad(&self.human())
+    }
+}
+
+/// A duration as a fractional number of days, which is how reports present
+/// durations.
+pub fn days(time: TimeDelta) -> f64 {
+    const SECONDS_PER_DAY: f64 = 86400.0;
+    time.num_seconds() as f64 / SECONDS_PER_DAY
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn cells_render_per_format() {
+        let rate = Cell::percent(Some(0.425));
+        assert_eq!(rate.human(), "42.50%");
+        assert_eq!(rate.csv(), "0.425");
+        let cell = rate.sheets();
+        assert!(
+            matches!(cell.user_entered_value, Some(ExtendedValue::NumberValue(n)) if n == 0.425)
+        );
+        assert_eq!(cell.user_entered_format, Some(CellFormat::percent()));
+
+        let na = Cell::percent(None);
+        assert_eq!(na.human(), "N/A");
+        assert_eq!(format!("{:>5}", na), "  N/A");
+        assert_eq!(na.csv(), "");
+        assert!(na.sheets().user_entered_value.is_none());
+
+        assert_eq!(Cell::cents(12345).human(), "$123.45");
+        assert_eq!(Cell::days(TimeDelta::hours(36)).human(), "1.5 days");
+        assert_eq!(Cell::Number(3.0).human(), "3");
+    }
+
+    fn sample_report() -> Report {
+        let mut report = Report::new("Sample");
+        let section = report.section("Stats Jane Doe");
+        section.table(["Conversion", "Rate"]).row([Cell::text("Appt"), Cell::percent(Some(0.5))]);
+        section.table(["Appts"]).titled("Counts").row([Cell::Number(12.0)]);
+        report
+    }
+
+    #[test]
+    fn renderers_lay_out_tables() {
+        let report = sample_report();
+        assert_eq!(report.sections[0].file_stem(), "stats-jane-doe");
+
+        let mut human = Vec::new();
+        human::Human.render(&report, &mut human).unwrap();
+        assert_eq!(
+            String::from_utf8(human).unwrap(),
+            "Sample\n======\n\nStats Jane Doe\n--------------\n\n\
+             Conversion    Rate\n----------  ------\nAppt        50.00%\n\n\
+             Counts:\nAppts\n-----\n   12\n"
+        );
+
+        let mut csv = Vec::new();
+        csv::Csv.render(&report, &mut csv).unwrap();
+        assert_eq!(String::from_utf8(csv).unwrap(), "Conversion,Rate\nAppt,0.5\nAppts\n12\n");
+    }
+
+    #[test]
+    fn human_shows_key_figures_and_totals() {
+        let mut report = Report::new("AR");
+        report.key_figure("Total Receivable", Cell::Currency(12.5));
+        report
+            .section("Collections")
+            .table(["Job", "Amount", "Days"])
+            .row([Cell::text("Smith"), Cell::Currency(12.5), Cell::Number(3.0)])
+            .totaled([Cell::text("Total"), Cell::Currency(12.5), Cell::Empty]);
+
+        let mut human = Vec::new();
+        human::Human.render(&report, &mut human).unwrap();
+        assert_eq!(
+            String::from_utf8(human).unwrap(),
+            "AR\n==\n\nTotal Receivable: $12.50\n\nCollections\n-----------\n\n\
+             Job    Amount  Days\n-----  ------  ----\nSmith  $12.50     3\n\
+             -----  ------  ----\nTotal  $12.50\n"
+        );
+    }
+
+    #[test]
+    fn csv_writes_only_the_flat_table() {
+        let mut report = sample_report();
+        let mut flat = Table::new(["Sales Rep", "Appts"]);
+        flat.row([Cell::text("Jane Doe"), Cell::Number(12.0)]);
+        report.flat = Some(flat);
+
+        let mut csv = Vec::new();
+        csv::Csv.render(&report, &mut csv).unwrap();
+        assert_eq!(String::from_utf8(csv).unwrap(), "Sales Rep,Appts\nJane Doe,12\n");
+    }
+
+    #[test]
+    fn only_splitting_formats_write_a_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        let report = sample_report();
+
+        let path = dir.path().join("x.md");
+        output_sections(&report, OutputFormat::Markdown, path.to_str(), false, SheetNickname::Kpi)
+            .unwrap();
+        assert!(path.is_file());
+
+        let path = dir.path().join("x");
+        output_sections(&report, OutputFormat::Csv, path.to_str(), false, SheetNickname::Kpi)
+            .unwrap();
+        assert!(path.join("stats-jane-doe.csv").is_file());
+    }
+
+    #[test]
+    fn json_formats_carry_records() {
+        let mut report = sample_report();
+        report.record(serde_json::json!({ "type": "stats", "subject": "Jane Doe" }));
+        report.record(crate::jobs::JobAnalysisError::OutOfOrderDates(Some(
+            crate::jobs::Milestone::ContractSigned,
+        )));
+        assert!(!json::Json.splits_sections());
+
+        let mut document = Vec::new();
+        json::Json.render(&report, &mut document).unwrap();
+        let document: serde_json::Value = serde_json::from_slice(&document).unwrap();
+        assert_eq!(
+            document,
+            serde_json::json!({
+                "schema_version": json::SCHEMA_VERSION,
+                "title": "Sample",
+                "records": [
+                    { "type": "stats", "subject": "Jane Doe" },
+                    { "kind": "out_of_order_dates", "milestone": "contract_signed" },
+                ],
+            })
+        );
+
+        let mut lines = Vec::new();
+        json::Ndjson.render(&report, &mut lines).unwrap();
+        let lines: Vec<serde_json::Value> = String::from_utf8(lines)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0]["type"], "report");
+        assert_eq!(lines[0]["schema_version"], json::SCHEMA_VERSION);
+        assert_eq!(lines[1..], report.records[..]);
+    }
+
+    #[test]
+    fn highlights_apply_to_numbers_in_their_rows() {
+        let mut report = Report::new("Sample");
+        let table = report.section("Rates").table(["Rate", "Count"]);
+        for (rate, count) in [(0.1, 1.0), (0.2, 2.0), (0.9, 0.0)] {
+            table.row([Cell::percent(Some(rate)), Cell::Number(count)]);
+        }
+        table.row([Cell::percent(None), Cell::Empty]);
+        table.highlights.push(Highlight {
+            column: 0,
+            rows: Some(1..4),
+            condition: Condition::Below(0.5),
+        });
+        let highlighted: Vec<_> = (0..4)
+            .flat_map(|row| (0..2).map(move |column| (row, column)))
+            .filter(|&(row, column)| table.is_highlighted(row, column))
+            .collect();
+        assert_eq!(highlighted, [(1, 0)]);
+    }
+}
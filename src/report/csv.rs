@@ -0,0 +1,29 @@
+use std::io::Write;
+
+use super::{Renderer, Report};
+
+/// Prints reports as CSV. A report with a flat table is printed as that one
+/// table; otherwise, each table is printed in turn, starting with its headers.
+pub struct Csv;
+
+impl Renderer for Csv {
+    fn extension(&self) -> &'static str {
+        "csv"
+    }
+
+    fn render(&self, report: &Report, out: &mut dyn Write) -> anyhow::Result<()> {
+        let tables: Vec<_> = match &report.flat {
+            Some(flat) => vec![flat],
+            None => report.sections.iter().flat_map(|section| &section.tables).collect(),
+        };
+        let mut writer = ::csv::WriterBuilder::new().flexible(true).from_writer(out);
+        for table in tables {
+            writer.write_record(&table.headers)?;
+            for row in &table.rows {
+                writer.write_record(row.iter().map(|cell| cell.csv()))?;
+            }
+        }
+        writer.flush()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,391 @@
+use std::{fmt::Write as _, io::Write};
+
+use super::{Cell, Chart, ChartKind, Renderer, Report, Table};
+
+/// The colors of the bars and slices of charts.
+const PALETTE: [&str; 8] =
+    ["#4e79a7", "#f28e2b", "#e15759", "#76b7b2", "#59a14f", "#edc948", "#b07aa1", "#9c755f"];
+
+const STYLE: &str = r#"
+body {
+  font-family: -apple-system, "Segoe UI", Helvetica, Arial, sans-serif;
+  margin: 2em;
+  color: #222;
+}
+h1 { margin-bottom: 0.2em; }
+nav ul { list-style: none; padding: 0; display: flex; flex-wrap: wrap; gap: 0.4em 1.2em; }
+.figures { display: flex; flex-wrap: wrap; gap: 1em; margin: 1.5em 0; }
+.figure { border: 1px solid #ddd; border-radius: 6px; padding: 0.8em 1.2em; min-width: 9em; }
+.figure .value { font-size: 1.6em; font-weight: bold; }
+.figure .name { color: #666; }
+section { margin-top: 2.5em; }
+table { border-collapse: collapse; margin: 0.5em 0 1.5em; }
+th, td { border: 1px solid #ddd; padding: 0.3em 0.6em; }
+th { background: #d9d9d9; cursor: pointer; user-select: none; }
+th[data-order="asc"]::after { content: " \25B2"; }
+th[data-order="desc"]::after { content: " \25BC"; }
+tbody tr:nth-child(even) { background: #f2f2f2; }
+td.number { text-align: right; }
+td.highlight { background: #f4cccc; }
+svg text { font-size: 12px; }
+svg .title { font-size: 14px; font-weight: bold; }
+"#;
+
+/// Sorts a table by a column when its header is clicked, by number if the
+/// column's cells have values, and by text otherwise.
+const SCRIPT: &str = r#"
+function compareCells(a, b) {
+  var x = a && a.dataset.value, y = b && b.dataset.value;
+  if (x !== undefined || y !== undefined) {
+    if (x === undefined) return -1;
+    if (y === undefined) return 1;
+    return Number(x) - Number(y);
+  }
+  return (a ? a.textContent : "").localeCompare(b ? b.textContent : "");
+}
+document.querySelectorAll("table.sortable th").forEach(function (th) {
+  th.addEventListener("click", function () {
+    var table = th.closest("table"), body = table.tBodies[0], column = th.cellIndex;
+    var ascending = th.dataset.order !== "asc";
+    table.querySelectorAll("th").forEach(function (other) { delete other.dataset.order; });
+    th.dataset.order = ascending ? "asc" : "desc";
+    Array.from(body.rows)
+      .sort(function (a, b) {
+        var order = compareCells(a.cells[column], b.cells[column]);
+        return ascending ? order : -order;
+      })
+      .forEach(function (row) { body.appendChild(row); });
+  });
+});
+"#;
+
+/// Writes reports as a single static web page, with a dashboard of the key
+/// figures, a sortable table for each table of the report, and its charts as
+/// inline SVG. The page doesn't load anything, so it can be sent by email.
+pub struct Html;
+
+impl Renderer for Html {
+    fn extension(&self) -> &'static str {
+        "html"
+    }
+
+    fn render(&self, report: &Report, out: &mut dyn Write) -> anyhow::Result<()> {
+        out.write_all(page(report).as_bytes())?;
+        Ok(())
+    }
+
+    /// The whole report is one page.
+    fn splits_sections(&self) -> bool {
+        false
+    }
+}
+
+fn page(report: &Report) -> String {
+    let mut html = String::new();
+    let title = escape(&report.title);
+    writeln!(html, "<!DOCTYPE html>").unwrap();
+    writeln!(html, "<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">").unwrap();
+    writeln!(html, "<meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">")
+        .unwrap();
+    writeln!(html, "<title>{}</title>\n<style>{}</style>\n</head>\n<body>", title, STYLE).unwrap();
+    writeln!(html, "<h1>{}</h1>", title).unwrap();
+
+    if !report.key_figures.is_empty() {
+        writeln!(html, "<div class=\"figures\">").unwrap();
+        for figure in &report.key_figures {
+            writeln!(
+                html,
+                "<div class=\"figure\"><div class=\"value\">{}</div>\
+                 <div class=\"name\">{}</div></div>",
+                escape(&figure.value.human()),
+                escape(&figure.name)
+            )
+            .unwrap();
+        }
+        writeln!(html, "</div>").unwrap();
+    }
+    if report.sections.len() > 1 {
+        writeln!(html, "<nav><ul>").unwrap();
+        for section in &report.sections {
+            let name = escape(&section.name);
+            writeln!(html, "<li><a href=\"#{}\">{}</a></li>", section.file_stem(), name).unwrap();
+        }
+        writeln!(html, "</ul></nav>").unwrap();
+    }
+
+    for section in &report.sections {
+        writeln!(html, "<section id=\"{}\">", section.file_stem()).unwrap();
+        writeln!(html, "<h2>{}</h2>", escape(&section.name)).unwrap();
+        for table in &section.tables {
+            if let Some(title) = &table.title {
+                writeln!(html, "<h3>{}</h3>", escape(title)).unwrap();
+            }
+            html.push_str(&table_html(table));
+            for chart in &table.charts {
+                writeln!(html, "<figure>{}</figure>", chart_svg(table, chart)).unwrap();
+            }
+        }
+        writeln!(html, "</section>").unwrap();
+    }
+    writeln!(html, "<script>{}</script>\n</body>\n</html>", SCRIPT).unwrap();
+    html
+}
+
+fn table_html(table: &Table) -> String {
+    let mut html = String::from("<table class=\"sortable\">\n<thead><tr>");
+    for header in &table.headers {
+        write!(html, "<th>{}</th>", escape(header)).unwrap();
+    }
+    html.push_str("</tr></thead>\n<tbody>\n");
+    for (row, cells) in table.rows.iter().enumerate() {
+        html.push_str("<tr>");
+        for (column, cell) in cells.iter().enumerate() {
+            let mut classes = Vec::new();
+            if cell.is_number() {
+                classes.push("number");
+            }
+            if table.is_highlighted(row, column) {
+                classes.push("highlight");
+            }
+            html.push_str("<td");
+            if !classes.is_empty() {
+                write!(html, " class=\"{}\"", classes.join(" ")).unwrap();
+            }
+            if let Some(number) = cell.value() {
+                write!(html, " data-value=\"{}\"", number).unwrap();
+            }
+            html.push('>');
+            match cell {
+                Cell::Link { text, url } => {
+                    write!(html, "<a href=\"{}\">{}</a>", escape(url), escape(text)).unwrap()
+                }
+                Cell::Links(links) => {
+                    let links: Vec<_> = links
+                        .iter()
+                        .map(|(text, url)| {
+                            format!("<a href=\"{}\">{}</a>", escape(url), escape(text))
+                        })
+                        .collect();
+                    html.push_str(&links.join(", "));
+                }
+                _ => html.push_str(&escape(&cell.human())),
+            }
+            html.push_str("</td>");
+        }
+        html.push_str("</tr>\n");
+    }
+    if table.rows.is_empty() {
+        writeln!(html, "<tr><td colspan=\"{}\">(none)</td></tr>", table.headers.len().max(1))
+            .unwrap();
+    }
+    html.push_str("</tbody>\n</table>\n");
+    html
+}
+
+/// A chart of a table as an SVG image. Negative and missing numbers are
+/// charted as zero.
+fn chart_svg(table: &Table, chart: &Chart) -> String {
+    let rows = match &chart.rows {
+        Some(rows) => &table.rows[rows.start.min(table.rows.len())..rows.end.min(table.rows.len())],
+        None => &table.rows[..],
+    };
+    let bars: Vec<_> = rows
+        .iter()
+        .map(|row| {
+            let cell = |column: usize| row.get(column).unwrap_or(&Cell::Empty);
+            let value = cell(chart.values).value().unwrap_or(0.0).max(0.0);
+            (escape(&cell(chart.labels).human()), value, escape(&cell(chart.values).human()))
+        })
+        .collect();
+    let max = bars.iter().map(|(_, value, _)| *value).fold(0.0, f64::max);
+    let scale = |value: f64, length: f64| if max > 0.0 { value / max * length } else { 0.0 };
+    let title = escape(&chart.title);
+
+    let mut svg = String::new();
+    match chart.kind {
+        ChartKind::Bar => {
+            const LABEL_WIDTH: f64 = 200.0;
+            const BAR_LENGTH: f64 = 300.0;
+            const BAR_HEIGHT: f64 = 24.0;
+            let (width, height) =
+                (LABEL_WIDTH + BAR_LENGTH + 80.0, 30.0 + bars.len() as f64 * BAR_HEIGHT);
+            open_svg(&mut svg, width, height, &title);
+            for (i, (label, value, text)) in bars.iter().enumerate() {
+                let y = 30.0 + i as f64 * BAR_HEIGHT;
+                let length = scale(*value, BAR_LENGTH);
+                write!(
+                    svg,
+                    "<text x=\"{:.1}\" y=\"{:.1}\" text-anchor=\"end\">{}</text>\
+                     <rect x=\"{:.1}\" y=\"{:.1}\" width=\"{:.1}\" height=\"{:.1}\" fill=\"{}\"/>\
+                     <text x=\"{:.1}\" y=\"{:.1}\">{}</text>",
+                    LABEL_WIDTH - 6.0,
+                    y + 16.0,
+                    label,
+                    LABEL_WIDTH,
+                    y + 4.0,
+                    length,
+                    BAR_HEIGHT - 8.0,
+                    PALETTE[0],
+                    LABEL_WIDTH + length + 4.0,
+                    y + 16.0,
+                    text
+                )
+                .unwrap();
+            }
+        }
+        ChartKind::Column => {
+            const COLUMN_WIDTH: f64 = 90.0;
+            const PLOT_HEIGHT: f64 = 180.0;
+            let width = (bars.len() as f64 * COLUMN_WIDTH).max(300.0);
+            open_svg(&mut svg, width, PLOT_HEIGHT + 80.0, &title);
+            for (i, (label, value, text)) in bars.iter().enumerate() {
+                let x = i as f64 * COLUMN_WIDTH;
+                let length = scale(*value, PLOT_HEIGHT);
+                let bottom = 50.0 + PLOT_HEIGHT;
+                write!(
+                    svg,
+                    "<rect x=\"{:.1}\" y=\"{:.1}\" width=\"{:.1}\" height=\"{:.1}\" fill=\"{}\"/>\
+                     <text x=\"{:.1}\" y=\"{:.1}\" text-anchor=\"middle\">{}</text>\
+                     <text x=\"{:.1}\" y=\"{:.1}\" text-anchor=\"middle\">{}</text>",
+                    x + 10.0,
+                    bottom - length,
+                    COLUMN_WIDTH - 20.0,
+                    length,
+                    PALETTE[0],
+                    x + COLUMN_WIDTH / 2.0,
+                    bottom - length - 4.0,
+                    text,
+                    x + COLUMN_WIDTH / 2.0,
+                    bottom + 16.0,
+                    label
+                )
+                .unwrap();
+            }
+        }
+        ChartKind::Pie => {
+            const RADIUS: f64 = 90.0;
+            const LEGEND_ROW: f64 = 20.0;
+            let (cx, cy) = (20.0 + RADIUS, 40.0 + RADIUS);
+            let height = (60.0 + 2.0 * RADIUS).max(50.0 + bars.len() as f64 * LEGEND_ROW);
+            open_svg(&mut svg, 2.0 * RADIUS + 360.0, height, &title);
+            let total: f64 = bars.iter().map(|(_, value, _)| value).sum();
+            let mut angle = 0.0;
+            for (i, (label, value, text)) in bars.iter().enumerate() {
+                let color = PALETTE[i % PALETTE.len()];
+                let fraction = if total > 0.0 { value / total } else { 0.0 };
+                if fraction >= 0.9999 {
+                    write!(
+                        svg,
+                        "<circle cx=\"{cx}\" cy=\"{cy}\" r=\"{RADIUS}\" fill=\"{color}\"/>"
+                    )
+                    .unwrap();
+                } else if fraction > 0.0 {
+                    let point = |angle: f64| {
+                        let radians = angle * std::f64::consts::TAU;
+                        (cx + RADIUS * radians.sin(), cy - RADIUS * radians.cos())
+                    };
+                    let (start, end) = (point(angle), point(angle + fraction));
+                    write!(
+                        svg,
+                        "<path d=\"M{cx:.1},{cy:.1} L{:.1},{:.1} \
+                         A{RADIUS},{RADIUS} 0 {} 1 {:.1},{:.1} Z\" fill=\"{color}\"/>",
+                        start.0,
+                        start.1,
+                        u8::from(fraction > 0.5),
+                        end.0,
+                        end.1
+                    )
+                    .unwrap();
+                }
+                angle += fraction;
+                let y = 40.0 + i as f64 * LEGEND_ROW;
+                let x = cx + RADIUS + 30.0;
+                write!(
+                    svg,
+                    "<rect x=\"{:.1}\" y=\"{:.1}\" width=\"12\" height=\"12\" fill=\"{color}\"/>\
+                     <text x=\"{:.1}\" y=\"{:.1}\">{}: {}</text>",
+                    x,
+                    y,
+                    x + 18.0,
+                    y + 11.0,
+                    label,
+                    text
+                )
+                .unwrap();
+            }
+        }
+    }
+    svg.push_str("</svg>");
+    svg
+}
+
+fn open_svg(svg: &mut String, width: f64, height: f64, title: &str) {
+    write!(
+        svg,
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{width:.0}\" height=\"{height:.0}\" \
+         viewBox=\"0 0 {width:.0} {height:.0}\" role=\"img\"><title>{title}</title>\
+         <text class=\"title\" x=\"0\" y=\"18\">{title}</text>"
+    )
+    .unwrap();
+}
+
+/// Escapes text for HTML content and attribute values.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::report::{Condition, Highlight};
+
+    #[test]
+    fn page_is_self_contained() {
+        let mut report = Report::new("AR <Report>");
+        report.key_figure("Total", Cell::cents(150));
+        let table = report.section("Summary").table(["Status", "Amount"]);
+        table.row([Cell::link("#1", "https://example.com/?a=1&b=2"), Cell::cents(100)]);
+        table.row([Cell::text("Collections"), Cell::cents(50)]);
+        report.section("Stats").table(["Conversion", "Jobs"]).row([
+            Cell::text("Appt to Contract"),
+            Cell::links([("1001", "https://example.com/1"), ("1002", "https://example.com/2")]),
+        ]);
+        let table = &mut report.sections[0].tables[0];
+        table.highlights.push(Highlight {
+            column: 1,
+            rows: None,
+            condition: Condition::Above(0.75),
+        });
+        table.charts.push(Chart {
+            title: "By Status".to_owned(),
+            kind: ChartKind::Pie,
+            labels: 0,
+            values: 1,
+            rows: None,
+        });
+
+        let html = page(&report);
+        assert!(html.contains("<h1>AR &lt;Report&gt;</h1>"));
+        assert!(html.contains("<div class=\"value\">$1.50</div>"));
+        assert!(html.contains("<a href=\"https://example.com/?a=1&amp;b=2\">#1</a>"));
+        assert!(html.contains(
+            "<td><a href=\"https://example.com/1\">1001</a>, \
+             <a href=\"https://example.com/2\">1002</a></td>"
+        ));
+        assert!(html.contains("<td class=\"number highlight\" data-value=\"1\">$1.00</td>"));
+        assert!(html.contains("<td class=\"number\" data-value=\"0.5\">$0.50</td>"));
+        assert_eq!(html.matches("<path").count(), 2);
+        assert!(!html.contains("src="));
+    }
+}
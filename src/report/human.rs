@@ -0,0 +1,121 @@
+use std::io::Write;
+
+use super::{Cell, Renderer, Report, Table};
+
+/// Prints reports as plain text, with the columns of each table aligned. The
+/// key figures are listed under the title.
+pub struct Human;
+
+impl Renderer for Human {
+    fn extension(&self) -> &'static str {
+        "txt"
+    }
+
+    fn render(&self, report: &Report, out: &mut dyn Write) -> anyhow::Result<()> {
+        writeln!(out, "{}", report.title)?;
+        writeln!(out, "{}", "=".repeat(report.title.chars().count()))?;
+        if !report.key_figures.is_empty() {
+            writeln!(out)?;
+            for figure in &report.key_figures {
+                writeln!(out, "{}: {}", figure.name, figure.value.human())?;
+            }
+        }
+        for section in &report.sections {
+            writeln!(out)?;
+            writeln!(out, "{}", section.name)?;
+            writeln!(out, "{}", "-".repeat(section.name.chars().count()))?;
+            for table in &section.tables {
+                writeln!(out)?;
+                if let Some(title) = &table.title {
+                    writeln!(out, "{}:", title)?;
+                }
+                write_table(table, out)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Writes the table with its columns aligned, followed by its total row, if
+/// any, below a line of dashes.
+fn write_table(table: &Table, out: &mut dyn Write) -> std::io::Result<()> {
+    if table.rows.is_empty() {
+        return writeln!(out, "(none)");
+    }
+
+    let rows: Vec<Vec<(String, bool)>> = table
+        .rows
+        .iter()
+        .map(|row| row.iter().map(|cell| (cell.human(), cell.is_number())).collect())
+        .collect();
+    // the empty cells of the total row are left blank rather than "N/A"
+    let total: Option<Vec<(String, bool)>> = table.total.as_ref().map(|total| {
+        total
+            .iter()
+            .map(|cell| match cell {
+                Cell::Empty => (String::new(), false),
+                _ => (cell.human(), cell.is_number()),
+            })
+            .collect()
+    });
+    let num_columns =
+        rows.iter().chain(&total).map(Vec::len).chain([table.headers.len()]).max().unwrap_or(0);
+    let mut widths = vec![0; num_columns];
+    let all_cells = table.headers.iter().map(String::as_str).enumerate().chain(
+        rows.iter()
+            .chain(&total)
+            .flat_map(|row| row.iter().map(|(text, _)| text.as_str()).enumerate()),
+    );
+    for (column, text) in all_cells {
+        widths[column] = widths[column].max(text.chars().count());
+    }
+
+    // the headers of columns of numbers are aligned with the numbers
+    let numeric: Vec<bool> = (0..num_columns)
+        .map(|column| {
+            rows.iter().all(|row| row.get(column).is_none_or(|(_, is_number)| *is_number))
+        })
+        .collect();
+    let dashes: Vec<String> = widths.iter().map(|width| "-".repeat(*width)).collect();
+    write_line(
+        out,
+        &widths,
+        table.headers.iter().zip(&numeric).map(|(header, numeric)| (header.as_str(), *numeric)),
+    )?;
+    write_line(out, &widths, dashes.iter().map(|dashes| (dashes.as_str(), false)))?;
+    for row in &rows {
+        write_line(out, &widths, row.iter().map(|(text, is_number)| (text.as_str(), *is_number)))?;
+    }
+    if let Some(total) = &total {
+        write_line(out, &widths, dashes.iter().map(|dashes| (dashes.as_str(), false)))?;
+        write_line(
+            out,
+            &widths,
+            total.iter().map(|(text, is_number)| (text.as_str(), *is_number)),
+        )?;
+    }
+    Ok(())
+}
+
+/// Writes a line of cells padded to the widths of their columns. Numbers are
+/// aligned to the right.
+fn write_line<'a>(
+    out: &mut dyn Write,
+    widths: &[usize],
+    cells: impl Iterator<Item = (&'a str, bool)>,
+) -> std::io::Result<()> {
+    let line = cells
+        .zip(widths)
+        .map(
+            |((text, is_number), width)| {
+                if is_number {
+                    format!("{:>width$}", text)
+                } else {
+                    format!("{:width$}", text)
+                }
+            },
+        )
+        .collect::<Vec<_>>()
+        .join("  ");
+    writeln!(out, "{}", line.trim_end())
+}
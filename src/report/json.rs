@@ -0,0 +1,86 @@
+use std::{io::Write, rc::Rc};
+
+use serde::{Serialize, Serializer};
+
+use crate::jobs::{AnalyzedJob, TimeDelta};
+
+use super::{Renderer, Report};
+
+/// The version of the schema of the JSON formats. It is incremented whenever a
+/// record changes in a way that could break a consumer, e.g. when a field is
+/// removed, renamed, or changes its type. Adding fields or types of records
+/// doesn't change the version.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// Prints the records of reports as a JSON document.
+pub struct Json;
+
+/// Prints the records of reports as newline-delimited JSON. The first line is
+/// a header with the schema version, so that records can be streamed.
+pub struct Ndjson;
+
+#[derive(Serialize)]
+struct Document<'a> {
+    schema_version: u32,
+    title: &'a str,
+    records: &'a [serde_json::Value],
+}
+
+#[derive(Serialize)]
+#[serde(tag = "type", rename = "report")]
+struct Header<'a> {
+    schema_version: u32,
+    title: &'a str,
+}
+
+impl Renderer for Json {
+    fn extension(&self) -> &'static str {
+        "json"
+    }
+
+    fn render(&self, report: &Report, out: &mut dyn Write) -> anyhow::Result<()> {
+        let document = Document {
+            schema_version: SCHEMA_VERSION,
+            title: &report.title,
+            records: &report.records,
+        };
+        serde_json::to_writer_pretty(&mut *out, &document)?;
+        writeln!(out)?;
+        Ok(())
+    }
+
+    fn splits_sections(&self) -> bool {
+        false
+    }
+}
+
+impl Renderer for Ndjson {
+    fn extension(&self) -> &'static str {
+        "ndjson"
+    }
+
+    fn render(&self, report: &Report, out: &mut dyn Write) -> anyhow::Result<()> {
+        let header = Header { schema_version: SCHEMA_VERSION, title: &report.title };
+        serde_json::to_writer(&mut *out, &header)?;
+        writeln!(out)?;
+        for record in &report.records {
+            serde_json::to_writer(&mut *out, record)?;
+            writeln!(out)?;
+        }
+        Ok(())
+    }
+
+    fn splits_sections(&self) -> bool {
+        false
+    }
+}
+
+/// Serializes a duration as a number of days, for `#[serde(serialize_with)]`.
+pub fn days<S: Serializer>(time: &TimeDelta, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_f64(super::days(*time))
+}
+
+/// Serializes jobs as their JobNimbus IDs, for `#[serde(serialize_with)]`.
+pub fn job_ids<S: Serializer>(jobs: &[Rc<AnalyzedJob>], serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_seq(jobs.iter().map(|job| &job.job.jnid))
+}
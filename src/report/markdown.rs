@@ -0,0 +1,163 @@
+use std::io::Write;
+
+use super::{Cell, Renderer, Report, Table};
+
+/// Prints reports as GitHub-flavored Markdown, for pasting into a wiki or a
+/// chat. Each section is a heading, each table is a Markdown table, and tables
+/// that read better as lists are bulleted lists.
+pub struct Markdown;
+
+impl Renderer for Markdown {
+    fn extension(&self) -> &'static str {
+        "md"
+    }
+
+    fn render(&self, report: &Report, out: &mut dyn Write) -> anyhow::Result<()> {
+        writeln!(out, "# {}", escape(&report.title))?;
+        if !report.key_figures.is_empty() {
+            writeln!(out)?;
+            for figure in &report.key_figures {
+                writeln!(out, "- **{}**: {}", escape(&figure.name), inline(&figure.value))?;
+            }
+        }
+        for section in &report.sections {
+            writeln!(out)?;
+            writeln!(out, "## {}", escape(&section.name))?;
+            for table in &section.tables {
+                writeln!(out)?;
+                if let Some(title) = &table.title {
+                    writeln!(out, "### {}", escape(title))?;
+                    writeln!(out)?;
+                }
+                if table.rows.is_empty() {
+                    writeln!(out, "(none)")?;
+                } else if table.list {
+                    write_list(table, out)?;
+                } else {
+                    write_table(table, out)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// The whole report is one document, so that it can be pasted at once.
+    fn splits_sections(&self) -> bool {
+        false
+    }
+}
+
+fn write_table(table: &Table, out: &mut dyn Write) -> std::io::Result<()> {
+    let row = |cells: Vec<String>| format!("| {} |", cells.join(" | "));
+    writeln!(out, "{}", row(table.headers.iter().map(|header| escape(header)).collect()))?;
+    // columns of numbers are aligned to the right
+    let alignments = (0..table.headers.len())
+        .map(|column| {
+            let numeric =
+                table.rows.iter().all(|row| row.get(column).is_none_or(|cell| cell.is_number()));
+            if numeric { "---:" } else { "---" }.to_owned()
+        })
+        .collect();
+    writeln!(out, "{}", row(alignments))?;
+    for cells in &table.rows {
+        writeln!(out, "{}", row(cells.iter().map(inline).collect()))?;
+    }
+    if let Some(total) = &table.total {
+        let bold = |cell: &Cell| match cell {
+            Cell::Empty => String::new(),
+            _ => format!("**{}**", inline(cell)),
+        };
+        writeln!(out, "{}", row(total.iter().map(bold).collect()))?;
+    }
+    Ok(())
+}
+
+/// Writes each row as an item of a bulleted list, with its cells separated by
+/// dashes. Empty cells are left out.
+fn write_list(table: &Table, out: &mut dyn Write) -> std::io::Result<()> {
+    for cells in &table.rows {
+        let cells: Vec<String> =
+            cells.iter().filter(|cell| **cell != Cell::Empty).map(inline).collect();
+        writeln!(out, "- {}", cells.join(" — "))?;
+    }
+    Ok(())
+}
+
+/// The cell as inline Markdown: its human-readable text, or a link.
+fn inline(cell: &Cell) -> String {
+    match cell {
+        Cell::Link { text, url } => link(text, url),
+        Cell::Links(links) => {
+            links.iter().map(|(text, url)| link(text, url)).collect::<Vec<_>>().join(", ")
+        }
+        _ => escape(&cell.human()),
+    }
+}
+
+fn link(text: &str, url: &str) -> String {
+    format!("[{}](<{}>)", escape(text), url.replace('>', "%3E"))
+}
+
+/// Escapes the characters that Markdown would treat as formatting, and keeps
+/// text on one line so that it stays in its table cell.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' | '`' | '*' | '_' | '[' | ']' | '<' | '>' | '|' | '#' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            '\n' | '\r' => escaped.push(' '),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn tables_and_lists_are_markdown() {
+        let mut report = Report::new("KPI Report");
+        report.key_figure("Red Flags", Cell::Number(1.0));
+        let section = report.section("Stats [Global]");
+        section.table(["Conversion", "Rate"]).row([Cell::text("Appt"), Cell::percent(Some(0.5))]);
+        section
+            .table(["Job", "Amount"])
+            .row([Cell::text("Smith"), Cell::Currency(12.5)])
+            .totaled([Cell::text("Total"), Cell::Currency(12.5)]);
+        report.section("Red Flags").table(["Sales Rep", "Job Number", "Error"]).listed().row([
+            Cell::text("Jane Doe"),
+            Cell::link("1001", "https://app.jobnimbus.com/job/abc"),
+            Cell::text("missing a_date | b"),
+        ]);
+
+        let mut out = Vec::new();
+        Markdown.render(&report, &mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "# KPI Report\n\
+             \n\
+             - **Red Flags**: 1\n\
+             \n\
+             ## Stats \\[Global\\]\n\
+             \n\
+             | Conversion | Rate |\n\
+             | --- | ---: |\n\
+             | Appt | 50.00% |\n\
+             \n\
+             | Job | Amount |\n\
+             | --- | ---: |\n\
+             | Smith | $12.50 |\n\
+             | **Total** | **$12.50** |\n\
+             \n\
+             ## Red Flags\n\
+             \n\
+             - Jane Doe — [1001](<https://app.jobnimbus.com/job/abc>) — \
+             missing a\\_date \\| b\n"
+        );
+    }
+}
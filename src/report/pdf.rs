@@ -0,0 +1,400 @@
+use std::io::Write;
+
+use pdf_writer::{
+    types::{ActionType, AnnotationType},
+    Content, Finish, Name, Pdf as PdfWriter, Rect, Ref, Str, TextStr,
+};
+
+use super::{Cell, Renderer, Report, Table};
+
+/// US Letter, in points.
+const PAGE_WIDTH: f32 = 612.0;
+const PAGE_HEIGHT: f32 = 792.0;
+const MARGIN: f32 = 54.0;
+/// The space at the bottom of each page for its page number.
+const FOOTER_HEIGHT: f32 = 24.0;
+
+const FONT_SIZE: f32 = 9.0;
+const ROW_HEIGHT: f32 = 13.0;
+/// The width of every character of Courier, as a fraction of the font size.
+/// Tables are set in Courier so that their columns can be measured without
+/// embedding a font.
+const COURIER_WIDTH: f32 = 0.6;
+/// The space between the columns of a table, in characters.
+const COLUMN_GAP: usize = 2;
+/// The narrowest that a column gets when a table is too wide for the page.
+const MIN_COLUMN_WIDTH: usize = 6;
+
+/// Writes reports as printable PDF documents: a cover page with the title and
+/// the contents, a page with the key figures, then each section starting on a
+/// new page, with its tables broken across pages as needed. Charts are left
+/// out.
+pub struct Pdf;
+
+impl Renderer for Pdf {
+    fn extension(&self) -> &'static str {
+        "pdf"
+    }
+
+    fn render(&self, report: &Report, out: &mut dyn Write) -> anyhow::Result<()> {
+        out.write_all(&document(report))?;
+        Ok(())
+    }
+
+    /// The whole report is one document.
+    fn splits_sections(&self) -> bool {
+        false
+    }
+}
+
+/// The fonts used in documents. They are among the standard fonts of PDF
+/// readers, so they aren't embedded.
+#[derive(Debug, Clone, Copy)]
+enum Font {
+    Regular,
+    Bold,
+    Mono,
+    MonoBold,
+}
+
+impl Font {
+    const ALL: [Font; 4] = [Font::Regular, Font::Bold, Font::Mono, Font::MonoBold];
+
+    /// The name of the font in the resources of pages.
+    fn resource_name(self) -> Name<'static> {
+        match self {
+            Font::Regular => Name(b"F1"),
+            Font::Bold => Name(b"F2"),
+            Font::Mono => Name(b"F3"),
+            Font::MonoBold => Name(b"F4"),
+        }
+    }
+
+    fn base_font(self) -> Name<'static> {
+        match self {
+            Font::Regular => Name(b"Helvetica"),
+            Font::Bold => Name(b"Helvetica-Bold"),
+            Font::Mono => Name(b"Courier"),
+            Font::MonoBold => Name(b"Courier-Bold"),
+        }
+    }
+}
+
+struct Page {
+    content: Content,
+    /// The areas of the page that link to web pages.
+    links: Vec<(Rect, String)>,
+}
+
+/// Lays out a document from the top of each page down.
+struct Layout {
+    pages: Vec<Page>,
+    /// The baseline of the next line on the current page.
+    y: f32,
+}
+
+impl Layout {
+    fn new() -> Self {
+        Layout { pages: Vec::new(), y: 0.0 }
+    }
+
+    fn new_page(&mut self) {
+        self.pages.push(Page { content: Content::new(), links: Vec::new() });
+        self.y = PAGE_HEIGHT - MARGIN;
+    }
+
+    fn page(&mut self) -> &mut Page {
+        if self.pages.is_empty() {
+            self.new_page();
+        }
+        self.pages.last_mut().expect("there is a page")
+    }
+
+    /// Starts a new page unless `height` more fits on the current one.
+    fn reserve(&mut self, height: f32) {
+        if self.pages.is_empty() || self.y - height < MARGIN + FOOTER_HEIGHT {
+            self.new_page();
+        }
+    }
+
+    /// Writes a line of text at the current baseline, then moves down a line.
+    fn line(&mut self, font: Font, size: f32, text: &str) {
+        self.reserve(size * 1.4);
+        let y = self.y;
+        show(&mut self.page().content, font, size, MARGIN, y, text);
+        self.y -= size * 1.4;
+    }
+
+    fn space(&mut self, height: f32) {
+        self.y -= height;
+    }
+
+    fn table(&mut self, title: Option<&str>, table: &Table) {
+        let max_chars = ((PAGE_WIDTH - 2.0 * MARGIN) / (FONT_SIZE * COURIER_WIDTH)) as usize;
+        let widths = column_widths(table, max_chars);
+        let char_width = FONT_SIZE * COURIER_WIDTH;
+        let column_x = |column: usize| {
+            MARGIN
+                + (widths[..column].iter().sum::<usize>() + column * COLUMN_GAP) as f32 * char_width
+        };
+        let table_width = column_x(widths.len()) - COLUMN_GAP as f32 * char_width;
+
+        // keep the title and header with at least the first row
+        self.reserve(ROW_HEIGHT * 3.0 + if title.is_some() { 16.0 } else { 0.0 });
+        if let Some(title) = title {
+            self.line(Font::Bold, 11.0, title);
+        }
+        let header = |layout: &mut Layout| {
+            let y = layout.y;
+            let content = &mut layout.page().content;
+            fill(content, (0.85, 0.85, 0.85), MARGIN - 2.0, y - 3.5, table_width + 4.0, ROW_HEIGHT);
+            for (column, header) in table.headers.iter().enumerate() {
+                let text = truncate(header, widths[column]);
+                show(content, Font::MonoBold, FONT_SIZE, column_x(column), y, &text);
+            }
+            layout.y -= ROW_HEIGHT;
+        };
+        header(self);
+        if table.rows.is_empty() {
+            let y = self.y;
+            show(&mut self.page().content, Font::Mono, FONT_SIZE, MARGIN, y, "(none)");
+            self.y -= ROW_HEIGHT;
+        }
+        for (row, cells) in table.rows.iter().enumerate() {
+            if self.y - ROW_HEIGHT < MARGIN + FOOTER_HEIGHT {
+                self.new_page();
+                header(self);
+            }
+            let y = self.y;
+            let page = self.page();
+            if row % 2 == 1 {
+                let gray = (0.95, 0.95, 0.95);
+                fill(&mut page.content, gray, MARGIN - 2.0, y - 3.5, table_width + 4.0, ROW_HEIGHT);
+            }
+            for (column, cell) in cells.iter().enumerate().take(widths.len()) {
+                let width = widths[column];
+                let text = truncate(&cell.human(), width);
+                let mut x = column_x(column);
+                if table.is_highlighted(row, column) {
+                    let cell_width = width as f32 * char_width;
+                    let light_red = (0.96, 0.8, 0.8);
+                    fill(
+                        &mut page.content,
+                        light_red,
+                        x - 2.0,
+                        y - 3.5,
+                        cell_width + 4.0,
+                        ROW_HEIGHT,
+                    );
+                }
+                if cell.is_number() {
+                    x += (width - text.chars().count()) as f32 * char_width;
+                }
+                show(&mut page.content, Font::Mono, FONT_SIZE, x, y, &text);
+                if let Cell::Link { url, .. } = cell {
+                    let text_width = text.chars().count() as f32 * char_width;
+                    let area = Rect::new(x, y - 3.5, x + text_width, y - 3.5 + ROW_HEIGHT);
+                    page.links.push((area, url.clone()));
+                }
+            }
+            self.y -= ROW_HEIGHT;
+        }
+        self.space(ROW_HEIGHT);
+    }
+}
+
+fn document(report: &Report) -> Vec<u8> {
+    let mut layout = Layout::new();
+
+    // cover page
+    layout.new_page();
+    layout.space(120.0);
+    layout.line(Font::Bold, 20.0, &report.title);
+    layout.space(24.0);
+    if report.sections.len() > 1 {
+        layout.line(Font::Bold, 12.0, "Contents");
+        for section in &report.sections {
+            layout.line(Font::Regular, 11.0, &section.name);
+        }
+    }
+
+    if !report.key_figures.is_empty() {
+        layout.new_page();
+        layout.line(Font::Bold, 16.0, "Summary");
+        layout.space(8.0);
+        let mut table = Table::new(["Key Figure", "Value"]);
+        for figure in &report.key_figures {
+            table.row([Cell::text(figure.name.clone()), figure.value.clone()]);
+        }
+        layout.table(None, &table);
+    }
+
+    for section in &report.sections {
+        layout.new_page();
+        layout.line(Font::Bold, 16.0, &section.name);
+        layout.space(8.0);
+        for table in &section.tables {
+            layout.table(table.title.as_deref(), table);
+        }
+    }
+
+    write_pdf(&report.title, layout.pages)
+}
+
+fn write_pdf(title: &str, pages: Vec<Page>) -> Vec<u8> {
+    let catalog_id = Ref::new(1);
+    let page_tree_id = Ref::new(2);
+    let info_id = Ref::new(3);
+    let font_ids = [Ref::new(4), Ref::new(5), Ref::new(6), Ref::new(7)];
+    // each page has a page object and a content stream, then its links
+    let mut next_id = Ref::new(8);
+    let mut alloc = || next_id.bump();
+
+    let mut pdf = PdfWriter::new();
+    pdf.catalog(catalog_id).pages(page_tree_id);
+    pdf.document_info(info_id).title(TextStr(title)).creator(TextStr("ahitool"));
+    for (font, id) in Font::ALL.into_iter().zip(font_ids) {
+        pdf.type1_font(id)
+            .base_font(font.base_font())
+            .encoding_predefined(Name(b"WinAnsiEncoding"));
+    }
+
+    let num_pages = pages.len();
+    let mut page_ids = Vec::new();
+    for (number, mut page) in pages.into_iter().enumerate() {
+        let page_id = alloc();
+        let content_id = alloc();
+        page_ids.push(page_id);
+
+        let footer = format!("Page {} of {}", number + 1, num_pages);
+        let footer_width = footer.len() as f32 * FONT_SIZE * COURIER_WIDTH;
+        let footer_x = PAGE_WIDTH - MARGIN - footer_width;
+        show(&mut page.content, Font::Mono, FONT_SIZE, footer_x, MARGIN, &footer);
+
+        let mut page_writer = pdf.page(page_id);
+        page_writer.media_box(Rect::new(0.0, 0.0, PAGE_WIDTH, PAGE_HEIGHT));
+        page_writer.parent(page_tree_id);
+        page_writer.contents(content_id);
+        let mut resources = page_writer.resources();
+        let mut fonts = resources.fonts();
+        for (font, id) in Font::ALL.into_iter().zip(font_ids) {
+            fonts.pair(font.resource_name(), id);
+        }
+        fonts.finish();
+        resources.finish();
+        if !page.links.is_empty() {
+            let mut annotations = page_writer.annotations();
+            for (area, url) in &page.links {
+                let mut annotation = annotations.push();
+                annotation.subtype(AnnotationType::Link);
+                annotation.rect(*area);
+                annotation.border(0.0, 0.0, 0.0, None);
+                annotation.action().action_type(ActionType::Uri).uri(Str(url.as_bytes()));
+            }
+        }
+        page_writer.finish();
+        pdf.stream(content_id, &page.content.finish());
+    }
+    pdf.pages(page_tree_id).count(page_ids.len() as i32).kids(page_ids);
+    pdf.finish()
+}
+
+/// Writes text with its baseline at `y`.
+fn show(content: &mut Content, font: Font, size: f32, x: f32, y: f32, text: &str) {
+    content.set_fill_gray(0.0);
+    content.begin_text();
+    content.set_font(font.resource_name(), size);
+    content.next_line(x, y);
+    content.show(Str(&win_ansi(text)));
+    content.end_text();
+}
+
+fn fill(
+    content: &mut Content,
+    (r, g, b): (f32, f32, f32),
+    x: f32,
+    y: f32,
+    width: f32,
+    height: f32,
+) {
+    content.set_fill_rgb(r, g, b);
+    content.rect(x, y, width, height);
+    content.fill_nonzero();
+}
+
+/// Encodes text for the standard fonts, which can show ASCII and Latin-1.
+/// Other characters are replaced with "?".
+fn win_ansi(text: &str) -> Vec<u8> {
+    text.chars()
+        .map(|c| match u32::from(c) {
+            code @ (0x20..=0x7E | 0xA0..=0xFF) => code as u8,
+            _ => b'?',
+        })
+        .collect()
+}
+
+/// The widths of the columns of a table, in characters. If the table is wider
+/// than `max_chars`, the widest columns are narrowed until it fits.
+fn column_widths(table: &Table, max_chars: usize) -> Vec<usize> {
+    let mut widths: Vec<usize> =
+        table.headers.iter().map(|header| header.chars().count()).collect();
+    for row in &table.rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.human().chars().count());
+        }
+    }
+    let total = |widths: &[usize]| {
+        widths.iter().sum::<usize>() + widths.len().saturating_sub(1) * COLUMN_GAP
+    };
+    while total(&widths) > max_chars {
+        let Some(widest) = widths.iter_mut().filter(|width| **width > MIN_COLUMN_WIDTH).max()
+        else {
+            break;
+        };
+        *widest -= 1;
+    }
+    widths
+}
+
+/// Cuts text down to `width` characters, ending in "..." if it was cut.
+fn truncate(text: &str, width: usize) -> String {
+    if text.chars().count() <= width {
+        return text.to_owned();
+    }
+    let mut truncated: String = text.chars().take(width.saturating_sub(3)).collect();
+    truncated.push_str("...");
+    truncated
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn wide_tables_are_narrowed_to_fit() {
+        let mut report = Report::new("Sample");
+        let table = report.section("Jobs").table(["Job", "Notes"]);
+        table.row([Cell::text("1001"), Cell::text("x".repeat(200))]);
+        let widths = column_widths(&report.sections[0].tables[0], 80);
+        assert_eq!(widths, [4, 74]);
+        assert_eq!(truncate("abcdefgh", 6), "abc...");
+        assert_eq!(win_ansi("Café ✓"), b"Caf\xe9 ?");
+    }
+
+    #[test]
+    fn document_has_a_page_per_section() {
+        let mut report = Report::new("Sample");
+        report.key_figure("Total", Cell::cents(100));
+        for name in ["Stats Jane Doe", "Red Flags"] {
+            let table = report.section(name).table(["Job Number"]);
+            for number in 0..60 {
+                table.row([Cell::link(number.to_string(), "https://example.com")]);
+            }
+        }
+        let pdf = document(&report);
+        assert!(pdf.starts_with(b"%PDF-"));
+        let text = String::from_utf8_lossy(&pdf);
+        // a cover, the summary, and two pages for each section
+        assert!(text.contains("/Count 6"));
+    }
+}
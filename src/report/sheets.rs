@@ -0,0 +1,162 @@
+use std::ops::Range;
+
+use crate::{
+    apis::google_sheets::{
+        self,
+        spreadsheet::{
+            BandedRange, CellData, CellFormat, Color, ConditionalFormatRule, EmbeddedChart,
+            ExtendedValue, GridCoordinate, GridData, GridProperties, GridRange, RowData, Sheet,
+            SheetProperties, Spreadsheet, SpreadsheetProperties,
+        },
+        SheetNickname,
+    },
+    utils,
+};
+
+use super::{ChartKind, Condition, Report, Section};
+
+/// The number of rows that a chart takes up, at the default size of charts and
+/// rows.
+const CHART_ROWS: u64 = 20;
+
+/// Creates or updates the Google Sheet of the report, then opens it.
+pub fn publish(report: &Report, nickname: SheetNickname, update: bool) -> anyhow::Result<()> {
+    let spreadsheet = spreadsheet(report);
+    let url = tokio::runtime::Builder::new_multi_thread().enable_all().build().unwrap().block_on(
+        google_sheets::run_with_credentials(|token| {
+            // FIXME cloning the token is a workaround because I can't
+            // get lifetimes to work correctly in run_with_credentials
+            let token = token.clone();
+            let spreadsheet = &spreadsheet;
+            async move {
+                let spreadsheet = spreadsheet.clone();
+                if update {
+                    google_sheets::create_or_write_spreadsheet(&token, nickname, spreadsheet).await
+                } else {
+                    google_sheets::create_spreadsheet(&token, nickname, spreadsheet).await
+                }
+            }
+        }),
+    )?;
+    utils::open_url(url.as_str());
+    Ok(())
+}
+
+/// The spreadsheet of a report, with a sheet for each section.
+pub fn spreadsheet(report: &Report) -> Spreadsheet {
+    Spreadsheet {
+        properties: SpreadsheetProperties { title: Some(report.title.clone()) },
+        sheets: Some(report.sections.iter().map(sheet).collect()),
+        ..Default::default()
+    }
+}
+
+/// The sheet of a section. The tables are stacked vertically with a blank row
+/// between them, and the charts go to the right of the tables.
+fn sheet(section: &Section) -> Sheet {
+    fn bold_row(texts: impl IntoIterator<Item = impl Into<String>>) -> RowData {
+        RowData {
+            values: texts
+                .into_iter()
+                .map(|text| CellData {
+                    user_entered_value: Some(ExtendedValue::StringValue(text.into())),
+                    user_entered_format: Some(CellFormat::bold()),
+                })
+                .collect(),
+        }
+    }
+    fn range(rows: Range<usize>, columns: Range<usize>) -> GridRange {
+        GridRange {
+            start_row_index: Some(rows.start as u64),
+            end_row_index: Some(rows.end as u64),
+            start_column_index: Some(columns.start as u64),
+            end_column_index: Some(columns.end as u64),
+            ..Default::default()
+        }
+    }
+
+    let width = section.tables.iter().map(|table| table.headers.len()).max().unwrap_or(0);
+    let mut rows = Vec::new();
+    let mut conditional_formats = Vec::new();
+    let mut banded_ranges = Vec::new();
+    let mut charts = Vec::new();
+    let mut next_chart_row = 0;
+    for table in &section.tables {
+        if !rows.is_empty() {
+            rows.push(RowData { values: Vec::new() });
+        }
+        if let Some(title) = &table.title {
+            rows.push(bold_row([title.clone()]));
+        }
+        let header_row = rows.len();
+        rows.push(bold_row(table.headers.iter().cloned()));
+        rows.extend(
+            table
+                .rows
+                .iter()
+                .map(|row| RowData { values: row.iter().map(|c| c.sheets()).collect() }),
+        );
+        banded_ranges
+            .push(BandedRange::rows(range(header_row..rows.len(), 0..table.headers.len())));
+
+        // the rows of the table that a highlight or chart covers, in the sheet
+        let first_row = header_row + 1;
+        let sheet_rows = |rows: &Option<Range<usize>>| match rows {
+            Some(rows) => first_row + rows.start..first_row + rows.end.min(table.rows.len()),
+            None => first_row..first_row + table.rows.len(),
+        };
+        for highlight in &table.highlights {
+            let cells = range(sheet_rows(&highlight.rows), highlight.column..highlight.column + 1);
+            conditional_formats.push(match highlight.condition {
+                Condition::Below(threshold) => {
+                    ConditionalFormatRule::number_less(cells, threshold, Color::LIGHT_RED)
+                }
+                Condition::Above(threshold) => {
+                    ConditionalFormatRule::number_greater(cells, threshold, Color::LIGHT_RED)
+                }
+            });
+        }
+        for chart in &table.charts {
+            let chart_rows = sheet_rows(&chart.rows);
+            let labels = range(chart_rows.clone(), chart.labels..chart.labels + 1);
+            let values = range(chart_rows, chart.values..chart.values + 1);
+            let anchor_row = next_chart_row.max(header_row as u64);
+            next_chart_row = anchor_row + CHART_ROWS;
+            let anchor_cell = GridCoordinate {
+                sheet_id: 0,
+                row_index: anchor_row,
+                column_index: width as u64 + 1,
+            };
+            charts.push(match chart.kind {
+                ChartKind::Bar => {
+                    EmbeddedChart::basic(&chart.title, "BAR", labels, [values], anchor_cell)
+                }
+                ChartKind::Column => {
+                    EmbeddedChart::basic(&chart.title, "COLUMN", labels, [values], anchor_cell)
+                }
+                ChartKind::Pie => EmbeddedChart::pie(&chart.title, labels, values, anchor_cell),
+            });
+        }
+    }
+
+    // the header stays in view if it is the only one
+    let frozen_row_count = match &section.tables[..] {
+        [table] if table.title.is_none() => 1,
+        _ => 0,
+    };
+    Sheet {
+        properties: SheetProperties {
+            title: Some(section.name.clone()),
+            grid_properties: Some(GridProperties {
+                frozen_row_count: Some(frozen_row_count),
+                ..Default::default()
+            }),
+            ..Default::default()
+        },
+        data: Some(GridData { start_row: 0, start_column: 0, row_data: rows }),
+        conditional_formats: Some(conditional_formats),
+        banded_ranges: Some(banded_ranges),
+        charts: Some(charts),
+        auto_resize: true,
+    }
+}
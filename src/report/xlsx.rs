@@ -0,0 +1,228 @@
+use std::{io::Write, ops::Range};
+
+use rust_xlsxwriter::{
+    Chart as XlsxChart, ConditionalFormatCell, ConditionalFormatCellRule, DocProperties, Format,
+    Workbook, Worksheet, XlsxError,
+};
+
+use super::{Cell, ChartKind, Condition, Renderer, Report, Section};
+
+/// The number of rows that a chart takes up, at the default size of charts and
+/// rows.
+const CHART_ROWS: u32 = 15;
+
+/// The longest name that Excel allows for a worksheet.
+const MAX_SHEET_NAME_LEN: usize = 31;
+
+/// Writes reports as Excel workbooks, laid out like the Google Sheets of the
+/// reports: a worksheet for each section, with the tables stacked vertically
+/// and the charts to their right.
+pub struct Xlsx;
+
+impl Renderer for Xlsx {
+    fn extension(&self) -> &'static str {
+        "xlsx"
+    }
+
+    fn render(&self, report: &Report, out: &mut dyn Write) -> anyhow::Result<()> {
+        let mut workbook = Workbook::new();
+        workbook.set_properties(&DocProperties::new().set_title(&report.title));
+        let mut names = Vec::new();
+        for section in &report.sections {
+            let name = sheet_name(&section.name, &names);
+            let worksheet = workbook.add_worksheet();
+            worksheet.set_name(&name)?;
+            write_section(worksheet, section)?;
+            names.push(name);
+        }
+        out.write_all(&workbook.save_to_buffer()?)?;
+        Ok(())
+    }
+
+    /// Every section is a worksheet of one workbook.
+    fn splits_sections(&self) -> bool {
+        false
+    }
+}
+
+/// The name of a section's worksheet. Excel limits the length of names and
+/// the characters in them, and names must be unique regardless of case.
+fn sheet_name(section_name: &str, taken: &[String]) -> String {
+    let base: String =
+        section_name.chars().map(|c| if "[]:*?/\\".contains(c) { '-' } else { c }).collect();
+    let base = base.trim_matches(|c: char| c == '\'' || c.is_whitespace());
+    let base = if base.is_empty() { "Sheet" } else { base };
+    let is_taken = |name: &str| taken.iter().any(|taken| taken.eq_ignore_ascii_case(name));
+    let mut name: String = base.chars().take(MAX_SHEET_NAME_LEN).collect();
+    let mut suffix = 2;
+    while is_taken(&name) {
+        let suffix_text = format!(" ({})", suffix);
+        name = base.chars().take(MAX_SHEET_NAME_LEN - suffix_text.len()).collect();
+        name.push_str(&suffix_text);
+        suffix += 1;
+    }
+    name
+}
+
+/// Writes the tables of a section into its worksheet, like
+/// [`super::sheets`] lays out the sheet of a section.
+fn write_section(worksheet: &mut Worksheet, section: &Section) -> Result<(), XlsxError> {
+    let bold = Format::new().set_bold();
+    let highlighted = Format::new().set_background_color(0xF4CCCC);
+    let width = section.tables.iter().map(|table| table.headers.len()).max().unwrap_or(0);
+    let mut row = 0;
+    let mut next_chart_row = 0;
+    for table in &section.tables {
+        if row > 0 {
+            row += 1;
+        }
+        if let Some(title) = &table.title {
+            worksheet.write_string_with_format(row, 0, title, &bold)?;
+            row += 1;
+        }
+        let header_row = row;
+        for (column, header) in table.headers.iter().enumerate() {
+            worksheet.write_string_with_format(row, column as u16, header, &bold)?;
+        }
+        row += 1;
+        for cells in &table.rows {
+            for (column, cell) in cells.iter().enumerate() {
+                write_cell(worksheet, row, column as u16, cell)?;
+            }
+            row += 1;
+        }
+
+        // the rows of the table that a highlight or chart covers, in the sheet
+        let first_row = header_row + 1;
+        let sheet_rows = |rows: &Option<Range<usize>>| match rows {
+            Some(rows) => {
+                first_row + rows.start as u32..first_row + rows.end.min(table.rows.len()) as u32
+            }
+            None => first_row..row,
+        };
+        for highlight in &table.highlights {
+            let rows = sheet_rows(&highlight.rows);
+            if rows.is_empty() {
+                continue;
+            }
+            let rule = match highlight.condition {
+                Condition::Below(threshold) => ConditionalFormatCellRule::LessThan(threshold),
+                Condition::Above(threshold) => ConditionalFormatCellRule::GreaterThan(threshold),
+            };
+            let format = ConditionalFormatCell::new().set_rule(rule).set_format(&highlighted);
+            let column = highlight.column as u16;
+            worksheet.add_conditional_format(rows.start, column, rows.end - 1, column, &format)?;
+        }
+        for chart in &table.charts {
+            let rows = sheet_rows(&chart.rows);
+            if rows.is_empty() {
+                continue;
+            }
+            let mut xlsx_chart = match chart.kind {
+                ChartKind::Bar => XlsxChart::new_bar(),
+                ChartKind::Column => XlsxChart::new_column(),
+                ChartKind::Pie => XlsxChart::new_pie(),
+            };
+            let sheet = worksheet.name();
+            let (labels, values) = (chart.labels as u16, chart.values as u16);
+            xlsx_chart
+                .add_series()
+                .set_categories((sheet.as_str(), rows.start, labels, rows.end - 1, labels))
+                .set_values((sheet.as_str(), rows.start, values, rows.end - 1, values));
+            xlsx_chart.title().set_name(&chart.title);
+            if chart.kind != ChartKind::Pie {
+                xlsx_chart.legend().set_hidden();
+            }
+            let anchor_row = next_chart_row.max(header_row);
+            next_chart_row = anchor_row + CHART_ROWS;
+            worksheet.insert_chart(anchor_row, width as u16 + 1, &xlsx_chart)?;
+        }
+    }
+
+    // the header stays in view if it is the only one
+    if let [table] = &section.tables[..] {
+        if table.title.is_none() {
+            worksheet.set_freeze_panes(1, 0)?;
+        }
+    }
+    worksheet.autofit();
+    Ok(())
+}
+
+/// Writes a cell as a value with a number format like the one it has in a
+/// Google Sheet. Empty cells are left blank.
+fn write_cell(
+    worksheet: &mut Worksheet,
+    row: u32,
+    column: u16,
+    cell: &Cell,
+) -> Result<(), XlsxError> {
+    let number_format = |pattern: &str| Format::new().set_num_format(pattern);
+    match cell {
+        Cell::Empty => {}
+        Cell::Text(text) => {
+            worksheet.write_string(row, column, text)?;
+        }
+        // a cell can only hold one link
+        Cell::Links(_) => {
+            worksheet.write_string(row, column, cell.human())?;
+        }
+        Cell::Bool(value) => {
+            worksheet.write_boolean(row, column, *value)?;
+        }
+        Cell::Number(number) => {
+            worksheet.write_number(row, column, *number)?;
+        }
+        Cell::Percent(rate) => {
+            worksheet.write_number_with_format(row, column, *rate, &number_format("0.00%"))?;
+        }
+        Cell::Currency(amount) => {
+            worksheet.write_number_with_format(
+                row,
+                column,
+                *amount,
+                &number_format("$#,##0.00"),
+            )?;
+        }
+        Cell::Days(days) => {
+            worksheet.write_number_with_format(row, column, *days, &number_format("0.0"))?;
+        }
+        Cell::Link { text, url } => {
+            worksheet.write_url_with_text(row, column, url.as_str(), text)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::report::Chart;
+
+    #[test]
+    fn sheet_names_are_valid_and_unique() {
+        let taken = vec!["Summary".to_owned()];
+        assert_eq!(sheet_name("Submit COC & Proof of Completion", &[]).len(), 31);
+        assert_eq!(sheet_name("Jobs: 2024/25", &[]), "Jobs- 2024-25");
+        assert_eq!(sheet_name("summary", &taken), "summary (2)");
+    }
+
+    #[test]
+    fn workbook_has_a_sheet_per_section() {
+        let mut report = Report::new("Sample");
+        for name in ["Stats Jane Doe", "Red Flags"] {
+            let table = report.section(name).table(["Conversion", "Rate"]);
+            table.row([Cell::text("Appt"), Cell::percent(Some(0.5))]);
+            table.charts.push(Chart {
+                title: "Rates".to_owned(),
+                kind: ChartKind::Bar,
+                labels: 0,
+                values: 1,
+                rows: None,
+            });
+        }
+        let mut out = Vec::new();
+        Xlsx.render(&report, &mut out).unwrap();
+        assert!(out.starts_with(b"PK"));
+    }
+}
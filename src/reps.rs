@@ -0,0 +1,187 @@
+use std::{
+    cmp::Reverse,
+    collections::{BTreeMap, HashMap},
+};
+
+use crate::{config::Config, jobs::Job};
+
+/// The similarity (between 0 and 1) at or above which two different rep names
+/// are considered probable duplicates.
+const DUPLICATE_THRESHOLD: f64 = 0.9;
+
+/// Returns the name with its whitespace collapsed.
+fn collapse_whitespace(name: &str) -> String {
+    name.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Returns a key that is the same for all spellings of a name that differ only
+/// in whitespace and case.
+pub fn name_key(name: &str) -> String {
+    collapse_whitespace(name).to_lowercase()
+}
+
+/// Determines the canonical name of each of the specified rep names. A rep
+/// listed in the configuration is known by the configured name, including
+/// under any of the rep's aliases. Otherwise, names that differ only in
+/// whitespace and case are merged under the spelling that appears most often.
+///
+/// The returned map contains an entry for each distinct name.
+pub fn canonical_names<'a>(
+    names: impl IntoIterator<Item = &'a str>,
+    config: &Config,
+) -> HashMap<String, String> {
+    let mut names_by_key: HashMap<String, BTreeMap<&str, usize>> = HashMap::new();
+    for name in names {
+        *names_by_key.entry(name_key(name)).or_default().entry(name).or_default() += 1;
+    }
+
+    let mut canonical_names = HashMap::new();
+    for (key, names) in names_by_key {
+        let canonical_name = match config.find_rep(&key) {
+            Some(placement) => placement.rep.name.clone(),
+            None => {
+                // count the spellings after collapsing whitespace, and prefer
+                // the first alphabetically in case of a tie
+                let mut spellings: BTreeMap<String, usize> = BTreeMap::new();
+                for (name, count) in &names {
+                    *spellings.entry(collapse_whitespace(name)).or_default() += count;
+                }
+                spellings
+                    .into_iter()
+                    .max_by_key(|(spelling, count)| (*count, Reverse(spelling.clone())))
+                    .map(|(spelling, _)| spelling)
+                    .expect("there is at least one name for each key")
+            }
+        };
+        for name in names.into_keys() {
+            canonical_names.insert(name.to_owned(), canonical_name.clone());
+        }
+    }
+    canonical_names
+}
+
+/// Replaces the sales rep of each job with the rep's canonical name (see
+/// [`canonical_names`]).
+pub fn normalize_sales_reps(jobs: &mut [Job], config: &Config) {
+    let canonical_names =
+        canonical_names(jobs.iter().filter_map(|job| job.sales_rep.as_deref()), config);
+    for job in jobs {
+        if let Some(name) = &mut job.sales_rep {
+            *name = canonical_names[name.as_str()].clone();
+        }
+    }
+}
+
+/// A pair of different rep names that probably refer to the same rep.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProbableDuplicate {
+    pub names: (String, String),
+    /// How similar the names are, between 0 and 1.
+    pub similarity: f64,
+}
+
+/// Finds the pairs of names that are probably the same rep, most similar
+/// first. Names that are both listed in the configuration are never
+/// considered duplicates, since the configuration says they are different
+/// reps.
+pub fn find_probable_duplicates(names: &[&str], config: &Config) -> Vec<ProbableDuplicate> {
+    let mut duplicates = Vec::new();
+    for (i, a) in names.iter().enumerate() {
+        for b in &names[i + 1..] {
+            if config.find_rep(a).is_some() && config.find_rep(b).is_some() {
+                continue;
+            }
+            let similarity = similarity(a, b);
+            if similarity >= DUPLICATE_THRESHOLD {
+                duplicates
+                    .push(ProbableDuplicate { names: (a.to_string(), b.to_string()), similarity });
+            }
+        }
+    }
+    duplicates.sort_by(|a, b| b.similarity.total_cmp(&a.similarity));
+    duplicates
+}
+
+/// How similar two names are, between 0 and 1. Besides similar spellings, this
+/// recognizes a first name that was abbreviated to an initial (e.g. "H.
+/// Asbury" and "Heinrich Asbury").
+fn similarity(a: &str, b: &str) -> f64 {
+    /// The similarity given to names where one abbreviates the other.
+    const ABBREVIATION_SIMILARITY: f64 = 0.95;
+
+    let (a, b) = (name_key(a), name_key(b));
+    let is_initial = |word: &str| word.trim_end_matches('.').chars().count() == 1;
+    if let (Some((a_first, a_rest)), Some((b_first, b_rest))) =
+        (a.split_once(' '), b.split_once(' '))
+    {
+        if a_rest == b_rest
+            && a_first.chars().next() == b_first.chars().next()
+            && (is_initial(a_first) || is_initial(b_first))
+        {
+            return ABBREVIATION_SIMILARITY;
+        }
+    }
+    strsim::jaro_winkler(&a, &b)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::config::{Branch, Rep, Team};
+
+    fn config_with_rep(name: &str, aliases: &[&str]) -> Config {
+        Config {
+            branches: vec![Branch {
+                name: "Main".to_owned(),
+                teams: vec![Team {
+                    name: "Team A".to_owned(),
+                    reps: vec![Rep {
+                        name: name.to_owned(),
+                        aliases: aliases.iter().map(|alias| alias.to_string()).collect(),
+                        email: None,
+                    }],
+                }],
+            }],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn merges_names_differing_in_whitespace_and_case() {
+        let names = ["Heinrich Asbury", "heinrich asbury ", "Heinrich  Asbury", "Heinrich Asbury"];
+        let canonical_names = canonical_names(names, &Config::default());
+        assert_eq!(canonical_names.len(), 3);
+        for name in names {
+            assert_eq!(canonical_names[name], "Heinrich Asbury");
+        }
+    }
+
+    #[test]
+    fn applies_configured_aliases() {
+        let config = config_with_rep("Heinrich Asbury", &["H. Asbury"]);
+        let canonical_names = canonical_names(["h. asbury", "HEINRICH ASBURY", "Bob"], &config);
+        assert_eq!(canonical_names["h. asbury"], "Heinrich Asbury");
+        assert_eq!(canonical_names["HEINRICH ASBURY"], "Heinrich Asbury");
+        assert_eq!(canonical_names["Bob"], "Bob");
+    }
+
+    #[test]
+    fn suggests_probable_duplicates() {
+        let names = ["Heinrich Asbury", "H. Asbury", "Heinrich Asbery", "Maria Lopez"];
+        let duplicates = find_probable_duplicates(&names, &Config::default());
+        let pairs: Vec<_> =
+            duplicates.iter().map(|d| (d.names.0.as_str(), d.names.1.as_str())).collect();
+        assert!(pairs.contains(&("Heinrich Asbury", "H. Asbury")));
+        assert!(pairs.contains(&("Heinrich Asbury", "Heinrich Asbery")));
+        assert!(!pairs.iter().any(|(a, b)| *a == "Maria Lopez" || *b == "Maria Lopez"));
+
+        // once the alias is configured, the names are no longer suggested
+        let config = config_with_rep("Heinrich Asbury", &["H. Asbury"]);
+        let canonical_names = canonical_names(names, &config);
+        let mut names: Vec<_> = canonical_names.values().map(String::as_str).collect();
+        names.sort();
+        names.dedup();
+        let duplicates = find_probable_duplicates(&names, &config);
+        assert!(!duplicates.iter().any(|d| d.names.0 == "H. Asbury" || d.names.1 == "H. Asbury"));
+    }
+}
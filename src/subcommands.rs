@@ -1,5 +1,14 @@
 pub mod acc_receivable;
+pub mod audit;
+pub mod commission;
+pub mod forecast;
 pub mod kpi;
+pub mod leaderboard;
+pub mod losses;
+pub mod notify;
+pub mod pipeline;
+pub mod reps;
+pub mod stale;
 pub mod update;
 
 #[derive(clap::Subcommand, Debug)]
@@ -10,5 +19,29 @@ pub enum Subcommand {
     Kpi(kpi::Args),
     /// Generate a report for all accounts receivable.
     Ar(acc_receivable::Args),
+    /// Generate a report of all open jobs and how long they have been at their
+    /// current milestone.
+    Pipeline(pipeline::Args),
+    /// Project the contracts, installs, and revenue expected from the open
+    /// jobs in the near future.
+    Forecast(forecast::Args),
+    /// Generate a report of lost jobs, broken down by the stage at which they
+    /// were lost and the reason they were lost.
+    Losses(losses::Args),
+    /// List the sales reps as identified after merging differently spelled
+    /// names, along with names that are probably duplicates.
+    Reps(reps::Args),
+    /// Calculate the commission owed to each sales rep for a pay period.
+    Commission(commission::Args),
+    /// Rank the sales reps on various metrics over a period.
+    Leaderboard(leaderboard::Args),
+    /// Check the jobs for data-quality problems, such as missing fields and
+    /// contradictory dates.
+    Audit(audit::Args),
+    /// Send each sales rep a digest of the red flags in their jobs, by email
+    /// or webhook.
+    Notify(notify::Args),
+    /// List the jobs that have been in their current status for too long.
+    Stale(stale::Args),
     SaySomething,
 }
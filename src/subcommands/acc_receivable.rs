@@ -1,23 +1,25 @@
-use std::{collections::HashMap, io::Write};
+use std::collections::HashMap;
 
 use chrono::Utc;
-use clap::CommandFactory as _;
+use serde::Serialize;
 
 use crate::{
-    apis::{
-        google_sheets::{
-            self,
-            spreadsheet::{
-                CellData, ExtendedValue, GridData, RowData, Sheet, SheetProperties, Spreadsheet,
-                SpreadsheetProperties,
-            },
-        },
-        job_nimbus,
-    },
+    apis::{google_sheets::SheetNickname, job_nimbus},
+    config::Config,
     jobs::{Job, Status},
-    utils, CliArgs,
+    report::{self, Cell, Chart, ChartKind, Condition, Highlight, OutputFormat, Report, Table},
+    reps,
 };
 
+/// Receivables that have been in their status for longer than this many days
+/// are highlighted.
+const OLD_RECEIVABLE_DAYS: f64 = 60.0;
+
+/// The buckets that receivables are grouped into by how many days they have
+/// been in their status, as the name and the maximum number of days of each.
+const AGING_BUCKETS: [(&str, i64); 4] =
+    [("0-30 days", 30), ("31-60 days", 60), ("61-90 days", 90), ("Over 90 days", i64::MAX)];
+
 #[derive(clap::Args, Debug)]
 pub struct Args {
     /// The format in which to print the output.
@@ -35,17 +37,6 @@ pub struct Args {
     update: bool,
 }
 
-#[derive(Debug, clap::ValueEnum, Clone, Copy, Eq, PartialEq)]
-enum OutputFormat {
-    /// Prints a human-readable report into the output file.
-    Human,
-    /// Prints a CSV file into the output file.
-    Csv,
-    /// Outputs a Google Sheet on the user's Google Drive (requires OAuth
-    /// authorization).
-    GoogleSheets,
-}
-
 const CATEGORIES_WE_CARE_ABOUT: &[Status] = &[
     Status::PendingPayments,
     Status::PostInstallSupplementPending,
@@ -62,26 +53,12 @@ struct AccRecvableData<'a> {
     categorized_jobs: HashMap<Status, (i32, Vec<&'a Job>)>,
 }
 
-pub fn main(api_key: &str, args: Args) -> anyhow::Result<()> {
+pub fn main(api_key: &str, config: &Config, args: Args) -> anyhow::Result<()> {
     let Args { output, format, update } = args;
-    if format == OutputFormat::GoogleSheets && output.is_some() {
-        CliArgs::command()
-            .error(
-                clap::error::ErrorKind::ArgumentConflict,
-                "The `--output` option cannot be used with `--format google-sheets`",
-            )
-            .exit();
-    }
-    if format != OutputFormat::GoogleSheets && update {
-        CliArgs::command()
-            .error(
-                clap::error::ErrorKind::ArgumentConflict,
-                "The `--update` option can only be used with `--format google-sheets`",
-            )
-            .exit();
-    }
+    report::check_output_args(format, output.as_deref(), update);
 
-    let jobs = job_nimbus::get_all_jobs_from_job_nimbus(&api_key, None)?;
+    let mut jobs = job_nimbus::get_all_jobs_from_job_nimbus(&api_key, None, &config.job_fields)?;
+    reps::normalize_sales_reps(&mut jobs, config);
 
     let mut results = AccRecvableData { total: 0, categorized_jobs: HashMap::new() };
     for category in CATEGORIES_WE_CARE_ABOUT {
@@ -99,166 +76,221 @@ pub fn main(api_key: &str, args: Args) -> anyhow::Result<()> {
         }
     }
 
-    let output_writer: Box<dyn Write> = match output.as_deref() {
-        Some("-") | None => Box::new(std::io::stdout()),
-        Some(path) => Box::new(std::fs::File::create(path)?),
-    };
+    report::output(
+        &report(&results),
+        format,
+        output.as_deref(),
+        update,
+        SheetNickname::AccReceivable,
+    )
+}
 
-    match format {
-        OutputFormat::Human => print_human(&results, output_writer)?,
-        OutputFormat::Csv => print_csv(&results, output_writer)?,
-        OutputFormat::GoogleSheets => {
-            generate_report_google_sheets(&results, update)?;
-        }
-    }
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Record<'a> {
+    /// The total amount receivable, and the amount in each aging bucket.
+    Summary { total: f64, aging: Vec<AgingBucket<'a>> },
+    /// The jobs in one of the statuses that are considered receivable.
+    Category { status: String, total: f64, jobs: Vec<ReceivableJob<'a>> },
+}
 
-    Ok(())
+#[derive(Serialize)]
+struct AgingBucket<'a> {
+    name: &'a str,
+    /// The most days in status of a job in the bucket. `None` for the last
+    /// bucket.
+    max_days: Option<i64>,
+    total: f64,
 }
 
-fn print_human(results: &AccRecvableData, mut writer: impl Write) -> std::io::Result<()> {
-    let mut zero_amt_jobs = Vec::new();
+#[derive(Serialize)]
+struct ReceivableJob<'a> {
+    job_id: &'a str,
+    job_number: Option<&'a str>,
+    job_name: Option<&'a str>,
+    amount: f64,
+    days_in_status: i64,
+    sales_rep: Option<&'a str>,
+}
 
-    writeln!(writer, "Total: ${}", results.total as f64 / 100.0)?;
-    for (status, (category_total, jobs)) in &results.categorized_jobs {
-        writeln!(writer, "    - {}: total ${}", status, *category_total as f64 / 100.0)?;
-        for job in jobs {
-            if job.amt_receivable == 0 {
-                zero_amt_jobs.push(job);
-                continue;
-            }
+/// The report has a summary of the amount receivable in each status and in
+/// each aging bucket, followed by a section with the receivable jobs of each
+/// status, and then a list of the jobs with nothing receivable.
+fn report(results: &AccRecvableData) -> Report {
+    let mut report = Report::new(format!("Accounts Receivable Report ({})", Utc::now()));
+    let days_in_status =
+        |job: &Job| Utc::now().signed_duration_since(job.status_mod_date).num_days();
+    let dollars = |cents: i32| cents as f64 / 100.0;
 
-            let name = job.job_name.as_deref().unwrap_or("");
-            let number = job.job_number.as_deref().unwrap_or("Unknown Job Number");
-            let amount_receivable = job.amt_receivable as f64 / 100.0;
-            let days_in_status = Utc::now().signed_duration_since(job.status_mod_date).num_days();
-            writeln!(
-                writer,
-                "        - {} (#{}): ${:.2} ({} days)",
-                name, number, amount_receivable, days_in_status
-            )?;
+    let mut aging_totals = [0; AGING_BUCKETS.len()];
+    for (_category_total, jobs) in results.categorized_jobs.values() {
+        for job in jobs {
+            let bucket = AGING_BUCKETS
+                .iter()
+                .position(|(_name, max_days)| days_in_status(job) <= *max_days)
+                .unwrap_or(AGING_BUCKETS.len() - 1);
+            aging_totals[bucket] += job.amt_receivable;
         }
     }
 
-    writeln!(writer, "Jobs with $0 receivable:")?;
-    for job in zero_amt_jobs {
-        let name = job.job_name.as_deref().unwrap_or("");
-        let number = job.job_number.as_deref().unwrap_or("Unknown Job Number");
-        let days_in_status = Utc::now().signed_duration_since(job.status_mod_date).num_days();
-        writeln!(
-            writer,
-            "    - {} (#{}): ({} for {} days)",
-            name, number, job.status, days_in_status
-        )?;
+    let num_jobs: usize = results.categorized_jobs.values().map(|(_, jobs)| jobs.len()).sum();
+    let old_total: i32 = results
+        .categorized_jobs
+        .values()
+        .flat_map(|(_, jobs)| jobs)
+        .filter(|job| days_in_status(job) as f64 > OLD_RECEIVABLE_DAYS)
+        .map(|job| job.amt_receivable)
+        .sum();
+    report.key_figure("Total Receivable", Cell::cents(results.total.into()));
+    report.key_figure("Receivable Jobs", Cell::Number(num_jobs as f64));
+    report.key_figure(
+        format!("Over {} Days In Status", OLD_RECEIVABLE_DAYS),
+        Cell::cents(old_total.into()),
+    );
+
+    report.record(Record::Summary {
+        total: dollars(results.total),
+        aging: AGING_BUCKETS
+            .iter()
+            .zip(aging_totals)
+            .map(|((name, max_days), total)| AgingBucket {
+                name,
+                max_days: (*max_days != i64::MAX).then_some(*max_days),
+                total: dollars(total),
+            })
+            .collect(),
+    });
+    for status in CATEGORIES_WE_CARE_ABOUT {
+        let Some((total, jobs)) = results.categorized_jobs.get(status) else {
+            continue;
+        };
+        report.record(Record::Category {
+            status: status.to_string(),
+            total: dollars(*total),
+            jobs: jobs
+                .iter()
+                .map(|job| ReceivableJob {
+                    job_id: &job.jnid,
+                    job_number: job.job_number.as_deref(),
+                    job_name: job.job_name.as_deref(),
+                    amount: dollars(job.amt_receivable),
+                    days_in_status: days_in_status(job),
+                    sales_rep: job.sales_rep.as_deref(),
+                })
+                .collect(),
+        });
     }
 
-    Ok(())
-}
+    let summary = report.section("Summary");
+    let table = summary
+        .table(["Job Status", "Amount"])
+        .titled(format!("Total {}", Cell::cents(results.total.into())));
+    for status in CATEGORIES_WE_CARE_ABOUT {
+        let total = results.categorized_jobs.get(status).map(|(total, _)| *total).unwrap_or(0);
+        table.row([Cell::text(status.to_string()), Cell::cents(total.into())]);
+    }
+    table.charts.push(Chart {
+        title: "Receivables by Status".to_string(),
+        kind: ChartKind::Pie,
+        labels: 0,
+        values: 1,
+        rows: None,
+    });
 
-fn print_csv(results: &AccRecvableData, writer: impl Write) -> std::io::Result<()> {
-    let mut writer = csv::Writer::from_writer(writer);
-    writer
-        .write_record(&["Job Name", "Job Number", "Job Status", "Amount", "Days In Status"])
-        .unwrap();
-    for (_status, (_category_total, jobs)) in &results.categorized_jobs {
-        for job in jobs {
-            let name = job.job_name.as_deref().unwrap_or("");
-            let number = job.job_number.as_deref().unwrap_or("Unknown Job Number");
-            let status = format!("{}", job.status);
-            let amount_receivable = (job.amt_receivable as f64) / 100.0;
-            let days_in_status = Utc::now().signed_duration_since(job.status_mod_date).num_days();
-            writer
-                .write_record(&[
-                    name,
-                    number,
-                    &status,
-                    &amount_receivable.to_string(),
-                    &days_in_status.to_string(),
-                ])
-                .unwrap();
+    let table = summary.table(["Days In Status", "Amount"]).titled("Aging");
+    for ((name, _max_days), total) in AGING_BUCKETS.iter().zip(aging_totals) {
+        table.row([Cell::text(*name), Cell::cents(total.into())]);
+    }
+    table.charts.push(Chart {
+        title: "Receivables by Days In Status".to_string(),
+        kind: ChartKind::Column,
+        labels: 0,
+        values: 1,
+        rows: None,
+    });
+
+    for status in CATEGORIES_WE_CARE_ABOUT {
+        let Some((category_total, jobs)) = results.categorized_jobs.get(status) else {
+            continue;
+        };
+        let table = report.section(status.to_string()).table([
+            "Job Name",
+            "Job Number",
+            "Amount",
+            "Days In Status",
+            "Sales Rep",
+        ]);
+        for job in jobs.iter().filter(|job| job.amt_receivable != 0) {
+            table.row([
+                Cell::text(job.job_name.as_deref().unwrap_or("")),
+                Cell::link(
+                    job.job_number.as_deref().unwrap_or("Unknown Job Number"),
+                    job_nimbus::job_url(&job.jnid),
+                ),
+                Cell::cents(job.amt_receivable.into()),
+                Cell::Number(days_in_status(job) as f64),
+                Cell::text(job.sales_rep.clone().unwrap_or_default()),
+            ]);
         }
+        table.highlights.push(Highlight {
+            column: 3,
+            rows: None,
+            condition: Condition::Above(OLD_RECEIVABLE_DAYS),
+        });
+        table.totaled([
+            Cell::text("Total"),
+            Cell::Empty,
+            Cell::cents((*category_total).into()),
+            Cell::Empty,
+            Cell::Empty,
+        ]);
     }
-    writer.flush().unwrap();
-    Ok(())
-}
 
-fn generate_report_google_sheets(
-    results: &AccRecvableData<'_>,
-    update: bool,
-) -> anyhow::Result<()> {
-    fn mk_row(cells: impl IntoIterator<Item = ExtendedValue>) -> RowData {
-        RowData {
-            values: cells
-                .into_iter()
-                .map(|cell| CellData { user_entered_value: Some(cell) })
-                .collect(),
+    let table = report
+        .section("Jobs with $0 Receivable")
+        .table(["Job Name", "Job Number", "Job Status", "Days In Status"])
+        .listed();
+    for status in CATEGORIES_WE_CARE_ABOUT {
+        let Some((_category_total, jobs)) = results.categorized_jobs.get(status) else {
+            continue;
+        };
+        for job in jobs.iter().filter(|job| job.amt_receivable == 0) {
+            table.row([
+                Cell::text(job.job_name.as_deref().unwrap_or("")),
+                Cell::link(
+                    job.job_number.as_deref().unwrap_or("Unknown Job Number"),
+                    job_nimbus::job_url(&job.jnid),
+                ),
+                Cell::text(status.to_string()),
+                Cell::Number(days_in_status(job) as f64),
+            ]);
         }
     }
 
-    let mut rows = Vec::new();
-    rows.push(mk_row([
-        ExtendedValue::StringValue("Job Name".to_string()),
-        ExtendedValue::StringValue("Job Number".to_string()),
-        ExtendedValue::StringValue("Job Status".to_string()),
-        ExtendedValue::StringValue("Amount".to_string()),
-        ExtendedValue::StringValue("Days In Status".to_string()),
-    ]));
-    for (_status, (_category_total, jobs)) in &results.categorized_jobs {
+    let mut flat = Table::new([
+        "Job Name",
+        "Job Number",
+        "Job Status",
+        "Amount",
+        "Days In Status",
+        "Sales Rep",
+    ]);
+    for status in CATEGORIES_WE_CARE_ABOUT {
+        let Some((_category_total, jobs)) = results.categorized_jobs.get(status) else {
+            continue;
+        };
         for job in jobs {
-            let name = job.job_name.as_deref().unwrap_or("");
-            let number = job.job_number.as_deref().unwrap_or("Unknown Job Number");
-            let status = job.status.to_string();
-            let amount_receivable = (job.amt_receivable as f64) / 100.0;
-            let days_in_status = Utc::now().signed_duration_since(job.status_mod_date).num_days();
-            rows.push(mk_row([
-                ExtendedValue::StringValue(name.to_owned()),
-                ExtendedValue::StringValue(number.to_owned()),
-                ExtendedValue::StringValue(status),
-                ExtendedValue::NumberValue(amount_receivable),
-                ExtendedValue::NumberValue(days_in_status as f64),
-            ]));
+            flat.row([
+                Cell::text(job.job_name.as_deref().unwrap_or("")),
+                Cell::text(job.job_number.as_deref().unwrap_or("Unknown Job Number")),
+                Cell::text(status.to_string()),
+                Cell::cents(job.amt_receivable.into()),
+                Cell::Number(days_in_status(job) as f64),
+                Cell::text(job.sales_rep.clone().unwrap_or_default()),
+            ]);
         }
     }
+    report.flat = Some(flat);
 
-    let spreadsheet = Spreadsheet {
-        properties: SpreadsheetProperties {
-            title: Some(format!("Accounts Receivable Report ({})", Utc::now())),
-        },
-        sheets: Some(vec![Sheet {
-            properties: SheetProperties {
-                title: Some("Accounts Receivable".to_string()),
-                ..Default::default()
-            },
-            data: Some(GridData { start_row: 1, start_column: 1, row_data: rows }),
-        }]),
-        ..Default::default()
-    };
-
-    let url = tokio::runtime::Builder::new_multi_thread().enable_all().build().unwrap().block_on(
-        google_sheets::run_with_credentials(|token| {
-            // FIXME cloning the token is a workaround because I can't
-            // get lifetimes to work correctly in run_with_credentials
-            let token = token.clone();
-            let spreadsheet = &spreadsheet;
-            async move {
-                let spreadsheet = spreadsheet.clone();
-                if update {
-                    google_sheets::create_or_write_spreadsheet(
-                        &token,
-                        google_sheets::SheetNickname::AccReceivable,
-                        spreadsheet,
-                    )
-                    .await
-                } else {
-                    google_sheets::create_spreadsheet(
-                        &token,
-                        google_sheets::SheetNickname::AccReceivable,
-                        spreadsheet,
-                    )
-                    .await
-                }
-            }
-        }),
-    )?;
-    utils::open_url(url.as_str());
-    Ok(())
+    report
 }
@@ -0,0 +1,331 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use chrono::Utc;
+use clap::CommandFactory as _;
+use serde::Serialize;
+use tracing::info;
+
+use crate::{
+    apis::{google_sheets::SheetNickname, job_nimbus},
+    audit::{self, fix, Finding, Severity},
+    config::Config,
+    jobs::Job,
+    report::{self, Cell, OutputFormat, Report},
+    reps,
+    subcommands::kpi::KpiSubject,
+    CliArgs,
+};
+
+#[derive(clap::Args, Debug)]
+pub struct Args {
+    /// The filter to use when query JobNimbus for jobs, using ElasticSearch
+    /// syntax.
+    #[arg(short, long = "filter", default_value = None)]
+    filter_filename: Option<String>,
+
+    /// Only check the specified rules, by ID. Rules disabled in the
+    /// configuration are still skipped. Use `--list-rules` to see the IDs of
+    /// all the rules.
+    #[arg(long, value_delimiter = ',')]
+    only: Vec<String>,
+
+    /// Only report problems at least this severe.
+    #[arg(long, value_enum, default_value = "info")]
+    min_severity: Severity,
+
+    /// List the available rules and exit without checking any jobs.
+    #[arg(long)]
+    list_rules: bool,
+
+    /// Instead of reporting problems, propose changes to JobNimbus that fix
+    /// the problems that can be fixed automatically. The changes are applied
+    /// after confirmation, and recorded in the fix log so that they can be
+    /// undone. Only the problems that `--only`, `--min-severity`, and the
+    /// configuration would report are fixed.
+    #[arg(long)]
+    fix: bool,
+
+    /// Only valid with `--fix`. Apply the changes without asking for
+    /// confirmation.
+    #[arg(short, long)]
+    yes: bool,
+
+    /// The file to record the changes made by `--fix` in.
+    #[arg(long, default_value = "ahitool_fix_log.jsonl")]
+    fix_log: PathBuf,
+
+    /// Undo the changes recorded in the specified fix log, then exit. The log
+    /// is renamed afterwards, so that the changes aren't undone twice.
+    #[arg(long, default_value = None)]
+    undo: Option<PathBuf>,
+
+    /// The format in which to print the output.
+    #[arg(long, value_enum, default_value = "human")]
+    format: OutputFormat,
+
+    /// The file to write the output to. "-" or unspecified will write to
+    /// stdout. This option is ignored with `--format google-sheets`.
+    #[arg(short, long, default_value = None)]
+    output: Option<String>,
+
+    /// Only valid with `--format google-sheets`. Whether to update an existing
+    /// Google Sheet; if not specified, creates a new Google Sheet.
+    #[arg(long)]
+    update: bool,
+}
+
+pub fn main(api_key: &str, config: &Config, args: Args) -> anyhow::Result<()> {
+    let Args {
+        filter_filename,
+        only,
+        min_severity,
+        list_rules,
+        fix,
+        yes,
+        fix_log,
+        undo,
+        format,
+        output,
+        update,
+    } = args;
+    if yes && !fix {
+        CliArgs::command()
+            .error(
+                clap::error::ErrorKind::ArgumentConflict,
+                "The `--yes` option can only be used with `--fix`",
+            )
+            .exit();
+    }
+    report::check_output_args(format, output.as_deref(), update);
+
+    if let Some(undo) = undo {
+        return undo_fixes(api_key, &undo);
+    }
+
+    let mut rules = audit::all_rules(config.strictness);
+    audit::check_config(&rules, &config.audit)?;
+    if list_rules {
+        for rule in &rules {
+            let enabled = config
+                .audit
+                .rules
+                .get(rule.id())
+                .and_then(|rule_config| rule_config.enabled)
+                .unwrap_or(true);
+            println!(
+                "{} ({}{}): {}",
+                rule.id(),
+                rule.default_severity(),
+                if enabled { "" } else { ", disabled" },
+                rule.description()
+            );
+        }
+        return Ok(());
+    }
+    if let Some(unknown) = only.iter().find(|id| !rules.iter().any(|rule| rule.id() == *id)) {
+        CliArgs::command()
+            .error(
+                clap::error::ErrorKind::InvalidValue,
+                format!("Unknown audit rule `{}`; use `--list-rules` to see all rules", unknown),
+            )
+            .exit();
+    }
+    if !only.is_empty() {
+        rules.retain(|rule| only.iter().any(|id| id == rule.id()));
+    }
+
+    let filter = if let Some(filter_filename) = filter_filename {
+        Some(std::fs::read_to_string(filter_filename)?)
+    } else {
+        None
+    };
+    if fix {
+        // only fix the problems that the audit would report
+        let enabled = |rule_id: Option<&str>| match rule_id {
+            Some(rule_id) => rules
+                .iter()
+                .filter(|rule| rule.id() == rule_id)
+                .filter_map(|rule| audit::configured_severity(rule.as_ref(), &config.audit))
+                .any(|severity| severity >= min_severity),
+            None => only.is_empty() && min_severity == Severity::Info,
+        };
+        return fix_jobs(api_key, config, filter.as_deref(), enabled, yes, &fix_log);
+    }
+    let mut jobs =
+        job_nimbus::get_all_jobs_from_job_nimbus(api_key, filter.as_deref(), &config.job_fields)?;
+    reps::normalize_sales_reps(&mut jobs, config);
+
+    let findings: Vec<_> = audit::run_audit(&jobs, &rules, &config.audit, Utc::now())
+        .into_iter()
+        .filter(|finding| finding.severity >= min_severity)
+        .collect();
+    info!("Found {} problems in {} jobs", findings.len(), jobs.len());
+    let findings_by_rep = group_by_rep(&findings);
+
+    report::output(
+        &report(&findings_by_rep),
+        format,
+        output.as_deref(),
+        update,
+        SheetNickname::Audit,
+    )
+}
+
+/// Proposes fixes for the jobs, then applies them to JobNimbus after
+/// confirmation. Only the fixers whose rule ID is accepted by `enabled` are
+/// used.
+fn fix_jobs(
+    api_key: &str,
+    config: &Config,
+    filter: Option<&str>,
+    enabled: impl Fn(Option<&str>) -> bool,
+    yes: bool,
+    fix_log: &Path,
+) -> anyhow::Result<()> {
+    let values = job_nimbus::get_all_job_values_from_job_nimbus(api_key, filter)?;
+    let jobs = values
+        .iter()
+        .map(|value| Job::from_json(value.clone(), &config.job_fields))
+        .collect::<Result<Vec<_>, _>>()?;
+    // parsing succeeded, so every value is an object with a JNID
+    let values: HashMap<String, serde_json::Map<String, serde_json::Value>> = jobs
+        .iter()
+        .zip(values)
+        .filter_map(|(job, value)| match value {
+            serde_json::Value::Object(map) => Some((job.jnid.clone(), map)),
+            _ => None,
+        })
+        .collect();
+
+    let fixes = fix::propose_fixes(&jobs, &values, enabled);
+    if fixes.is_empty() {
+        println!("No problems that can be fixed automatically were found.");
+        return Ok(());
+    }
+    fix::print_diff(&fixes, std::io::stdout())?;
+
+    let changes = fix::group_changes(&fixes);
+    if !yes && !confirm(&format!("Apply these changes to {} job(s) in JobNimbus?", changes.len()))?
+    {
+        println!("No changes were made.");
+        return Ok(());
+    }
+    for (jnid, job_number, changes) in &changes {
+        fix::apply_changes(jnid, job_number.as_deref(), changes, fix_log, |jnid, fields| {
+            job_nimbus::update_job(api_key, jnid, fields)
+        })?;
+    }
+    println!(
+        "Updated {} job(s). The changes were recorded in {}; undo them with `--undo {}`.",
+        changes.len(),
+        fix_log.display(),
+        fix_log.display()
+    );
+    Ok(())
+}
+
+/// Reverts the changes recorded in the fix log, then moves the log aside so
+/// that the changes aren't reverted again.
+fn undo_fixes(api_key: &str, fix_log: &Path) -> anyhow::Result<()> {
+    let changes = fix::read_undo_changes(fix_log)?;
+    for (jnid, changes) in &changes {
+        let fields =
+            changes.iter().map(|change| (change.field.clone(), change.new.clone())).collect();
+        job_nimbus::update_job(api_key, jnid, &fields)?;
+    }
+    let retired = fix::retire_log(fix_log)?;
+    println!(
+        "Reverted the changes to {} job(s). The fix log was moved to {}.",
+        changes.len(),
+        retired.display()
+    );
+    Ok(())
+}
+
+/// Asks the user a yes-or-no question on the terminal, defaulting to no.
+fn confirm(question: &str) -> std::io::Result<bool> {
+    print!("{} [y/N] ", question);
+    std::io::stdout().flush()?;
+    let mut answer = String::new();
+    std::io::stdin().read_line(&mut answer)?;
+    Ok(matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"))
+}
+
+/// Groups the findings by the sales rep of the job, with the most severe
+/// findings first.
+fn group_by_rep<'a, 'b>(findings: &'b [Finding<'a>]) -> BTreeMap<KpiSubject, Vec<&'b Finding<'a>>> {
+    let mut findings_by_rep: BTreeMap<KpiSubject, Vec<&Finding>> = BTreeMap::new();
+    for finding in findings {
+        let rep = match finding.job.sales_rep.clone() {
+            Some(name) => KpiSubject::SalesRep(name),
+            None => KpiSubject::UnknownSalesRep,
+        };
+        findings_by_rep.entry(rep).or_default().push(finding);
+    }
+    for findings in findings_by_rep.values_mut() {
+        // stable sort, so findings of equal severity stay in rule order
+        findings.sort_by_key(|finding| std::cmp::Reverse(finding.severity));
+    }
+    findings_by_rep
+}
+
+fn job_number<'a>(finding: &Finding<'a>) -> &'a str {
+    finding.job.job_number.as_deref().unwrap_or(&finding.job.jnid)
+}
+
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Record<'a> {
+    Finding {
+        sales_rep: String,
+        severity: Severity,
+        rule: &'a str,
+        job_id: &'a str,
+        job_number: Option<&'a str>,
+        job_name: Option<&'a str>,
+        message: &'a str,
+    },
+}
+
+/// The report has a row for each finding, grouped by sales rep.
+fn report(findings_by_rep: &BTreeMap<KpiSubject, Vec<&Finding>>) -> Report {
+    let mut report = Report::new(format!("Audit Report ({})", Utc::now()));
+    for (rep, findings) in findings_by_rep {
+        for finding in findings {
+            report.record(Record::Finding {
+                sales_rep: rep.to_string(),
+                severity: finding.severity,
+                rule: finding.rule,
+                job_id: &finding.job.jnid,
+                job_number: finding.job.job_number.as_deref(),
+                job_name: finding.job.job_name.as_deref(),
+                message: &finding.message,
+            });
+        }
+    }
+    let table = report.section("Audit").table([
+        "Sales Rep",
+        "Severity",
+        "Rule",
+        "Job Number",
+        "Job Name",
+        "Message",
+    ]);
+    for (rep, findings) in findings_by_rep {
+        for finding in findings {
+            table.row([
+                Cell::text(rep.to_string()),
+                Cell::text(finding.severity.to_string()),
+                Cell::text(finding.rule),
+                Cell::link(job_number(finding), job_nimbus::job_url(&finding.job.jnid)),
+                Cell::text(finding.job.job_name.clone().unwrap_or_default()),
+                Cell::text(finding.message.clone()),
+            ]);
+        }
+    }
+    report
+}
@@ -0,0 +1,598 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs::File,
+    io::BufReader,
+    path::Path,
+};
+
+use anyhow::{bail, Context as _};
+use chrono::{TimeDelta, Utc};
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use crate::{
+    apis::{google_sheets::SheetNickname, job_nimbus},
+    config::Config,
+    jobs::{self, Job, JobKind, Timestamp},
+    report::{self, Cell, OutputFormat, Report},
+    reps, utils,
+};
+
+/// The rules file that is used if none is specified.
+const DEFAULT_RULES_FILE: &str = "commission_rules.json";
+
+#[derive(clap::Args, Debug)]
+pub struct Args {
+    /// The filter to use when query JobNimbus for jobs, using ElasticSearch
+    /// syntax.
+    #[arg(short, long = "filter", default_value = None)]
+    filter_filename: Option<String>,
+
+    /// The file containing the commission rules.
+    #[arg(long = "rules", default_value = DEFAULT_RULES_FILE)]
+    rules_filename: String,
+
+    /// The start of the pay period. Valid options are a date of the form
+    /// "%Y-%m-%d", "ytd" (indicating the start of the current year), "mtd"
+    /// (indicating the start of the current month), "today" (indicating the
+    /// current date), or "forever" (indicating the beginning of time).
+    #[arg(long = "from", default_value = "mtd")]
+    from_date: String,
+    /// The end of the pay period, which includes the whole day. Valid options
+    /// are a date of the form "%Y-%m-%d", "today" (indicating the current
+    /// date), or "forever" (indicating the end of time).
+    #[arg(long = "to", default_value = "today")]
+    to_date: String,
+
+    /// Which date determines the pay period that a job's commission belongs
+    /// to.
+    #[arg(long = "key", value_enum, default_value = "install")]
+    key_date: KeyDate,
+
+    /// The format in which to print the output.
+    #[arg(long, value_enum, default_value = "human")]
+    format: OutputFormat,
+
+    /// The file to write the output to. "-" or unspecified will write to
+    /// stdout. This option is ignored with `--format google-sheets`.
+    #[arg(short, long, default_value = None)]
+    output: Option<String>,
+
+    /// Only valid with `--format google-sheets`. Whether to update an existing
+    /// Google Sheet; if not specified, creates a new Google Sheet.
+    #[arg(long)]
+    update: bool,
+}
+
+#[derive(Debug, clap::ValueEnum, Clone, Copy, Eq, PartialEq)]
+enum KeyDate {
+    /// Pays commission on jobs that were installed during the pay period.
+    Install,
+    /// Pays commission on jobs that were paid in full during the pay period.
+    Paid,
+}
+
+/// How commissions are calculated.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct CommissionRules {
+    /// The rates for insurance jobs (with or without contingency).
+    pub insurance: Vec<Tier>,
+    /// The rates for retail jobs.
+    pub retail: Vec<Tier>,
+    /// The splits of commissions between reps.
+    pub splits: Vec<Split>,
+}
+
+/// A commission rate that applies once a rep's volume for the pay period
+/// reaches a minimum. The rate of the highest tier that a rep reaches applies
+/// to all of the rep's jobs of that kind in the pay period.
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct Tier {
+    /// The minimum total contract value (in dollars) of the rep's jobs in the
+    /// pay period, counting all kinds of jobs.
+    #[serde(default)]
+    pub min_volume: f64,
+    /// The percentage of the contract value that is paid as commission.
+    pub percent: f64,
+}
+
+/// A split of commission between a rep and other reps.
+#[derive(Debug, Deserialize)]
+pub struct Split {
+    /// The rep whose commission is split.
+    pub rep: String,
+    /// The job number of the only job that is split. If unspecified, all of
+    /// the rep's jobs are split. Splits of a specific job take precedence.
+    #[serde(default)]
+    pub job_number: Option<String>,
+    /// The fraction of the commission that goes to each of the other reps.
+    /// The remainder goes to `rep`.
+    pub shares: BTreeMap<String, f64>,
+}
+
+impl CommissionRules {
+    /// Checks that every split gives away a share between 0 and 1 to each rep,
+    /// and no more than the whole commission in total.
+    fn validate(&self) -> anyhow::Result<()> {
+        for split in &self.splits {
+            let jobs = match &split.job_number {
+                Some(job_number) => format!("job {} of {}", job_number, split.rep),
+                None => format!("the jobs of {}", split.rep),
+            };
+            for (rep, share) in &split.shares {
+                if !(0.0..=1.0).contains(share) {
+                    bail!(
+                        "The split of {} gives {} a share of {}, not between 0 and 1",
+                        jobs,
+                        rep,
+                        share
+                    );
+                }
+            }
+            // allow for rounding in shares like thirds
+            let total: f64 = split.shares.values().sum();
+            if total > 1.0 + 1e-9 {
+                bail!("The split of {} gives away shares totaling {}, more than 1", jobs, total);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The commission paid to one rep for one job.
+#[derive(Debug, Clone, PartialEq)]
+struct Payout {
+    rep: String,
+    job_number: String,
+    job_name: String,
+    kind: JobKind,
+    key_date: Timestamp,
+    /// The value of the contract, in cents.
+    contract_value: i64,
+    /// The fraction of the job's commission that goes to this rep.
+    share: f64,
+    /// The commission rate, in percent.
+    percent: f64,
+    /// The commission, in cents.
+    amount: i64,
+}
+
+/// The total commission of one rep for the pay period.
+#[derive(Debug, Default)]
+struct RepSummary {
+    num_jobs: f64,
+    /// The total contract value credited to the rep, in cents.
+    volume: i64,
+    /// The total commission, in cents.
+    amount: i64,
+}
+
+pub fn main(api_key: &str, config: &Config, args: Args) -> anyhow::Result<()> {
+    let Args {
+        filter_filename,
+        rules_filename,
+        from_date,
+        to_date,
+        key_date,
+        format,
+        output,
+        update,
+    } = args;
+    report::check_output_args(format, output.as_deref(), update);
+
+    let rules = read_rules(Path::new(&rules_filename))?;
+
+    let filter = if let Some(filter_filename) = filter_filename {
+        Some(std::fs::read_to_string(filter_filename)?)
+    } else {
+        None
+    };
+    let mut jobs =
+        job_nimbus::get_all_jobs_from_job_nimbus(api_key, filter.as_deref(), &config.job_fields)?;
+    reps::normalize_sales_reps(&mut jobs, config);
+
+    let from_date = utils::parse_from_date(&from_date)?;
+    let to_date = utils::parse_to_date(&to_date)?;
+
+    let jobs: Vec<_> = jobs
+        .into_iter()
+        .filter_map(|job| {
+            let date = match key_date {
+                KeyDate::Install => job.milestone_dates.install_date,
+                KeyDate::Paid => job.paid_date,
+            }?;
+            in_pay_period(date, (from_date, to_date)).then_some((job, date))
+        })
+        .collect();
+    info!("Found {} jobs in the pay period", jobs.len());
+
+    let payouts = calculate_payouts(jobs, &rules, config);
+    let mut summaries: BTreeMap<&str, RepSummary> = BTreeMap::new();
+    for payout in &payouts {
+        let summary = summaries.entry(&payout.rep).or_default();
+        summary.num_jobs += payout.share;
+        summary.volume += (payout.contract_value as f64 * payout.share).round() as i64;
+        summary.amount += payout.amount;
+    }
+
+    report::output(
+        &report(&payouts, &summaries),
+        format,
+        output.as_deref(),
+        update,
+        SheetNickname::Commission,
+    )
+}
+
+/// Whether a date falls in the pay period. The period includes the whole of
+/// its last day, since `--to` only gives the start of that day.
+fn in_pay_period(date: Timestamp, (from, to): (Option<Timestamp>, Option<Timestamp>)) -> bool {
+    from.is_none_or(|from| date >= from) && to.is_none_or(|to| date < to + TimeDelta::days(1))
+}
+
+fn read_rules(path: &Path) -> anyhow::Result<CommissionRules> {
+    info!("Reading commission rules from {}", path.display());
+    let file = File::open(path)
+        .with_context(|| format!("Failed to open commission rules file {}", path.display()))?;
+    let rules: CommissionRules = serde_json::from_reader(BufReader::new(file))
+        .with_context(|| format!("Invalid commission rules file {}", path.display()))?;
+    rules
+        .validate()
+        .with_context(|| format!("Invalid commission rules file {}", path.display()))?;
+    Ok(rules)
+}
+
+/// Calculates the payouts for the specified jobs, each paired with the date
+/// that puts it in the pay period. Payouts are ordered by rep, then by date.
+fn calculate_payouts(
+    jobs: Vec<(Job, Timestamp)>,
+    rules: &CommissionRules,
+    config: &Config,
+) -> Vec<Payout> {
+    // the rep names in the rules are matched the same way as in JobNimbus
+    let canonical_name = |name: &str| match config.find_rep(name) {
+        Some(placement) => placement.rep.name.clone(),
+        None => name.to_owned(),
+    };
+    let splits: Vec<_> = rules
+        .splits
+        .iter()
+        .map(|split| {
+            let shares: Vec<_> =
+                split.shares.iter().map(|(rep, share)| (canonical_name(rep), *share)).collect();
+            (reps::name_key(&canonical_name(&split.rep)), split.job_number.as_deref(), shares)
+        })
+        .collect();
+
+    // split each job between reps
+    let mut shares_of_jobs = Vec::new();
+    for (job, date) in &jobs {
+        let Some(rep) = &job.sales_rep else {
+            warn!(
+                "Job {} has no sales rep, so no commission is paid for it",
+                job.job_number.as_deref().unwrap_or(&job.jnid)
+            );
+            continue;
+        };
+        let kind = match jobs::analyze_job(job.clone()) {
+            (jobs::AnalyzedJob { analysis: Some(analysis), .. }, _) => analysis.kind,
+            _ => {
+                warn!(
+                    "The kind of job {} could not be determined, so no commission is paid for it",
+                    job.job_number.as_deref().unwrap_or(&job.jnid)
+                );
+                continue;
+            }
+        };
+
+        let key = reps::name_key(rep);
+        let split = splits
+            .iter()
+            .find(|(split_rep, job_number, _)| {
+                *split_rep == key
+                    && job_number.is_some()
+                    && *job_number == job.job_number.as_deref()
+            })
+            .or_else(|| {
+                splits
+                    .iter()
+                    .find(|(split_rep, job_number, _)| *split_rep == key && job_number.is_none())
+            });
+        let mut shares = vec![(rep.clone(), 1.0)];
+        if let Some((_, _, other_shares)) = split {
+            for (other_rep, share) in other_shares {
+                shares[0].1 -= share;
+                shares.push((other_rep.clone(), *share));
+            }
+        }
+        shares_of_jobs.push((job, *date, kind, shares));
+    }
+
+    // the tier of each rep depends on the rep's total volume
+    let mut volumes: HashMap<&str, f64> = HashMap::new();
+    for (job, _, _, shares) in &shares_of_jobs {
+        for (rep, share) in shares {
+            *volumes.entry(rep).or_default() += job.amt_contract as f64 / 100.0 * share;
+        }
+    }
+    let rate = |rep: &str, kind: JobKind| {
+        let tiers = match kind {
+            JobKind::InsuranceWithContingency | JobKind::InsuranceWithoutContingency => {
+                &rules.insurance
+            }
+            JobKind::Retail => &rules.retail,
+        };
+        let volume = volumes.get(rep).copied().unwrap_or(0.0);
+        tiers
+            .iter()
+            .filter(|tier| volume >= tier.min_volume)
+            .max_by(|a, b| a.min_volume.total_cmp(&b.min_volume))
+            .map_or(0.0, |tier| tier.percent)
+    };
+
+    let mut payouts = Vec::new();
+    for (job, date, kind, shares) in &shares_of_jobs {
+        for (rep, share) in shares {
+            let percent = rate(rep, *kind);
+            let contract_value = job.amt_contract as i64;
+            payouts.push(Payout {
+                rep: rep.clone(),
+                job_number: job.job_number.clone().unwrap_or_else(|| job.jnid.clone()),
+                job_name: job.job_name.clone().unwrap_or_default(),
+                kind: *kind,
+                key_date: *date,
+                contract_value,
+                share: *share,
+                percent,
+                amount: (contract_value as f64 * share * percent / 100.0).round() as i64,
+            });
+        }
+    }
+    payouts.sort_by(|a, b| (&a.rep, a.key_date).cmp(&(&b.rep, b.key_date)));
+    payouts
+}
+
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Record<'a> {
+    RepSummary {
+        sales_rep: &'a str,
+        /// The number of jobs credited to the rep, counting split jobs by the
+        /// rep's share.
+        jobs: f64,
+        volume: f64,
+        commission: f64,
+    },
+    Payout {
+        sales_rep: &'a str,
+        job_number: &'a str,
+        job_name: &'a str,
+        job_kind: JobKind,
+        /// The install or paid date, as "%Y-%m-%d".
+        date: String,
+        contract_value: f64,
+        share: f64,
+        percent: f64,
+        commission: f64,
+    },
+}
+
+/// The report has a summary of each rep's commission, followed by every
+/// payout.
+fn report(payouts: &[Payout], summaries: &BTreeMap<&str, RepSummary>) -> Report {
+    let mut report = Report::new(format!("Commission Report ({})", Utc::now()));
+    let dollars = |cents: i64| cents as f64 / 100.0;
+    for (rep, summary) in summaries {
+        report.record(Record::RepSummary {
+            sales_rep: rep,
+            jobs: summary.num_jobs,
+            volume: dollars(summary.volume),
+            commission: dollars(summary.amount),
+        });
+    }
+    for payout in payouts {
+        report.record(Record::Payout {
+            sales_rep: &payout.rep,
+            job_number: &payout.job_number,
+            job_name: &payout.job_name,
+            job_kind: payout.kind,
+            date: payout.key_date.date_naive().to_string(),
+            contract_value: dollars(payout.contract_value),
+            share: payout.share,
+            percent: payout.percent,
+            commission: dollars(payout.amount),
+        });
+    }
+
+    let table = report.section("Summary").table(["Sales Rep", "Jobs", "Volume", "Commission"]);
+    for (rep, summary) in summaries {
+        table.row([
+            Cell::text(*rep),
+            Cell::Number(summary.num_jobs),
+            Cell::cents(summary.volume),
+            Cell::cents(summary.amount),
+        ]);
+    }
+
+    let table = report.section("Payouts").table([
+        "Sales Rep",
+        "Job Number",
+        "Job Name",
+        "Job Kind",
+        "Date",
+        "Contract Value",
+        "Share",
+        "Rate (%)",
+        "Commission",
+    ]);
+    for payout in payouts {
+        table.row([
+            Cell::text(payout.rep.clone()),
+            Cell::text(payout.job_number.clone()),
+            Cell::text(payout.job_name.clone()),
+            Cell::text(payout.kind.to_string()),
+            Cell::text(payout.key_date.date_naive().to_string()),
+            Cell::cents(payout.contract_value),
+            Cell::Percent(payout.share),
+            Cell::Number(payout.percent),
+            Cell::cents(payout.amount),
+        ]);
+    }
+    // the payroll CSV only has the payouts
+    let flat = table.clone();
+    report.flat = Some(flat);
+
+    report
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::DateTime;
+
+    use super::*;
+    use crate::{
+        jobs::{test_util::day, MilestoneDates, Status},
+        report::Renderer as _,
+    };
+
+    fn job(job_number: &str, rep: &str, insurance: bool, contract_dollars: i32) -> Job {
+        let ts = DateTime::from_timestamp(1, 0);
+        Job {
+            jnid: job_number.to_owned(),
+            milestone_dates: MilestoneDates {
+                appointment_date: ts,
+                contract_date: ts,
+                install_date: ts,
+                ..MilestoneDates::default()
+            },
+            status: Status::JobCompleted,
+            sales_rep: Some(rep.to_owned()),
+            insurance_checkbox: insurance,
+            insurance_claim_number: insurance.then(|| "123".to_owned()),
+            job_number: Some(job_number.to_owned()),
+            amt_contract: contract_dollars * 100,
+            ..Job::test_default()
+        }
+    }
+
+    #[test]
+    fn tiers_and_splits() {
+        let rules: CommissionRules = serde_json::from_value(serde_json::json!({
+            "insurance": [
+                { "percent": 5.0 },
+                { "min_volume": 20000.0, "percent": 10.0 },
+            ],
+            "retail": [{ "percent": 8.0 }],
+            "splits": [
+                { "rep": "Alice", "job_number": "2", "shares": { "Bob": 0.5 } },
+                { "rep": "Carol", "shares": { "Alice": 0.25 } },
+            ],
+        }))
+        .unwrap();
+        let jobs = vec![
+            job("1", "Alice", true, 10000),
+            job("2", "Alice", false, 10000),
+            job("3", "Carol", true, 8000),
+        ]
+        .into_iter()
+        .map(|job| {
+            let date = job.milestone_dates.install_date.unwrap();
+            (job, date)
+        })
+        .collect();
+        let payouts = calculate_payouts(jobs, &rules, &Config::default());
+        let amounts: Vec<_> = payouts
+            .iter()
+            .map(|payout| (payout.rep.as_str(), payout.job_number.as_str(), payout.amount))
+            .collect();
+
+        // Alice's volume is $10000 + $5000 + $2000 = $17000, below the top
+        // tier. amounts are in cents
+        assert_eq!(
+            amounts,
+            [
+                ("Alice", "1", 50000),
+                ("Alice", "2", 40000),
+                ("Alice", "3", 10000),
+                ("Bob", "2", 40000),
+                ("Carol", "3", 30000),
+            ]
+        );
+
+        // the payroll CSV only has the payouts, with the rate in percent
+        let mut csv = Vec::new();
+        report::csv::Csv.render(&report(&payouts, &BTreeMap::new()), &mut csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        let mut lines = csv.lines();
+        assert_eq!(
+            lines.next(),
+            Some(
+                "Sales Rep,Job Number,Job Name,Job Kind,Date,Contract Value,Share,Rate (%),\
+                 Commission"
+            )
+        );
+        assert!(lines.next().unwrap().ends_with(",1,5,500.00"));
+        assert_eq!(lines.count(), 4);
+    }
+
+    #[test]
+    fn pay_period_includes_its_last_day() {
+        let period = (day(10), day(20));
+        let in_period = |date: Option<Timestamp>| in_pay_period(date.unwrap(), period);
+        assert!(in_period(day(10)));
+        // a job paid in the afternoon of the last day
+        assert!(in_period(day(20).map(|date| date + TimeDelta::hours(15))));
+        assert!(!in_period(day(21)));
+        assert!(!in_period(day(10).map(|date| date - TimeDelta::seconds(1))));
+        assert!(in_pay_period(day(21).unwrap(), (None, None)));
+    }
+
+    #[test]
+    fn splits_resolve_aliases() {
+        let config: Config = serde_json::from_str(
+            r#"{"branches": [{"name": "North", "teams": [
+                {"name": "Team A", "reps": [{"name": "Alice Jones", "aliases": ["A. Jones"]}]}
+            ]}]}"#,
+        )
+        .unwrap();
+        let rules: CommissionRules = serde_json::from_value(serde_json::json!({
+            "retail": [{ "percent": 10.0 }],
+            "splits": [{ "rep": "A. Jones", "shares": { "Bob": 0.5 } }],
+        }))
+        .unwrap();
+        let job = job("1", "Alice Jones", false, 10000);
+        let date = job.milestone_dates.install_date.unwrap();
+        let payouts = calculate_payouts(vec![(job, date)], &rules, &config);
+        let amounts: Vec<_> =
+            payouts.iter().map(|payout| (payout.rep.as_str(), payout.amount)).collect();
+        assert_eq!(amounts, [("Alice Jones", 50000), ("Bob", 50000)]);
+    }
+
+    #[test]
+    fn splits_must_not_give_away_more_than_the_commission() {
+        let validate = |splits: serde_json::Value| {
+            let rules: CommissionRules =
+                serde_json::from_value(serde_json::json!({ "splits": splits })).unwrap();
+            rules.validate().map_err(|error| error.to_string())
+        };
+        assert_eq!(
+            validate(serde_json::json!([
+                { "rep": "Alice", "job_number": "2", "shares": { "Bob": 0.5, "Carol": 0.5 } },
+            ])),
+            Ok(())
+        );
+        assert_eq!(
+            validate(serde_json::json!([
+                { "rep": "Alice", "job_number": "2", "shares": { "Bob": 0.75, "Carol": 0.5 } },
+            ])),
+            Err("The split of job 2 of Alice gives away shares totaling 1.25, more than 1"
+                .to_owned())
+        );
+        assert_eq!(
+            validate(serde_json::json!([{ "rep": "Carol", "shares": { "Alice": -0.25 } }])),
+            Err("The split of the jobs of Carol gives Alice a share of -0.25, not between 0 and 1"
+                .to_owned())
+        );
+    }
+}
@@ -0,0 +1,582 @@
+use std::{collections::BTreeMap, rc::Rc};
+
+use chrono::Utc;
+use rand::Rng;
+use serde::Serialize;
+use tracing::info;
+
+use crate::{
+    apis::{google_sheets::SheetNickname, job_nimbus},
+    config::Config,
+    jobs::{self, AnalyzedJob, Job, JobKind, Milestone},
+    report::{self, Cell, OutputFormat, Report},
+    reps,
+    subcommands::kpi::{processing, KpiSubject},
+};
+
+#[derive(clap::Args, Debug)]
+pub struct Args {
+    /// The filter to use when query JobNimbus for jobs, using ElasticSearch
+    /// syntax.
+    #[arg(short, long = "filter", default_value = None)]
+    filter_filename: Option<String>,
+
+    /// The numbers of days ahead to project, separated by commas.
+    #[arg(long, value_delimiter = ',', default_value = "30,60,90")]
+    horizons: Vec<u32>,
+
+    /// Run a Monte Carlo simulation with the specified number of runs to
+    /// produce a range (10th to 90th percentile) for each projection, instead
+    /// of only the expected values.
+    #[arg(
+        long,
+        value_name = "RUNS",
+        value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..),
+    )]
+    monte_carlo: Option<usize>,
+
+    /// The format in which to print the output.
+    #[arg(long, value_enum, default_value = "human")]
+    format: OutputFormat,
+
+    /// The file to write the output to. "-" or unspecified will write to
+    /// stdout. This option is ignored with `--format google-sheets`.
+    #[arg(short, long, default_value = None)]
+    output: Option<String>,
+
+    /// Only valid with `--format google-sheets`. Whether to update an existing
+    /// Google Sheet; if not specified, creates a new Google Sheet.
+    #[arg(long)]
+    update: bool,
+}
+
+/// The historical likelihood and duration of a job moving from one milestone
+/// to the next milestone that applies to its kind.
+#[derive(Clone, Copy, Debug)]
+struct Transition {
+    to: Milestone,
+    /// The fraction of settled jobs at the previous milestone that went on to
+    /// reach the next milestone.
+    probability: f64,
+    /// The average number of days it took to reach the next milestone.
+    average_days: f64,
+}
+
+/// The transition out of each milestone, for each kind of job. `None` if there
+/// is no next milestone or no historical data.
+type Transitions = [[Option<Transition>; Milestone::NUM_VARIANTS]; JobKind::NUM_VARIANTS];
+
+/// A job that has not been settled yet.
+struct OpenJob {
+    rep: KpiSubject,
+    kind: JobKind,
+    milestone: Milestone,
+    /// The number of days that the job has been at its current milestone.
+    days_at_stage: f64,
+    /// The value of the job if it is installed, in dollars.
+    value: f64,
+}
+
+/// The projected totals within some number of days.
+#[derive(Debug, Clone, Copy, Default)]
+struct Totals {
+    contracts: f64,
+    installs: f64,
+    revenue: f64,
+}
+
+/// A projected quantity. Serialized with the percentiles as `p10` and `p90`.
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(into = "EstimateRecord")]
+struct Estimate {
+    expected: f64,
+    /// The 10th and 90th percentiles. Only available from a Monte Carlo
+    /// simulation.
+    range: Option<(f64, f64)>,
+}
+
+#[derive(Serialize)]
+struct EstimateRecord {
+    expected: f64,
+    p10: Option<f64>,
+    p90: Option<f64>,
+}
+
+impl From<Estimate> for EstimateRecord {
+    fn from(estimate: Estimate) -> Self {
+        EstimateRecord {
+            expected: estimate.expected,
+            p10: estimate.range.map(|(low, _)| low),
+            p90: estimate.range.map(|(_, high)| high),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct Forecast {
+    horizon_days: u32,
+    contracts: Estimate,
+    installs: Estimate,
+    revenue: Estimate,
+}
+
+pub fn main(api_key: &str, config: &Config, args: Args) -> anyhow::Result<()> {
+    let Args { filter_filename, horizons, monte_carlo, format, output, update } = args;
+    report::check_output_args(format, output.as_deref(), update);
+
+    let filter = if let Some(filter_filename) = filter_filename {
+        Some(std::fs::read_to_string(filter_filename)?)
+    } else {
+        None
+    };
+    let mut jobs =
+        job_nimbus::get_all_jobs_from_job_nimbus(api_key, filter.as_deref(), &config.job_fields)?;
+    reps::normalize_sales_reps(&mut jobs, config);
+
+    let (transitions, average_value) = calc_transitions(&jobs, config);
+    let open_jobs = find_open_jobs(jobs, average_value, config);
+    let forecasts = match monte_carlo {
+        None => project_expected(&open_jobs, &transitions, &horizons),
+        Some(runs) => {
+            let rng = &mut rand::thread_rng();
+            project_monte_carlo(&open_jobs, &transitions, &horizons, runs, rng)
+        }
+    };
+
+    report::output(&report(&forecasts), format, output.as_deref(), update, SheetNickname::Forecast)
+}
+
+/// Calculates the historical transitions between milestones from all settled
+/// jobs, as well as the average value of an installed job in dollars.
+fn calc_transitions(jobs: &[Job], config: &Config) -> (Transitions, f64) {
+    let mut tracker = processing::build_job_tracker();
+    let mut total_value = 0.0;
+    let mut num_valued = 0;
+    for job in jobs {
+        let (analyzed, _) = jobs::analyze_job_with_strictness(job.clone(), config.strictness);
+        let AnalyzedJob { job, analysis: Some(analysis) } = &analyzed else {
+            continue;
+        };
+        if analysis.date_settled().is_none() {
+            continue;
+        }
+        if analysis.loss_timestamp.is_none() && job.amt_contract > 0 {
+            total_value += job.amt_contract as f64 / 100.0;
+            num_valued += 1;
+        }
+
+        // use the creation date as the time that the lead was acquired so that
+        // the time it takes to get an appointment is meaningful
+        let mut timestamps = analysis.timestamps.clone();
+        let first_known = timestamps.iter().find_map(|&ts| ts);
+        timestamps[0] =
+            job.date_created.filter(|&created| first_known.is_none_or(|first| created <= first));
+
+        tracker.add_job(
+            &Rc::new(analyzed.clone()),
+            analysis.kind.into_int(),
+            &timestamps,
+            analysis.loss_timestamp,
+        );
+    }
+
+    let mut transitions: Transitions = Default::default();
+    for (kind, row) in transitions.iter_mut().enumerate() {
+        for (milestone, transition) in row.iter_mut().enumerate() {
+            let (Some(from), Some(next)) =
+                (tracker.get_bucket(kind, milestone), tracker.next_milestone(kind, milestone))
+            else {
+                continue;
+            };
+            let to = tracker.get_bucket(kind, next).expect("the next milestone should apply");
+            if from.achieved.is_empty() {
+                continue;
+            }
+            *transition = Some(Transition {
+                to: Milestone::ordered_iter().nth(next).expect("milestone should exist"),
+                probability: to.achieved.len() as f64 / from.achieved.len() as f64,
+                average_days: to.average_achieve_time().map_or(0.0, report::days),
+            });
+        }
+    }
+
+    let average_value = if num_valued == 0 { 0.0 } else { total_value / num_valued as f64 };
+    (transitions, average_value)
+}
+
+/// Finds all jobs that have not been settled yet. Jobs without a contract
+/// value are assumed to be worth the given average value.
+fn find_open_jobs(jobs: Vec<Job>, average_value: f64, config: &Config) -> Vec<OpenJob> {
+    let now = Utc::now();
+    let mut open_jobs = Vec::new();
+    for job in jobs {
+        let (AnalyzedJob { job, analysis: Some(analysis) }, _) =
+            jobs::analyze_job_with_strictness(job, config.strictness)
+        else {
+            continue;
+        };
+        if analysis.date_settled().is_some() {
+            continue;
+        }
+
+        let days_at_stage = analysis
+            .timestamps
+            .iter()
+            .rev()
+            .find_map(|&ts| ts)
+            .or(job.date_created)
+            .map_or(0.0, |date_reached| report::days(now - date_reached));
+        let value =
+            if job.amt_contract > 0 { job.amt_contract as f64 / 100.0 } else { average_value };
+        open_jobs.push(OpenJob {
+            rep: match job.sales_rep {
+                Some(name) => KpiSubject::SalesRep(name),
+                None => KpiSubject::UnknownSalesRep,
+            },
+            kind: analysis.kind,
+            milestone: analysis.current_milestone(),
+            days_at_stage,
+            value,
+        });
+    }
+    info!("Found {} open jobs", open_jobs.len());
+    open_jobs
+}
+
+/// Returns the sequence of transitions that the job must make to be
+/// installed.
+fn path_to_install(job: &OpenJob, transitions: &Transitions) -> Vec<Transition> {
+    let mut path = Vec::new();
+    let mut milestone = job.milestone;
+    while let Some(transition) = transitions[job.kind.into_int()][milestone.into_int()] {
+        path.push(transition);
+        milestone = transition.to;
+    }
+    path
+}
+
+/// Adds the contribution of a job to the totals, given the probability of the
+/// job reaching each milestone within the time limit.
+fn add_to_totals(totals: &mut Totals, job: &OpenJob, contract_prob: f64, install_prob: f64) {
+    totals.contracts += contract_prob;
+    totals.installs += install_prob;
+    totals.revenue += install_prob * job.value;
+}
+
+/// Projects the expected totals within each horizon, assuming that each
+/// transition takes exactly its historical average time. Time already spent at
+/// the current milestone counts towards the first transition.
+fn project_expected(
+    open_jobs: &[OpenJob],
+    transitions: &Transitions,
+    horizons: &[u32],
+) -> BTreeMap<KpiSubject, Vec<Forecast>> {
+    let mut totals: BTreeMap<KpiSubject, Vec<Totals>> = BTreeMap::new();
+    for job in open_jobs {
+        let mut probability = 1.0;
+        let mut days = -job.days_at_stage;
+        let mut arrivals = Vec::new();
+        for transition in path_to_install(job, transitions) {
+            probability *= transition.probability;
+            days = (days + transition.average_days).max(0.0);
+            arrivals.push((transition.to, probability, days));
+        }
+
+        for subject in [KpiSubject::Global, job.rep.clone()] {
+            let subject_totals =
+                totals.entry(subject).or_insert_with(|| vec![Totals::default(); horizons.len()]);
+            for (horizon, totals) in horizons.iter().zip(subject_totals) {
+                let prob_by = |milestone| {
+                    arrivals
+                        .iter()
+                        .find(|(to, _, days)| *to == milestone && *days <= *horizon as f64)
+                        .map_or(0.0, |(_, probability, _)| *probability)
+                };
+                add_to_totals(
+                    totals,
+                    job,
+                    prob_by(Milestone::ContractSigned),
+                    prob_by(Milestone::Installed),
+                );
+            }
+        }
+    }
+
+    let estimate = |expected| Estimate { expected, range: None };
+    totals
+        .into_iter()
+        .map(|(subject, totals)| {
+            let forecasts = horizons
+                .iter()
+                .zip(totals)
+                .map(|(&horizon_days, totals)| Forecast {
+                    horizon_days,
+                    contracts: estimate(totals.contracts),
+                    installs: estimate(totals.installs),
+                    revenue: estimate(totals.revenue),
+                })
+                .collect();
+            (subject, forecasts)
+        })
+        .collect()
+}
+
+/// Projects the totals within each horizon by simulating the future of each
+/// job many times. Each transition succeeds with its historical probability,
+/// and takes an exponentially distributed amount of time with the historical
+/// average as the mean. Since the exponential distribution is memoryless, the
+/// time already spent at the current milestone does not matter.
+fn project_monte_carlo(
+    open_jobs: &[OpenJob],
+    transitions: &Transitions,
+    horizons: &[u32],
+    runs: usize,
+    rng: &mut impl Rng,
+) -> BTreeMap<KpiSubject, Vec<Forecast>> {
+    info!("Running {} Monte Carlo simulations", runs);
+    let paths: Vec<_> = open_jobs.iter().map(|job| path_to_install(job, transitions)).collect();
+
+    // the totals of each run, for each subject and horizon
+    let mut samples: BTreeMap<KpiSubject, Vec<Vec<Totals>>> = BTreeMap::new();
+    for _ in 0..runs {
+        let mut run_totals: BTreeMap<KpiSubject, Vec<Totals>> = BTreeMap::new();
+        run_totals.insert(KpiSubject::Global, vec![Totals::default(); horizons.len()]);
+        for (job, path) in open_jobs.iter().zip(&paths) {
+            let mut days = 0.0;
+            let mut contract_days = None;
+            let mut install_days = None;
+            for transition in path {
+                if rng.gen::<f64>() >= transition.probability {
+                    break;
+                }
+                days += -transition.average_days * (1.0 - rng.gen::<f64>()).ln();
+                match transition.to {
+                    Milestone::ContractSigned => contract_days = Some(days),
+                    Milestone::Installed => install_days = Some(days),
+                    _ => {}
+                }
+            }
+
+            for subject in [KpiSubject::Global, job.rep.clone()] {
+                let subject_totals = run_totals
+                    .entry(subject)
+                    .or_insert_with(|| vec![Totals::default(); horizons.len()]);
+                for (horizon, totals) in horizons.iter().zip(subject_totals) {
+                    let happened_by = |days: Option<f64>| {
+                        if days.is_some_and(|d| d <= *horizon as f64) {
+                            1.0
+                        } else {
+                            0.0
+                        }
+                    };
+                    add_to_totals(
+                        totals,
+                        job,
+                        happened_by(contract_days),
+                        happened_by(install_days),
+                    );
+                }
+            }
+        }
+
+        // subjects that are missing from this run had nothing happen
+        for job in open_jobs {
+            run_totals
+                .entry(job.rep.clone())
+                .or_insert_with(|| vec![Totals::default(); horizons.len()]);
+        }
+        for (subject, totals) in run_totals {
+            samples.entry(subject).or_default().push(totals);
+        }
+    }
+
+    fn summarize(mut values: Vec<f64>) -> Estimate {
+        values.sort_by(f64::total_cmp);
+        let percentile = |q: f64| values[((values.len() - 1) as f64 * q).round() as usize];
+        Estimate {
+            expected: values.iter().sum::<f64>() / values.len() as f64,
+            range: Some((percentile(0.1), percentile(0.9))),
+        }
+    }
+    samples
+        .into_iter()
+        .map(|(subject, runs)| {
+            let forecasts = horizons
+                .iter()
+                .enumerate()
+                .map(|(i, &horizon_days)| Forecast {
+                    horizon_days,
+                    contracts: summarize(runs.iter().map(|run| run[i].contracts).collect()),
+                    installs: summarize(runs.iter().map(|run| run[i].installs).collect()),
+                    revenue: summarize(runs.iter().map(|run| run[i].revenue).collect()),
+                })
+                .collect();
+            (subject, forecasts)
+        })
+        .collect()
+}
+
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Record<'a> {
+    Forecast {
+        subject: String,
+        #[serde(flatten)]
+        forecast: &'a Forecast,
+    },
+}
+
+/// The report has a row for each subject and horizon. The 10th and 90th
+/// percentiles are only included for a Monte Carlo simulation.
+fn report(forecasts: &BTreeMap<KpiSubject, Vec<Forecast>>) -> Report {
+    const HEADERS: [&str; 5] = ["Subject", "Horizon (days)", "Contracts", "Installs", "Revenue"];
+    fn estimate_cells(estimate: &Estimate, cell: fn(f64) -> Cell) -> Vec<Cell> {
+        match estimate.range {
+            Some((low, high)) => vec![cell(estimate.expected), cell(low), cell(high)],
+            None => vec![cell(estimate.expected)],
+        }
+    }
+
+    let mut report = Report::new(format!("Forecast Report ({})", Utc::now()));
+    for (subject, forecasts) in forecasts {
+        for forecast in forecasts {
+            report.record(Record::Forecast { subject: subject.to_string(), forecast });
+        }
+    }
+
+    let ranged = forecasts.values().flatten().any(|forecast| forecast.contracts.range.is_some());
+    let headers = HEADERS.into_iter().flat_map(|header| match header {
+        "Contracts" | "Installs" | "Revenue" if ranged => {
+            vec![header.to_string(), format!("{} (P10)", header), format!("{} (P90)", header)]
+        }
+        _ => vec![header.to_string()],
+    });
+    let table = report.section("Forecast").table(headers);
+    for (subject, forecasts) in forecasts {
+        for forecast in forecasts {
+            let mut cells =
+                vec![Cell::text(subject.to_string()), Cell::Number(forecast.horizon_days as f64)];
+            cells.extend(estimate_cells(&forecast.contracts, Cell::Number));
+            cells.extend(estimate_cells(&forecast.installs, Cell::Number));
+            cells.extend(estimate_cells(&forecast.revenue, Cell::Currency));
+            table.row(cells);
+        }
+    }
+    report
+}
+
+#[cfg(test)]
+mod test {
+    use rand::{rngs::StdRng, SeedableRng as _};
+
+    use super::*;
+    use crate::jobs::{test_util::day, MilestoneDates, Status};
+
+    fn job(milestone_dates: MilestoneDates, amt_contract: i32) -> Job {
+        let status =
+            if milestone_dates.loss_date.is_some() { Status::Lost } else { Status::JobsInProgress };
+        Job { milestone_dates, status, amt_contract, ..Job::test_default() }
+    }
+
+    /// Retail jobs sign a contract after 10 days half of the time, and 75% of
+    /// contracts are installed 20 days later.
+    fn transitions() -> Transitions {
+        let mut transitions: Transitions = Default::default();
+        let retail = &mut transitions[JobKind::Retail.into_int()];
+        retail[Milestone::AppointmentMade.into_int()] = Some(Transition {
+            to: Milestone::ContractSigned,
+            probability: 0.5,
+            average_days: 10.0,
+        });
+        retail[Milestone::ContractSigned.into_int()] =
+            Some(Transition { to: Milestone::Installed, probability: 0.75, average_days: 20.0 });
+        transitions
+    }
+
+    fn open_job(milestone: Milestone, days_at_stage: f64) -> OpenJob {
+        OpenJob {
+            rep: KpiSubject::SalesRep("Jane Doe".to_owned()),
+            kind: JobKind::Retail,
+            milestone,
+            days_at_stage,
+            value: 1000.0,
+        }
+    }
+
+    #[test]
+    fn transitions_come_from_settled_jobs() {
+        let appointment =
+            |appointment_date| MilestoneDates { appointment_date, ..MilestoneDates::default() };
+        let installed = |contract, install| MilestoneDates {
+            contract_date: day(contract),
+            install_date: day(install),
+            ..appointment(day(0))
+        };
+        let jobs = [
+            job(installed(10, 30), 200000),
+            job(installed(20, 30), 400000),
+            job(MilestoneDates { loss_date: day(5), ..appointment(day(0)) }, 0),
+            job(MilestoneDates { loss_date: day(5), ..appointment(day(0)) }, 0),
+            // open jobs don't count
+            job(appointment(day(0)), 0),
+        ];
+
+        let (transitions, average_value) = calc_transitions(&jobs, &Config::default());
+        let retail = &transitions[JobKind::Retail.into_int()];
+        let to_contract = retail[Milestone::AppointmentMade.into_int()].unwrap();
+        assert_eq!(to_contract.to, Milestone::ContractSigned);
+        assert_eq!(to_contract.probability, 0.5);
+        assert_eq!(to_contract.average_days, 15.0);
+        let to_install = retail[Milestone::ContractSigned.into_int()].unwrap();
+        assert_eq!(to_install.to, Milestone::Installed);
+        assert_eq!(to_install.probability, 1.0);
+        assert_eq!(to_install.average_days, 15.0);
+        assert_eq!(retail[Milestone::Installed.into_int()].map(|t| t.to), None);
+        assert_eq!(average_value, 3000.0);
+    }
+
+    #[test]
+    fn expected_totals_accumulate_by_horizon() {
+        let open_jobs = [
+            open_job(Milestone::AppointmentMade, 0.0),
+            // already 15 days into the 20 days it takes to be installed
+            open_job(Milestone::ContractSigned, 15.0),
+        ];
+        let forecasts = project_expected(&open_jobs, &transitions(), &[4, 5, 10, 30]);
+
+        let totals: Vec<_> = forecasts[&KpiSubject::Global]
+            .iter()
+            .map(|f| (f.contracts.expected, f.installs.expected, f.revenue.expected))
+            .collect();
+        assert_eq!(
+            totals,
+            [(0.0, 0.0, 0.0), (0.0, 0.75, 750.0), (0.5, 0.75, 750.0), (0.5, 1.125, 1125.0)]
+        );
+        let rep = &forecasts[&KpiSubject::SalesRep("Jane Doe".to_owned())];
+        assert_eq!(rep[3].installs.expected, 1.125);
+        assert!(rep[3].installs.range.is_none());
+    }
+
+    #[test]
+    fn monte_carlo_agrees_with_the_expected_totals() {
+        let open_jobs = [open_job(Milestone::AppointmentMade, 0.0)];
+        // by a distant horizon, the timing of the transitions doesn't matter
+        let horizons = [10000];
+        let expected = project_expected(&open_jobs, &transitions(), &horizons);
+        let simulated = project_monte_carlo(
+            &open_jobs,
+            &transitions(),
+            &horizons,
+            10000,
+            &mut StdRng::seed_from_u64(7),
+        );
+
+        let (expected, simulated) =
+            (&expected[&KpiSubject::Global][0], &simulated[&KpiSubject::Global][0]);
+        assert!((expected.contracts.expected - simulated.contracts.expected).abs() < 0.02);
+        assert!((expected.installs.expected - simulated.installs.expected).abs() < 0.02);
+        assert!((expected.revenue.expected - simulated.revenue.expected).abs() < 20.0);
+        let (p10, p90) = simulated.installs.range.unwrap();
+        assert_eq!((p10, p90), (0.0, 1.0));
+    }
+}
@@ -2,18 +2,19 @@ use std::collections::BTreeMap;
 use std::fmt::Display;
 use std::path::Path;
 
+use crate::apis::google_sheets::SheetNickname;
 use crate::apis::job_nimbus;
+use crate::config::Config;
+use crate::jobs::Job;
+use crate::report::{self, OutputFormat};
+use crate::reps;
+use crate::utils;
 use crate::CliArgs;
-use anyhow::Context;
-use anyhow::Result;
-use chrono::Datelike as _;
-use chrono::NaiveDate;
-use chrono::NaiveDateTime;
-use chrono::NaiveTime;
-use chrono::TimeZone as _;
-use chrono::Utc;
+use anyhow::{Context as _, Result};
 use clap::CommandFactory as _;
 
+mod goals;
+
 #[derive(clap::Args, Debug)]
 pub struct Args {
     /// The filter to use when query JobNimbus for jobs, using ElasticSearch
@@ -24,9 +25,11 @@ pub struct Args {
     /// The minimum date to filter jobs by. The final report will only include
     /// jobs where the date that they were settled (date of install or date of
     /// loss) is after the minimum date. Valid options are a date of the form
-    /// "%Y-%m-%d", "ytd" (indicating the start of the current year), "today"
-    /// (indicating the current date), or "forever" (indicating the beginning of
-    /// time).
+    /// "%Y-%m-%d", "ytd" (indicating the start of the current year), "mtd"
+    /// (indicating the start of the current month), "today" (indicating the
+    /// current date), or "forever" (indicating the beginning of time).
+    /// Progress towards the reps' monthly targets is only reported if there is
+    /// a minimum date.
     #[arg(long = "from", default_value = "forever")]
     from_date: String,
     /// The maximum date to filter jobs by. The final report will only include
@@ -37,13 +40,32 @@ pub struct Args {
     #[arg(long = "to", default_value = "today")]
     to_date: String,
 
+    /// How to group jobs into reports, in addition to the global report.
+    /// Specifying both (`--by rep,source`) reports on each pair of sales rep
+    /// and lead source.
+    #[arg(long = "by", value_enum, value_delimiter = ',', default_value = "rep")]
+    group_by: Vec<GroupByField>,
+
+    /// Only valid with `--by source`. A CSV file with the marketing cost of
+    /// each lead source over the date range, with the columns "Lead Source"
+    /// and "Cost". Adds the cost per appointment and cost per install of each
+    /// lead source to the report.
+    #[arg(long = "lead-costs", default_value = None)]
+    lead_costs_filename: Option<String>,
+
+    /// The method used to estimate conversion rates and times.
+    #[arg(long, value_enum, default_value = "empirical")]
+    estimator: Estimator,
+
     /// The format in which to print the output.
     #[arg(long, value_enum, default_value = "human")]
     format: OutputFormat,
 
-    /// The directory to write the output to. "-" or unspecified will write
-    /// concatenated file contents to stdout. This option is ignored with
-    /// `--format google-sheets`.
+    /// The directory to write the output to, with a file for each sales rep's
+    /// stats, the red flags, and the lead source costs. Formats that hold the
+    /// whole report in one file (e.g. JSON or Markdown) write to this file
+    /// instead. "-" or unspecified will write concatenated file contents to
+    /// stdout. This option is ignored with `--format google-sheets`.
     #[arg(short, long, default_value = None)]
     output: Option<String>,
 
@@ -54,129 +76,293 @@ pub struct Args {
 }
 
 #[derive(Debug, clap::ValueEnum, Clone, Copy, Eq, PartialEq)]
-enum OutputFormat {
-    /// Prints a set of human-readable .txt files into the output directory (or
-    /// into stdout). Each file corresponds to a sales rep's stats or red flags.
-    Human,
-    /// Prints a set of CSV files into the output directory. Each file
-    /// corresponds to a sales rep's stats, and there is also a CSV file for
-    /// red flags.
-    Csv,
-    /// Outputs a Google Sheet on the user's Google Drive (requires OAuth
-    /// authorization).
-    GoogleSheets,
+enum GroupByField {
+    /// Groups jobs by sales rep.
+    Rep,
+    /// Groups jobs by lead source.
+    Source,
 }
 
-pub fn main(api_key: &str, args: Args) -> Result<()> {
-    let Args { filter_filename, from_date, to_date, format, output, update } = args;
-    if format == OutputFormat::GoogleSheets && output.is_some() {
-        CliArgs::command()
-            .error(
-                clap::error::ErrorKind::ArgumentConflict,
-                "The `--output` option cannot be used with `--format google-sheets`",
-            )
-            .exit();
-    }
-    if format != OutputFormat::GoogleSheets && update {
+#[derive(Debug, clap::ValueEnum, Clone, Copy, Eq, PartialEq)]
+enum Estimator {
+    /// Only considers jobs that were settled within the date range, and
+    /// calculates the fraction of those jobs that made each conversion.
+    Empirical,
+    /// Also considers jobs that were still open at the end of the date range,
+    /// treating them as right-censored observations, and estimates the
+    /// probability of eventually making each conversion with survival
+    /// analysis. This avoids underestimating conversion rates for recent date
+    /// ranges, where many jobs have not had time to settle yet. The average
+    /// time for "All Losses" is counted from the appointment.
+    Survival,
+}
+
+pub fn main(api_key: &str, config: &Config, args: Args) -> Result<()> {
+    let Args {
+        filter_filename,
+        from_date,
+        to_date,
+        group_by,
+        lead_costs_filename,
+        estimator,
+        format,
+        output,
+        update,
+    } = args;
+    let group_by =
+        match (group_by.contains(&GroupByField::Rep), group_by.contains(&GroupByField::Source)) {
+            (true, true) => GroupBy::SalesRepAndLeadSource,
+            (false, true) => GroupBy::LeadSource,
+            _ => GroupBy::SalesRep,
+        };
+    if group_by != GroupBy::LeadSource && lead_costs_filename.is_some() {
         CliArgs::command()
             .error(
                 clap::error::ErrorKind::ArgumentConflict,
-                "The `--update` option can only be used with `--format google-sheets`",
+                "The `--lead-costs` option can only be used with `--by source`",
             )
             .exit();
     }
+    report::check_output_args(format, output.as_deref(), update);
 
     let filter = if let Some(filter_filename) = filter_filename {
         Some(std::fs::read_to_string(filter_filename)?)
     } else {
         None
     };
-    let jobs = job_nimbus::get_all_jobs_from_job_nimbus(&api_key, filter.as_deref())?;
-
-    let from_date = match from_date.as_str() {
-        "forever" => None,
-        "ytd" => Some(
-            Utc.from_utc_datetime(&NaiveDateTime::new(
-                NaiveDate::from_ymd_opt(Utc::now().year(), 1, 1)
-                    .expect("Jan 1 should always be valid in the current year."),
-                NaiveTime::MIN,
-            )),
-        ),
-        "today" => Some(Utc::now()),
-        date_string => Some(
-            NaiveDate::parse_from_str(date_string, "%Y-%m-%d")
-                .map(|date| Utc.from_utc_datetime(&NaiveDateTime::new(date, NaiveTime::MIN)))
-                .context("Invalid date format. Use 'forever', 'ytd', 'today', or '%Y-%m-%d'.")?,
-        ),
+    let mut jobs =
+        job_nimbus::get_all_jobs_from_job_nimbus(&api_key, filter.as_deref(), &config.job_fields)?;
+
+    reps::normalize_sales_reps(&mut jobs, config);
+
+    let from_date = utils::parse_from_date(&from_date)?;
+    let to_date = utils::parse_to_date(&to_date)?;
+
+    let (trackers, red_flags) =
+        processing::process_jobs(jobs.iter().cloned(), (from_date, to_date), group_by, config);
+    let mut tracker_stats = match estimator {
+        Estimator::Empirical => trackers
+            .into_iter()
+            .map(|(rep, tracker)| (rep, processing::calculate_job_tracker_stats(&tracker)))
+            .collect::<BTreeMap<_, _>>(),
+        Estimator::Survival => processing::process_jobs_survival(
+            jobs.into_iter(),
+            (from_date, to_date),
+            group_by,
+            config,
+        )
+        .into_iter()
+        .map(|(rep, tracker)| (rep, processing::calculate_survival_tracker_stats(&tracker)))
+        .collect::<BTreeMap<_, _>>(),
     };
-    let to_date = match to_date.as_str() {
-        "forever" => None,
-        "today" => Some(Utc::now()),
-        date_string => Some(
-            NaiveDate::parse_from_str(date_string, "%Y-%m-%d")
-                .map(|date| Utc.from_utc_datetime(&NaiveDateTime::new(date, NaiveTime::MIN)))
-                .context("Invalid date format. Use 'forever', 'ytd', 'today', or '%Y-%m-%d'.")?,
-        ),
+    tracker_stats.retain(|_, stats| stats.appt_count > 0);
+
+    let targets = goals::load_targets(config)?;
+    let period =
+        if targets.is_empty() { None } else { goals::GoalPeriod::new(from_date, to_date)? };
+    let goals = match period {
+        Some(period) => tracker_stats
+            .iter()
+            .filter_map(|(subject, stats)| {
+                let KpiSubject::SalesRep(name) = subject else {
+                    return None;
+                };
+                let targets = targets.get(&reps::name_key(name))?;
+                Some((subject.clone(), period.progress(targets, stats)))
+            })
+            .collect(),
+        _ => BTreeMap::new(),
     };
 
-    let (trackers, red_flags) = processing::process_jobs(jobs.into_iter(), (from_date, to_date));
-    let tracker_stats = trackers
-        .into_iter()
-        .map(|(rep, tracker)| (rep, processing::calculate_job_tracker_stats(&tracker)))
-        .filter(|(_, stats)| stats.appt_count > 0)
-        .collect::<BTreeMap<_, _>>();
-
-    let output = output.filter(|s| s != "-");
-    let output = output.as_deref().map(|path| Path::new(path));
-    match format {
-        OutputFormat::Human => output::print_report_human(&tracker_stats, &red_flags, output)?,
-        OutputFormat::Csv => output::print_report_csv(&tracker_stats, &red_flags, output)?,
-        OutputFormat::GoogleSheets => {
-            output::generate_report_google_sheets(&tracker_stats, &red_flags, update)?
+    let lead_costs = match lead_costs_filename {
+        Some(lead_costs_filename) => {
+            lead_source_costs(read_lead_costs(Path::new(&lead_costs_filename))?, &tracker_stats)
         }
-    }
+        None => Vec::new(),
+    };
+
+    let report = output::report(&tracker_stats, &red_flags, &goals, &lead_costs);
+    report::output_sections(&report, format, output.as_deref(), update, SheetNickname::Kpi)?;
 
     Ok(())
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
-enum KpiSubject {
+pub enum KpiSubject {
     Global,
+    Branch(String),
+    Team(String),
     SalesRep(String),
     UnknownSalesRep,
+    LeadSource(String),
+    UnknownLeadSource,
+    SalesRepAndLeadSource { sales_rep: Option<String>, lead_source: Option<String> },
 }
 impl Display for KpiSubject {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             KpiSubject::Global => write!(f, "[Global]"),
+            KpiSubject::Branch(name) => write!(f, "[Branch] {}", name),
+            KpiSubject::Team(name) => write!(f, "[Team] {}", name),
             KpiSubject::SalesRep(name) => write!(f, "{}", name),
             KpiSubject::UnknownSalesRep => write!(f, "[Unknown]"),
+            KpiSubject::LeadSource(source) => write!(f, "{}", source),
+            KpiSubject::UnknownLeadSource => write!(f, "[Unknown Source]"),
+            KpiSubject::SalesRepAndLeadSource { sales_rep, lead_source } => write!(
+                f,
+                "{} ({})",
+                sales_rep.as_deref().unwrap_or("[Unknown]"),
+                lead_source.as_deref().unwrap_or("[Unknown Source]")
+            ),
         }
     }
 }
 
-mod processing {
+/// Determines which [`KpiSubject`] (besides [`KpiSubject::Global`]) each job
+/// counts towards.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GroupBy {
+    SalesRep,
+    LeadSource,
+    SalesRepAndLeadSource,
+}
+impl GroupBy {
+    pub fn subject_of(self, job: &Job) -> KpiSubject {
+        match self {
+            GroupBy::SalesRep => match job.sales_rep.clone() {
+                Some(name) => KpiSubject::SalesRep(name),
+                None => KpiSubject::UnknownSalesRep,
+            },
+            GroupBy::LeadSource => match job.lead_source.clone() {
+                Some(source) => KpiSubject::LeadSource(source),
+                None => KpiSubject::UnknownLeadSource,
+            },
+            GroupBy::SalesRepAndLeadSource => KpiSubject::SalesRepAndLeadSource {
+                sales_rep: job.sales_rep.clone(),
+                lead_source: job.lead_source.clone(),
+            },
+        }
+    }
+}
+
+/// The marketing cost of a lead source over the date range of the report,
+/// along with what it produced.
+#[derive(Debug)]
+pub struct LeadSourceCost {
+    pub lead_source: String,
+    /// The cost, in dollars.
+    pub cost: f64,
+    pub appt_count: usize,
+    pub install_count: usize,
+}
+impl LeadSourceCost {
+    /// The cost per appointment. `None` if there were no appointments.
+    pub fn cost_per_appt(&self) -> Option<f64> {
+        (self.appt_count > 0).then(|| self.cost / self.appt_count as f64)
+    }
+
+    /// The cost per install. `None` if there were no installs.
+    pub fn cost_per_install(&self) -> Option<f64> {
+        (self.install_count > 0).then(|| self.cost / self.install_count as f64)
+    }
+}
+
+/// Reads the marketing cost of each lead source from a CSV file with the
+/// columns "Lead Source" and "Cost".
+fn read_lead_costs(path: &Path) -> Result<BTreeMap<String, f64>> {
+    #[derive(serde::Deserialize)]
+    struct Record {
+        #[serde(rename = "Lead Source")]
+        lead_source: String,
+        #[serde(rename = "Cost")]
+        cost: String,
+    }
+
+    let mut lead_costs = BTreeMap::new();
+    let mut reader = csv::Reader::from_path(path)
+        .with_context(|| format!("Failed to open lead costs file {}", path.display()))?;
+    for record in reader.deserialize() {
+        let Record { lead_source, cost } = record?;
+        // allow costs to be formatted like "$1,234.56"
+        let cost: f64 =
+            cost.trim().trim_start_matches('$').replace(',', "").parse().with_context(|| {
+                format!("Invalid cost {:?} for lead source {}", cost, lead_source)
+            })?;
+        *lead_costs.entry(lead_source).or_default() += cost;
+    }
+    Ok(lead_costs)
+}
+
+/// Matches the cost of each lead source with the appointments and installs
+/// that it produced. Sources without any jobs are still included, so that
+/// their cost shows up in the report.
+fn lead_source_costs(
+    lead_costs: BTreeMap<String, f64>,
+    tracker_stats: &BTreeMap<KpiSubject, processing::JobTrackerStats>,
+) -> Vec<LeadSourceCost> {
+    lead_costs
+        .into_iter()
+        .map(|(lead_source, cost)| {
+            let stats = tracker_stats.get(&KpiSubject::LeadSource(lead_source.clone()));
+            LeadSourceCost {
+                lead_source,
+                cost,
+                appt_count: stats.map_or(0, |stats| stats.appt_count),
+                install_count: stats.map_or(0, |stats| stats.install_count),
+            }
+        })
+        .collect()
+}
+
+pub mod processing {
     use std::{collections::HashMap, rc::Rc};
 
+    use serde::Serialize;
     use tracing::info;
 
+    use chrono::Utc;
+
     use crate::{
-        job_tracker::{self, CalcStatsResult, JobTracker},
+        job_tracker::{self, CalcStatsResult, JobTracker, SurvivalStatsResult, SurvivalTracker},
         jobs::{
             self, AnalyzedJob, Job, JobAnalysisError, JobKind, Milestone, TimeDelta, Timestamp,
         },
+        report::json,
     };
 
-    use super::KpiSubject;
+    use crate::config::Config;
+
+    use super::{GroupBy, KpiSubject};
 
     pub type TrackersAndFlags = (
         HashMap<KpiSubject, JobTracker3x5>,
         HashMap<KpiSubject, Vec<(Rc<AnalyzedJob>, JobAnalysisError)>>,
     );
 
+    /// The subjects that a job counts towards: the global subject, the
+    /// subject determined by `group_by`, and, when grouping by sales rep, the
+    /// team and branch of the rep.
+    fn subjects_of(job: &Job, group_by: GroupBy, config: &Config) -> Vec<KpiSubject> {
+        let mut subjects = vec![KpiSubject::Global, group_by.subject_of(job)];
+        if group_by == GroupBy::SalesRep {
+            if let Some(placement) = job.sales_rep.as_deref().and_then(|name| config.find_rep(name))
+            {
+                subjects.push(KpiSubject::Team(placement.team.name.clone()));
+                subjects.push(KpiSubject::Branch(placement.branch.name.clone()));
+            }
+        }
+        subjects
+    }
+
+    /// Builds a tracker for each subject, grouping jobs according to
+    /// `group_by` and rolling reps up into their teams and branches. Red flags
+    /// are always grouped by sales rep, since the rep is the one responsible
+    /// for fixing them.
     pub fn process_jobs(
         jobs: impl Iterator<Item = Job>,
         (from_dt, to_dt): (Option<Timestamp>, Option<Timestamp>),
+        group_by: GroupBy,
+        config: &Config,
     ) -> TrackersAndFlags {
         info!(
             "Processing jobs settled between {} and {}",
@@ -187,12 +373,8 @@ mod processing {
         let mut trackers = HashMap::new();
         let mut red_flags = HashMap::new();
         for job in jobs {
-            let (analyzed, errors) = jobs::analyze_job(job);
+            let (analyzed, errors) = jobs::analyze_job_with_strictness(job, config.strictness);
             let analyzed = Rc::new(analyzed);
-            let target = match analyzed.job.sales_rep.clone() {
-                Some(name) => KpiSubject::SalesRep(name),
-                None => KpiSubject::UnknownSalesRep,
-            };
             if let AnalyzedJob { analysis: Some(analysis), .. } = analyzed.as_ref() {
                 // only add jobs that were settled
                 if let Some(date_settled) = analysis.date_settled() {
@@ -201,27 +383,21 @@ mod processing {
                         && (to_dt.is_none() || date_settled <= to_dt.unwrap())
                     {
                         let kind = analysis.kind.into_int();
-                        trackers
-                            .entry(KpiSubject::Global)
-                            .or_insert_with(build_job_tracker)
-                            .add_job(
+                        for subject in subjects_of(&analyzed.job, group_by, config) {
+                            trackers.entry(subject).or_insert_with(build_job_tracker).add_job(
                                 &analyzed,
                                 kind,
                                 &analysis.timestamps,
                                 analysis.loss_timestamp,
                             );
-                        trackers.entry(target.clone()).or_insert_with(build_job_tracker).add_job(
-                            &analyzed,
-                            kind,
-                            &analysis.timestamps,
-                            analysis.loss_timestamp,
-                        );
+                        }
                     }
                 }
             }
 
             if !errors.is_empty() {
-                let sales_rep_errors: &mut Vec<_> = red_flags.entry(target).or_default();
+                let sales_rep = GroupBy::SalesRep.subject_of(&analyzed.job);
+                let sales_rep_errors: &mut Vec<_> = red_flags.entry(sales_rep).or_default();
                 for error in errors {
                     sales_rep_errors.push((analyzed.clone(), error));
                 }
@@ -231,10 +407,70 @@ mod processing {
         (trackers, red_flags)
     }
 
-    type JobTracker3x5 =
+    /// Like `process_jobs`, but builds trackers for survival analysis, which
+    /// also include the jobs that were still open at the end of the date
+    /// range. Jobs that were settled after the end of the date range are
+    /// treated as if they were still open at that time.
+    pub fn process_jobs_survival(
+        jobs: impl Iterator<Item = Job>,
+        (from_dt, to_dt): (Option<Timestamp>, Option<Timestamp>),
+        group_by: GroupBy,
+        config: &Config,
+    ) -> HashMap<KpiSubject, SurvivalTracker3x5> {
+        let now = Utc::now();
+        let censor_dt = to_dt.map_or(now, |to_dt| to_dt.min(now));
+
+        let mut trackers = HashMap::new();
+        for job in jobs {
+            let (analyzed, _) = jobs::analyze_job_with_strictness(job, config.strictness);
+            let analyzed = Rc::new(analyzed);
+            let AnalyzedJob { analysis: Some(analysis), .. } = analyzed.as_ref() else {
+                continue;
+            };
+            let kind = analysis.kind.into_int();
+
+            let (timestamps, loss_timestamp, censor_timestamp) = match analysis.date_settled() {
+                // skip jobs that were settled before the date range
+                Some(date_settled) if from_dt.is_some_and(|from_dt| date_settled < from_dt) => {
+                    continue;
+                }
+                Some(date_settled) if date_settled <= censor_dt => {
+                    (&analysis.timestamps[..], analysis.loss_timestamp, None)
+                }
+                _ => {
+                    // skip jobs that did not exist yet at the end of the range
+                    if analyzed.job.date_created.is_some_and(|created| created > censor_dt) {
+                        continue;
+                    }
+                    // only keep the milestones that were reached by the end of
+                    // the date range
+                    let reached = analysis
+                        .timestamps
+                        .iter()
+                        .rposition(|ts| ts.is_some_and(|ts| ts <= censor_dt))
+                        .map_or(1, |i| i + 1);
+                    (&analysis.timestamps[..reached], None, Some(censor_dt))
+                }
+            };
+
+            for subject in subjects_of(&analyzed.job, group_by, config) {
+                trackers.entry(subject).or_insert_with(build_survival_tracker).add_job(
+                    &analyzed,
+                    kind,
+                    timestamps,
+                    loss_timestamp,
+                    censor_timestamp,
+                );
+            }
+        }
+
+        trackers
+    }
+
+    pub type JobTracker3x5 =
         JobTracker<{ JobKind::NUM_VARIANTS }, { Milestone::NUM_VARIANTS }, Rc<AnalyzedJob>>;
 
-    fn build_job_tracker() -> JobTracker3x5 {
+    pub fn build_job_tracker() -> JobTracker3x5 {
         JobTracker::new([
             [true, true, true, true, true],
             [true, true, false, true, true],
@@ -242,27 +478,58 @@ mod processing {
         ])
     }
 
-    #[derive(Debug)]
+    pub type SurvivalTracker3x5 =
+        SurvivalTracker<{ JobKind::NUM_VARIANTS }, { Milestone::NUM_VARIANTS }, Rc<AnalyzedJob>>;
+
+    fn build_survival_tracker() -> SurvivalTracker3x5 {
+        SurvivalTracker::new([
+            [true, true, true, true, true],
+            [true, true, false, true, true],
+            [true, true, false, true, true],
+        ])
+    }
+
+    /// Serialized with the names of the conversions as they appear in the
+    /// reports.
+    #[derive(Debug, Serialize)]
     pub struct JobTrackerStats {
         pub appt_count: usize,
         pub install_count: usize,
+        #[serde(rename = "all_losses")]
         pub loss_conv: ConversionStats,
+        #[serde(rename = "insurance_appt_to_contingency")]
         pub appt_continge_conv: ConversionStats,
+        #[serde(rename = "insurance_appt_to_contract")]
         pub appt_contract_insure_conv: ConversionStats,
+        #[serde(rename = "insurance_contingency_to_contract")]
         pub continge_contract_conv: ConversionStats,
+        #[serde(rename = "retail_appt_to_contract")]
         pub appt_contract_retail_conv: ConversionStats,
+        #[serde(rename = "insurance_contract_to_installation")]
         pub install_insure_conv: ConversionStats,
+        #[serde(rename = "retail_contract_to_installation")]
         pub install_retail_conv: ConversionStats,
     }
 
-    #[derive(Debug)]
+    impl JobTrackerStats {
+        /// The number of jobs that had a contract signed, of any kind.
+        pub fn contract_count(&self) -> usize {
+            self.appt_contract_insure_conv.achieved.len()
+                + self.continge_contract_conv.achieved.len()
+                + self.appt_contract_retail_conv.achieved.len()
+        }
+    }
+
+    #[derive(Debug, Serialize)]
     pub struct ConversionStats {
         /// All the jobs that made the conversion.
+        #[serde(rename = "job_ids", serialize_with = "json::job_ids")]
         pub achieved: Vec<Rc<AnalyzedJob>>,
         /// The rate of conversion. `None` if no jobs made the conversion.
         pub conversion_rate: Option<f64>,
         /// The average amount of time for a successful conversion. Zero if no
         /// jobs made the conversion.
+        #[serde(rename = "average_days", serialize_with = "json::days")]
         pub average_time_to_achieve: TimeDelta,
     }
 
@@ -358,57 +625,164 @@ mod processing {
             install_retail_conv,
         }
     }
+
+    pub fn calculate_survival_tracker_stats(tracker: &SurvivalTracker3x5) -> JobTrackerStats {
+        let iwc = JobKind::InsuranceWithContingency.into_int(); // "insurance with contingency"
+        let iwo = JobKind::InsuranceWithoutContingency.into_int(); // "insurance without contingency"
+        let ret = JobKind::Retail.into_int(); // "retail"
+
+        let appt = Milestone::AppointmentMade.into_int();
+        let continge = Milestone::ContingencySigned.into_int();
+        let contract = Milestone::ContractSigned.into_int();
+        let install = Milestone::Installed.into_int();
+
+        fn into_conversion_stats(result: SurvivalStatsResult<Rc<AnalyzedJob>>) -> ConversionStats {
+            let SurvivalStatsResult { achieved, probability, expected_time_to_achieve } = result;
+            ConversionStats {
+                achieved,
+                conversion_rate: probability,
+                average_time_to_achieve: expected_time_to_achieve.unwrap_or(TimeDelta::zero()),
+            }
+        }
+
+        JobTrackerStats {
+            appt_count: tracker.achieved(appt, &[iwc, iwo, ret]).len(),
+            install_count: tracker.achieved(install, &[iwc, iwo, ret]).len(),
+            loss_conv: into_conversion_stats(tracker.calc_stats_of_loss(appt, &[iwc, iwo, ret])),
+            appt_continge_conv: into_conversion_stats(tracker.calc_stats(
+                appt,
+                continge,
+                &[iwc, iwo],
+            )),
+            appt_contract_insure_conv: into_conversion_stats(tracker.calc_stats(
+                appt,
+                contract,
+                &[iwc, iwo],
+            )),
+            continge_contract_conv: into_conversion_stats(tracker.calc_stats(
+                continge,
+                contract,
+                &[iwc],
+            )),
+            appt_contract_retail_conv: into_conversion_stats(tracker.calc_stats(
+                appt,
+                contract,
+                &[ret],
+            )),
+            install_insure_conv: into_conversion_stats(tracker.calc_stats(
+                contract,
+                install,
+                &[iwc, iwo],
+            )),
+            install_retail_conv: into_conversion_stats(tracker.calc_stats(
+                contract,
+                install,
+                &[ret],
+            )),
+        }
+    }
 }
 
 mod output {
-    use std::{
-        io::{BufWriter, Write},
-        path::Path,
-        rc::Rc,
-    };
+    use std::{collections::BTreeMap, rc::Rc};
 
     use chrono::Utc;
+    use serde::Serialize;
 
     use crate::{
-        apis::google_sheets::{
-            self,
-            spreadsheet::{
-                CellData, ExtendedValue, GridData, RowData, Sheet, SheetProperties, Spreadsheet,
-                SpreadsheetProperties,
-            },
-        },
-        jobs::{AnalyzedJob, JobAnalysisError, TimeDelta},
-        utils,
+        apis::job_nimbus,
+        jobs::{AnalyzedJob, JobAnalysisError},
+        report::{Cell, Chart, ChartKind, Condition, Highlight, Report},
     };
 
-    use super::{processing::JobTrackerStats, KpiSubject};
+    use super::{goals::GoalProgress, processing::JobTrackerStats, KpiSubject, LeadSourceCost};
+
+    #[derive(Serialize)]
+    #[serde(tag = "type", rename_all = "snake_case")]
+    enum Record<'a> {
+        /// The stats of a sales rep, or of whatever else the jobs are grouped
+        /// by.
+        Stats { subject: String, stats: &'a JobTrackerStats },
+        /// A problem with a job that kept it out of the stats, or that
+        /// suggests it was entered incorrectly.
+        RedFlag {
+            subject: String,
+            job_id: &'a str,
+            job_number: Option<&'a str>,
+            error: &'a JobAnalysisError,
+            message: String,
+        },
+        Goal {
+            subject: String,
+            name: &'a str,
+            actual: usize,
+            target: f64,
+            projected: Option<f64>,
+            attainment: Option<f64>,
+            pace: Option<f64>,
+        },
+        LeadSourceCost {
+            lead_source: &'a str,
+            cost: f64,
+            appt_count: usize,
+            install_count: usize,
+            cost_per_appt: Option<f64>,
+            cost_per_install: Option<f64>,
+        },
+    }
+
+    /// Conversion rates below this are highlighted.
+    const LOW_CONVERSION_RATE: f64 = 0.25;
+
+    const CONVERSION_HEADERS: [&str; 5] =
+        ["Conversion", "Rate", "Total", "Avg Time (days)", "Jobs"];
+    const COUNT_HEADERS: [&str; 2] = ["Appts", "Installed"];
+    const GOAL_HEADERS: [&str; 5] = ["Goal", "Attained", "Actual", "Target", "Pace"];
+    const RED_FLAG_HEADERS: [&str; 3] = ["Sales Rep", "Job Number", "Error"];
+    const LEAD_COST_HEADERS: [&str; 6] =
+        ["Lead Source", "Cost", "Appts", "Installed", "Cost per Appt", "Cost per Install"];
 
-    pub fn print_report_human<'a>(
+    /// The report has a section with the stats of each subject, followed by a
+    /// section with the red flags, and a section with the lead source costs if
+    /// there are any.
+    pub fn report<'a>(
         tracker_stats: impl IntoIterator<Item = (&'a KpiSubject, &'a JobTrackerStats)>,
         red_flags: impl IntoIterator<
             Item = (&'a KpiSubject, &'a Vec<(Rc<AnalyzedJob>, JobAnalysisError)>),
         >,
-        output_dir: Option<&Path>,
-    ) -> std::io::Result<()> {
-        // make sure that output_dir exists
-        if let Some(output_dir) = output_dir {
-            std::fs::create_dir_all(output_dir)?;
-        }
+        goals: &BTreeMap<KpiSubject, Vec<GoalProgress>>,
+        lead_costs: &[LeadSourceCost],
+    ) -> Report {
+        let mut report = Report::new(format!("KPI Report ({})", Utc::now()));
 
-        for (rep, stats) in tracker_stats {
-            // create the file for this rep
-            let mut out: Box<dyn Write> = if let Some(output_dir) = output_dir {
-                Box::new(BufWriter::new(
-                    std::fs::File::create(output_dir.join(format!("rep-{}-stats.txt", rep)))
-                        .expect("the directory should exist"),
-                ))
-            } else {
-                Box::new(std::io::stdout())
-            };
+        for (subject, stats) in tracker_stats {
+            report.record(Record::Stats { subject: subject.to_string(), stats });
+            if *subject == KpiSubject::Global {
+                report.key_figure("Appointments", Cell::Number(stats.appt_count as f64));
+                report.key_figure("Installs", Cell::Number(stats.install_count as f64));
+                report.key_figure(
+                    "(I) Appt to Contract",
+                    Cell::percent(stats.appt_contract_insure_conv.conversion_rate),
+                );
+                report.key_figure(
+                    "(R) Appt to Contract",
+                    Cell::percent(stats.appt_contract_retail_conv.conversion_rate),
+                );
+            }
+            for goal in goals.get(subject).into_iter().flatten() {
+                report.record(Record::Goal {
+                    subject: subject.to_string(),
+                    name: goal.name,
+                    actual: goal.actual,
+                    target: goal.target,
+                    projected: goal.projected,
+                    attainment: goal.attainment(),
+                    pace: goal.pace(),
+                });
+            }
 
-            // print the report into the file
-            writeln!(out, "Tracker for {}: ================", rep)?;
-            writeln!(out, "Appts {} | Installed {}", stats.appt_count, stats.install_count)?;
+            let section = report.section(format!("Stats {}", subject));
+            let conversions = section.table(CONVERSION_HEADERS);
             for (name, conv_stats) in [
                 ("All Losses", &stats.loss_conv),
                 ("(I) Appt to Contingency", &stats.appt_continge_conv),
@@ -418,266 +792,240 @@ mod output {
                 ("(I) Contract to Installation", &stats.install_insure_conv),
                 ("(R) Contract to Installation", &stats.install_retail_conv),
             ] {
-                writeln!(
-                    out,
-                    "{:30}    Rate {} | Total {:2} | Avg Time {:.2} days",
-                    name,
-                    percent_or_na(conv_stats.conversion_rate),
-                    conv_stats.achieved.len(),
-                    into_days(conv_stats.average_time_to_achieve),
-                )?;
-                if *rep != KpiSubject::Global {
-                    writeln!(out, "    - {}", into_list_of_job_nums(&conv_stats.achieved))?;
+                conversions.row([
+                    Cell::text(name),
+                    Cell::percent(conv_stats.conversion_rate),
+                    Cell::Number(conv_stats.achieved.len() as f64),
+                    Cell::days(conv_stats.average_time_to_achieve),
+                    job_links(&conv_stats.achieved),
+                ]);
+            }
+            // the first row is the losses, where a low rate is good
+            let num_rows = conversions.rows.len();
+            conversions.highlights.push(Highlight {
+                column: 1,
+                rows: Some(1..num_rows),
+                condition: Condition::Below(LOW_CONVERSION_RATE),
+            });
+            conversions.charts.push(Chart {
+                title: format!("Conversion Rates ({})", subject),
+                kind: ChartKind::Bar,
+                labels: 0,
+                values: 1,
+                rows: Some(1..num_rows),
+            });
+
+            section.table(COUNT_HEADERS).titled("Counts").row([
+                Cell::Number(stats.appt_count as f64),
+                Cell::Number(stats.install_count as f64),
+            ]);
+
+            if let Some(goals) = goals.get(subject) {
+                let table = section.table(GOAL_HEADERS).titled("Goals");
+                for goal in goals {
+                    table.row([
+                        Cell::text(goal.name.to_string()),
+                        Cell::percent(goal.attainment()),
+                        Cell::Number(goal.actual as f64),
+                        Cell::Number(goal.target),
+                        Cell::percent(goal.pace()),
+                    ]);
+                }
+                for column in [1, 4] {
+                    table.highlights.push(Highlight {
+                        column,
+                        rows: None,
+                        condition: Condition::Below(1.0),
+                    });
                 }
             }
-            writeln!(out, "")?;
-            out.flush()?;
         }
 
-        let mut out: Box<dyn Write> = if let Some(output_dir) = output_dir {
-            Box::new(BufWriter::new(
-                std::fs::File::create(output_dir.join("red-flags.txt"))
-                    .expect("the directory should exist"),
-            ))
-        } else {
-            Box::new(std::io::stdout())
-        };
-        for (rep, red_flags) in red_flags {
-            writeln!(out, "Red flags for {}: ===============", rep)?;
-            for (job, err) in red_flags {
-                writeln!(
-                    out,
-                    "{}: {}",
+        let red_flags: Vec<_> = red_flags
+            .into_iter()
+            .flat_map(|(subject, red_flags)| red_flags.iter().map(move |flag| (subject, flag)))
+            .collect();
+        report.key_figure("Red Flags", Cell::Number(red_flags.len() as f64));
+        for (subject, (job, err)) in &red_flags {
+            report.record(Record::RedFlag {
+                subject: subject.to_string(),
+                job_id: &job.job.jnid,
+                job_number: job.job.job_number.as_deref(),
+                error: err,
+                message: err.to_string(),
+            });
+        }
+        let table = report.section("Red Flags").table(RED_FLAG_HEADERS).listed();
+        for (subject, (job, err)) in red_flags {
+            table.row([
+                Cell::text(subject.to_string()),
+                Cell::link(
                     job.job.job_number.as_deref().unwrap_or("unknown job #"),
-                    err
-                )?;
+                    job_nimbus::job_url(&job.job.jnid),
+                ),
+                Cell::text(err.to_string()),
+            ]);
+        }
+
+        for lead_cost in lead_costs {
+            report.record(Record::LeadSourceCost {
+                lead_source: &lead_cost.lead_source,
+                cost: lead_cost.cost,
+                appt_count: lead_cost.appt_count,
+                install_count: lead_cost.install_count,
+                cost_per_appt: lead_cost.cost_per_appt(),
+                cost_per_install: lead_cost.cost_per_install(),
+            });
+        }
+        if !lead_costs.is_empty() {
+            let table = report.section("Lead Source Costs").table(LEAD_COST_HEADERS);
+            for lead_cost in lead_costs {
+                table.row([
+                    Cell::text(lead_cost.lead_source.clone()),
+                    Cell::Currency(lead_cost.cost),
+                    Cell::Number(lead_cost.appt_count as f64),
+                    Cell::Number(lead_cost.install_count as f64),
+                    Cell::dollars(lead_cost.cost_per_appt()),
+                    Cell::dollars(lead_cost.cost_per_install()),
+                ]);
             }
-            writeln!(out, "")?;
         }
-        out.flush()?;
 
-        Ok(())
+        report
     }
 
-    pub fn print_report_csv<'a>(
-        tracker_stats: impl IntoIterator<Item = (&'a KpiSubject, &'a JobTrackerStats)>,
-        red_flags: impl IntoIterator<
-            Item = (&'a KpiSubject, &'a Vec<(Rc<AnalyzedJob>, JobAnalysisError)>),
-        >,
-        output_dir: Option<&Path>,
-    ) -> std::io::Result<()> {
-        // make sure that output_dir exists
-        if let Some(output_dir) = output_dir {
-            std::fs::create_dir_all(output_dir)?;
-        }
+    /// The job numbers of the jobs, each linking to the job in JobNimbus.
+    fn job_links(jobs: &[Rc<AnalyzedJob>]) -> Cell {
+        Cell::links(jobs.iter().map(|job| {
+            let number = job.job.job_number.as_deref().unwrap_or(&job.job.jnid);
+            (number, job_nimbus::job_url(&job.job.jnid))
+        }))
+    }
+}
 
-        for (rep, stats) in tracker_stats {
-            // create the file for this rep
-            let out: Box<dyn Write> = if let Some(output_dir) = output_dir {
-                Box::new(BufWriter::new(
-                    std::fs::File::create(output_dir.join(format!("rep-{}-stats.csv", rep)))
-                        .expect("the directory should exist"),
-                ))
-            } else {
-                Box::new(std::io::stdout())
-            };
-            let mut out = csv::Writer::from_writer(out);
+#[cfg(test)]
+mod test {
+    use std::io::Write as _;
 
-            out.write_record(&["Conversion", "Rate", "Total", "Avg Time (days)", "Jobs"])?;
-            for (name, conv_stats) in [
-                ("All Losses", &stats.loss_conv),
-                ("(I) Appt to Contingency", &stats.appt_continge_conv),
-                ("(I) Appt to Contract", &stats.appt_contract_insure_conv),
-                ("(I) Contingency to Contract", &stats.continge_contract_conv),
-                ("(R) Appt to Contract", &stats.appt_contract_retail_conv),
-                ("(I) Contract to Installation", &stats.install_insure_conv),
-                ("(R) Contract to Installation", &stats.install_retail_conv),
-            ] {
-                out.write_record(&[
-                    name,
-                    &percent_or_na(conv_stats.conversion_rate),
-                    &conv_stats.achieved.len().to_string(),
-                    &into_days(conv_stats.average_time_to_achieve).to_string(),
-                    &into_list_of_job_nums(&conv_stats.achieved),
-                ])?;
-            }
-            out.write_record(&[
-                "Appts",
-                &stats.appt_count.to_string(),
-                "",
-                "Installed",
-                &stats.install_count.to_string(),
-            ])?;
-
-            out.flush()?;
-        }
+    use super::*;
+    use crate::jobs::{test_util::day, MilestoneDates, Status};
 
-        let out: Box<dyn Write> = if let Some(output_dir) = output_dir {
-            Box::new(BufWriter::new(
-                std::fs::File::create(output_dir.join("red-flags.csv"))
-                    .expect("the directory should exist"),
-            ))
-        } else {
-            Box::new(std::io::stdout())
+    /// A retail job from the lead source, which is either installed or lost
+    /// after its appointment.
+    fn job(lead_source: Option<&str>, installed: bool) -> Job {
+        let milestone_dates = MilestoneDates {
+            appointment_date: day(0),
+            contract_date: if installed { day(5) } else { None },
+            install_date: if installed { day(10) } else { None },
+            loss_date: if installed { None } else { day(5) },
+            ..MilestoneDates::default()
         };
-        let mut out = csv::Writer::from_writer(out);
-        out.write_record(&["Sales Rep", "Job Number", "Error"])?;
-        for (rep, red_flags) in red_flags {
-            for (job, err) in red_flags {
-                out.write_record(&[
-                    &rep.to_string(),
-                    job.job.job_number.as_deref().unwrap_or("unknown job #"),
-                    &err.to_string(),
-                ])?;
-            }
+        Job {
+            milestone_dates,
+            status: if installed { Status::JobCompleted } else { Status::Lost },
+            sales_rep: Some("Jane Doe".to_owned()),
+            lead_source: lead_source.map(str::to_owned),
+            ..Job::test_default()
         }
-        out.flush()?;
-
-        Ok(())
     }
 
-    pub fn generate_report_google_sheets<'a>(
-        tracker_stats: impl IntoIterator<Item = (&'a KpiSubject, &'a JobTrackerStats)>,
-        red_flags: impl IntoIterator<
-            Item = (&'a KpiSubject, &'a Vec<(Rc<AnalyzedJob>, JobAnalysisError)>),
-        >,
-        update: bool,
-    ) -> anyhow::Result<()> {
-        fn mk_row(cells: impl IntoIterator<Item = ExtendedValue>) -> RowData {
-            RowData {
-                values: cells
-                    .into_iter()
-                    .map(|cell| CellData { user_entered_value: Some(cell) })
-                    .collect(),
-            }
-        }
-
-        // create a stats sheet for each rep
-        let mut sheets: Vec<_> = tracker_stats
+    #[test]
+    fn jobs_are_grouped_by_lead_source() {
+        let jobs = vec![
+            job(Some("Door Knocking"), true),
+            job(Some("Door Knocking"), false),
+            job(None, true),
+        ];
+        let (trackers, _) = processing::process_jobs(
+            jobs.into_iter(),
+            (None, None),
+            GroupBy::LeadSource,
+            &Config::default(),
+        );
+        let stats: BTreeMap<_, _> = trackers
             .into_iter()
-            .map(|(rep, stats)| {
-                let mut rows = Vec::new();
-                rows.push(mk_row([
-                    ExtendedValue::StringValue("Conversion".to_string()),
-                    ExtendedValue::StringValue("Rate".to_string()),
-                    ExtendedValue::StringValue("Total".to_string()),
-                    ExtendedValue::StringValue("Avg Time (days)".to_string()),
-                    ExtendedValue::StringValue("Jobs".to_string()),
-                ]));
-                for (name, conv_stats) in [
-                    ("All Losses", &stats.loss_conv),
-                    ("(I) Appt to Contingency", &stats.appt_continge_conv),
-                    ("(I) Appt to Contract", &stats.appt_contract_insure_conv),
-                    ("(I) Contingency to Contract", &stats.continge_contract_conv),
-                    ("(R) Appt to Contract", &stats.appt_contract_retail_conv),
-                    ("(I) Contract to Installation", &stats.install_insure_conv),
-                    ("(R) Contract to Installation", &stats.install_retail_conv),
-                ] {
-                    rows.push(mk_row([
-                        ExtendedValue::StringValue(name.to_string()),
-                        ExtendedValue::StringValue(percent_or_na(conv_stats.conversion_rate)),
-                        ExtendedValue::NumberValue(conv_stats.achieved.len() as f64),
-                        ExtendedValue::NumberValue(into_days(conv_stats.average_time_to_achieve)),
-                        ExtendedValue::StringValue(into_list_of_job_nums(&conv_stats.achieved)),
-                    ]));
-                }
-                rows.push(mk_row([
-                    ExtendedValue::StringValue("Appts".to_string()),
-                    ExtendedValue::NumberValue(stats.appt_count as f64),
-                    ExtendedValue::StringValue("".to_string()),
-                    ExtendedValue::StringValue("Installed".to_string()),
-                    ExtendedValue::NumberValue(stats.install_count as f64),
-                ]));
-
-                Sheet {
-                    properties: SheetProperties {
-                        title: Some(format!("Stats {}", rep)),
-                        ..Default::default()
-                    },
-                    data: Some(GridData { start_row: 1, start_column: 1, row_data: rows }),
-                    ..Default::default()
-                }
-            })
+            .map(|(subject, tracker)| (subject, processing::calculate_job_tracker_stats(&tracker)))
             .collect();
+        let counts: Vec<_> = stats
+            .iter()
+            .map(|(subject, stats)| (subject.clone(), stats.appt_count, stats.install_count))
+            .collect();
+        assert_eq!(
+            counts,
+            [
+                (KpiSubject::Global, 3, 2),
+                (KpiSubject::LeadSource("Door Knocking".to_owned()), 2, 1),
+                (KpiSubject::UnknownLeadSource, 1, 1),
+            ]
+        );
 
-        // create the red flags sheet
-        let mut rows = Vec::new();
-        rows.push(mk_row([
-            ExtendedValue::StringValue("Sales Rep".to_string()),
-            ExtendedValue::StringValue("Job Number".to_string()),
-            ExtendedValue::StringValue("Error".to_string()),
-        ]));
-        for (rep, red_flags) in red_flags {
-            for (job, err) in red_flags {
-                rows.push(mk_row([
-                    ExtendedValue::StringValue(rep.to_string()),
-                    ExtendedValue::StringValue(
-                        job.job.job_number.as_deref().unwrap_or("unknown job #").to_string(),
-                    ),
-                    ExtendedValue::StringValue(err.to_string()),
-                ]));
-            }
-        }
-        sheets.push(Sheet {
-            properties: SheetProperties {
-                title: Some("Red Flags".to_string()),
-                ..Default::default()
-            },
-            data: Some(GridData { start_row: 0, start_column: 0, row_data: rows }),
-            ..Default::default()
-        });
-
-        // create the spreadsheet
-        let spreadsheet = Spreadsheet {
-            properties: SpreadsheetProperties {
-                title: Some(format!("KPI Report ({})", Utc::now())),
-            },
-            sheets: Some(sheets),
-            ..Default::default()
-        };
+        let lead_costs =
+            BTreeMap::from([("Door Knocking".to_owned(), 300.0), ("Radio".to_owned(), 50.0)]);
+        let lead_costs = lead_source_costs(lead_costs, &stats);
+        let door_knocking = &lead_costs[0];
+        assert_eq!(door_knocking.cost_per_appt(), Some(150.0));
+        assert_eq!(door_knocking.cost_per_install(), Some(300.0));
+        // a source without any leads has no cost per lead
+        let radio = &lead_costs[1];
+        assert_eq!((radio.lead_source.as_str(), radio.appt_count), ("Radio", 0));
+        assert_eq!(radio.cost_per_appt(), None);
+        assert_eq!(radio.cost_per_install(), None);
+    }
 
-        let url =
-            tokio::runtime::Builder::new_multi_thread().enable_all().build().unwrap().block_on(
-                google_sheets::run_with_credentials(|token| {
-                    // FIXME cloning the token is a workaround because I can't
-                    // get lifetimes to work correctly in run_with_credentials
-                    let token = token.clone();
-                    let spreadsheet = &spreadsheet;
-                    async move {
-                        let spreadsheet = spreadsheet.clone();
-                        if update {
-                            google_sheets::create_or_write_spreadsheet(
-                                &token,
-                                google_sheets::SheetNickname::Kpi,
-                                spreadsheet,
-                            )
-                            .await
-                        } else {
-                            google_sheets::create_spreadsheet(
-                                &token,
-                                google_sheets::SheetNickname::Kpi,
-                                spreadsheet,
-                            )
-                            .await
-                        }
-                    }
-                }),
-            )?;
-        utils::open_url(url.as_str());
-        Ok(())
+    #[test]
+    fn reps_roll_up_into_their_teams_and_branches() {
+        let config: Config = serde_json::from_str(
+            r#"{"branches": [{"name": "North", "teams": [
+                {"name": "Team A", "reps": [{"name": "Heinrich Asbury", "aliases": ["H. Asbury"]}]}
+            ]}]}"#,
+        )
+        .unwrap();
+        let mut jobs = vec![job(None, true), job(None, true)];
+        jobs[0].sales_rep = Some("H. Asbury".to_owned());
+        jobs[1].sales_rep = Some("Bob".to_owned());
+        reps::normalize_sales_reps(&mut jobs, &config);
+        let (trackers, _) =
+            processing::process_jobs(jobs.into_iter(), (None, None), GroupBy::SalesRep, &config);
+        let mut subjects: Vec<_> = trackers.into_keys().collect();
+        subjects.sort();
+        assert_eq!(
+            subjects,
+            [
+                KpiSubject::Global,
+                KpiSubject::Branch("North".to_owned()),
+                KpiSubject::Team("Team A".to_owned()),
+                KpiSubject::SalesRep("Bob".to_owned()),
+                KpiSubject::SalesRep("Heinrich Asbury".to_owned()),
+            ]
+        );
     }
 
-    fn into_days(time: TimeDelta) -> f64 {
-        const SECONDS_PER_DAY: f64 = 86400.0;
-        time.num_seconds() as f64 / SECONDS_PER_DAY
+    fn read_lead_costs_from(contents: &str) -> Result<BTreeMap<String, f64>> {
+        let mut file = tempfile::NamedTempFile::new()?;
+        file.write_all(contents.as_bytes())?;
+        read_lead_costs(file.path())
     }
-    fn percent_or_na(rate: Option<f64>) -> String {
-        rate.map(|r| format!("{:6.2}%", r * 100.0)).unwrap_or_else(|| "    N/A".to_owned())
+
+    #[test]
+    fn lead_costs_are_summed_by_source() {
+        let lead_costs = read_lead_costs_from(
+            "Lead Source,Cost\n\
+             Door Knocking,\"$1,200.50\"\n\
+             Radio, 300\n\
+             Door Knocking,100\n",
+        )
+        .unwrap();
+        assert_eq!(
+            lead_costs,
+            BTreeMap::from([("Door Knocking".to_owned(), 1300.5), ("Radio".to_owned(), 300.0)])
+        );
     }
-    fn into_list_of_job_nums(jobs: &[Rc<AnalyzedJob>]) -> String {
-        jobs.iter()
-            .map(|job| job.job.job_number.as_deref().unwrap_or_else(|| &job.job.jnid))
-            .collect::<Vec<_>>()
-            .join(", ")
+
+    #[test]
+    fn bad_lead_cost_rows_are_errors() {
+        let error = read_lead_costs_from("Lead Source,Cost\nRadio,lots\n").unwrap_err();
+        assert_eq!(error.to_string(), "Invalid cost \"lots\" for lead source Radio");
+        assert!(read_lead_costs_from("Lead Source,Cost\nRadio\n").is_err());
+        assert!(read_lead_costs_from("Source,Cost\nRadio,300\n").is_err());
     }
 }
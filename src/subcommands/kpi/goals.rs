@@ -0,0 +1,266 @@
+use std::collections::BTreeMap;
+
+use anyhow::{bail, Context as _};
+use chrono::{Datelike as _, Months, NaiveDate, NaiveDateTime, NaiveTime, TimeZone as _, Utc};
+use tracing::{info, warn};
+
+use crate::{
+    apis::google_sheets,
+    config::{Config, RepTargets},
+    jobs::Timestamp,
+    reps,
+};
+
+use super::processing::JobTrackerStats;
+
+/// The progress of a sales rep towards the target for one milestone.
+#[derive(Debug)]
+pub struct GoalProgress {
+    /// The name of the milestone, e.g. "Appointments".
+    pub name: &'static str,
+    /// The number of times the milestone was reached during the period.
+    pub actual: usize,
+    /// The number of times the milestone was supposed to be reached during the
+    /// whole period.
+    pub target: f64,
+    /// The number of times the milestone is projected to be reached by the end
+    /// of the period at the current pace. `None` if the period is already
+    /// over.
+    pub projected: Option<f64>,
+}
+
+impl GoalProgress {
+    /// The fraction of the target that was reached. `None` if the target is
+    /// zero.
+    pub fn attainment(&self) -> Option<f64> {
+        (self.target > 0.0).then(|| self.actual as f64 / self.target)
+    }
+
+    /// The fraction of the target that is projected to be reached by the end
+    /// of the period. `None` if the target is zero or the period is over.
+    pub fn pace(&self) -> Option<f64> {
+        self.projected.filter(|_| self.target > 0.0).map(|projected| projected / self.target)
+    }
+}
+
+/// The calendar months covered by a report, which monthly targets are compared
+/// against.
+#[derive(Debug, Clone, Copy)]
+pub struct GoalPeriod {
+    start: Timestamp,
+    end: Timestamp,
+    num_months: u32,
+    /// How far the report extends into the period.
+    cutoff: Timestamp,
+}
+
+impl GoalPeriod {
+    /// The calendar months from the start of the month containing `from`
+    /// through the end of the month containing `to` (or the current month if
+    /// `to` is in the future). `None` if there is no start date, and an error
+    /// if the start date is after `to` or in the future.
+    pub fn new(from: Option<Timestamp>, to: Option<Timestamp>) -> anyhow::Result<Option<Self>> {
+        fn start_of_month(dt: Timestamp) -> Timestamp {
+            let date = NaiveDate::from_ymd_opt(dt.year(), dt.month(), 1)
+                .expect("the first of the month should always be valid");
+            Utc.from_utc_datetime(&NaiveDateTime::new(date, NaiveTime::MIN))
+        }
+
+        let Some(from) = from else {
+            return Ok(None);
+        };
+        let cutoff = to.map_or(Utc::now(), |to| to.min(Utc::now()));
+        if from > cutoff {
+            bail!(
+                "The date range starts at {}, which is after its end at {}",
+                from.date_naive(),
+                cutoff.date_naive()
+            );
+        }
+        let start = start_of_month(from);
+        if start != from {
+            warn!("The date range does not start at the beginning of a month, so targets will be compared against partial months");
+        }
+        let num_months =
+            (cutoff.year() - start.year()) * 12 + cutoff.month() as i32 - start.month() as i32 + 1;
+        let num_months =
+            u32::try_from(num_months).expect("the start should not be after the cutoff");
+        let end = start + Months::new(num_months);
+        Ok(Some(GoalPeriod { start, end, num_months, cutoff }))
+    }
+
+    /// Calculates the progress towards each of the specified monthly targets.
+    pub fn progress(&self, targets: &RepTargets, stats: &JobTrackerStats) -> Vec<GoalProgress> {
+        // the fraction of the period that has elapsed, if it is not over
+        let elapsed = (self.cutoff < self.end).then(|| {
+            (self.cutoff - self.start).num_seconds() as f64
+                / (self.end - self.start).num_seconds() as f64
+        });
+
+        [
+            ("Appointments", targets.appointments, stats.appt_count),
+            ("Contracts", targets.contracts, stats.contract_count()),
+            ("Installs", targets.installs, stats.install_count),
+        ]
+        .into_iter()
+        .filter_map(|(name, monthly_target, actual)| {
+            let target = monthly_target? as f64 * self.num_months as f64;
+            let projected =
+                elapsed.map(|elapsed| if elapsed > 0.0 { actual as f64 / elapsed } else { 0.0 });
+            Some(GoalProgress { name, actual, target, projected })
+        })
+        .collect()
+    }
+}
+
+/// Collects the monthly targets from the configuration and from the Google
+/// Sheet of targets, if there is one. Targets in the Google Sheet take
+/// precedence. The targets are keyed by [`reps::name_key`] of the rep's
+/// configured name, so targets can be given under any of the rep's aliases.
+pub fn load_targets(config: &Config) -> anyhow::Result<BTreeMap<String, RepTargets>> {
+    let key = |name: &str| match config.find_rep(name) {
+        Some(placement) => reps::name_key(&placement.rep.name),
+        None => reps::name_key(name),
+    };
+    let mut targets: BTreeMap<_, _> =
+        config.targets.iter().map(|(name, targets)| (key(name), *targets)).collect();
+
+    if let Some(targets_sheet) = &config.targets_sheet {
+        info!("Reading targets from Google Sheet {}", targets_sheet.spreadsheet_id);
+        let rows =
+            tokio::runtime::Builder::new_multi_thread().enable_all().build().unwrap().block_on(
+                google_sheets::run_with_credentials(|token| {
+                    // FIXME cloning the token is a workaround because I can't
+                    // get lifetimes to work correctly in run_with_credentials
+                    let token = token.clone();
+                    async move {
+                        google_sheets::read_values(
+                            &token,
+                            &targets_sheet.spreadsheet_id,
+                            &targets_sheet.sheet_name,
+                        )
+                        .await
+                    }
+                }),
+            )?;
+        for (name, rep_targets) in parse_targets_sheet(&rows)? {
+            targets.insert(key(&name), rep_targets);
+        }
+    }
+
+    Ok(targets)
+}
+
+/// Parses the rows of a sheet of targets, where the first row is a header.
+fn parse_targets_sheet(rows: &[Vec<String>]) -> anyhow::Result<Vec<(String, RepTargets)>> {
+    let Some((header, rows)) = rows.split_first() else {
+        bail!("The targets sheet is empty");
+    };
+    let column = |name: &str| header.iter().position(|cell| cell.trim().eq_ignore_ascii_case(name));
+    let Some(rep_column) = column("Sales Rep") else {
+        bail!("The targets sheet does not have a \"Sales Rep\" column");
+    };
+    let columns = [column("Appointments"), column("Contracts"), column("Installs")];
+
+    let mut targets = Vec::new();
+    for row in rows {
+        let Some(name) = row.get(rep_column).filter(|name| !name.trim().is_empty()) else {
+            continue;
+        };
+        let [appointments, contracts, installs] = columns.map(|column| {
+            column
+                .and_then(|column| row.get(column))
+                .map(|cell| cell.trim())
+                .filter(|cell| !cell.is_empty())
+                .map(|cell| {
+                    cell.parse::<u32>()
+                        .with_context(|| format!("Invalid target {:?} for {}", cell, name))
+                })
+                .transpose()
+        });
+        targets.push((
+            name.clone(),
+            RepTargets { appointments: appointments?, contracts: contracts?, installs: installs? },
+        ));
+    }
+    Ok(targets)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn ts(year: i32, month: u32, day: u32) -> Timestamp {
+        Utc.from_utc_datetime(&NaiveDateTime::new(
+            NaiveDate::from_ymd_opt(year, month, day).unwrap(),
+            NaiveTime::MIN,
+        ))
+    }
+
+    #[test]
+    fn goal_period_spans_calendar_months() {
+        let period =
+            GoalPeriod::new(Some(ts(2023, 11, 1)), Some(ts(2024, 2, 10))).unwrap().unwrap();
+        assert_eq!(period.start, ts(2023, 11, 1));
+        assert_eq!(period.end, ts(2024, 3, 1));
+        assert_eq!(period.num_months, 4);
+
+        assert!(GoalPeriod::new(None, Some(ts(2024, 2, 10))).unwrap().is_none());
+    }
+
+    #[test]
+    fn goal_period_must_start_before_it_ends() {
+        let error = GoalPeriod::new(Some(ts(2024, 3, 1)), Some(ts(2024, 1, 15))).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "The date range starts at 2024-03-01, which is after its end at 2024-01-15"
+        );
+        let next_year = Utc::now().year() + 1;
+        assert!(GoalPeriod::new(Some(ts(next_year, 1, 1)), None).is_err());
+        // a period within a single day is still a month
+        let period = GoalPeriod::new(Some(ts(2024, 1, 15)), Some(ts(2024, 1, 15))).unwrap();
+        assert_eq!(period.unwrap().num_months, 1);
+    }
+
+    #[test]
+    fn parse_targets() {
+        let rows: Vec<Vec<String>> = [
+            vec!["Sales Rep", "Installs", "Appointments"],
+            vec!["Heinrich Asbury", "6", "20"],
+            vec!["Maria Lopez", "", "15"],
+            vec![""],
+        ]
+        .into_iter()
+        .map(|row| row.into_iter().map(str::to_owned).collect())
+        .collect();
+        let targets = parse_targets_sheet(&rows).unwrap();
+        assert_eq!(targets.len(), 2);
+        let (name, heinrich) = &targets[0];
+        assert_eq!(name, "Heinrich Asbury");
+        assert_eq!(heinrich.appointments, Some(20));
+        assert_eq!(heinrich.contracts, None);
+        assert_eq!(heinrich.installs, Some(6));
+        let (_, maria) = &targets[1];
+        assert_eq!(maria.installs, None);
+
+        let rows = vec![vec!["Rep".to_owned()]];
+        assert!(parse_targets_sheet(&rows).is_err());
+    }
+
+    #[test]
+    fn targets_are_keyed_by_configured_name() {
+        let config: Config = serde_json::from_str(
+            r#"{
+                "branches": [{"name": "North", "teams": [
+                    {"name": "Team A", "reps": [{"name": "Heinrich Asbury", "aliases": ["H. Asbury"]}]}
+                ]}],
+                "targets": {"H. Asbury": {"installs": 6}, "Maria Lopez": {"installs": 4}}
+            }"#,
+        )
+        .unwrap();
+        let targets = load_targets(&config).unwrap();
+        assert_eq!(targets[&reps::name_key("Heinrich Asbury")].installs, Some(6));
+        assert_eq!(targets[&reps::name_key("Maria Lopez")].installs, Some(4));
+        assert!(!targets.contains_key(&reps::name_key("H. Asbury")));
+    }
+}
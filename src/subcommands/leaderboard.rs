@@ -0,0 +1,431 @@
+use std::{collections::BTreeMap, fmt::Display, rc::Rc};
+
+use chrono::Utc;
+use serde::Serialize;
+
+use crate::{
+    apis::{google_sheets::SheetNickname, job_nimbus},
+    config::Config,
+    jobs::{AnalyzedJob, Job, Milestone, TimeDelta, Timestamp},
+    report::{self, Cell, OutputFormat, Report, Table},
+    reps,
+    subcommands::kpi::{
+        processing::{self, JobTrackerStats},
+        GroupBy, KpiSubject,
+    },
+    utils,
+};
+
+#[derive(clap::Args, Debug)]
+pub struct Args {
+    /// The filter to use when query JobNimbus for jobs, using ElasticSearch
+    /// syntax.
+    #[arg(short, long = "filter", default_value = None)]
+    filter_filename: Option<String>,
+
+    /// The start of the period to rank reps over. Only jobs that were settled
+    /// (date of install or date of loss) within the period are considered.
+    /// Valid options are a date of the form "%Y-%m-%d", "ytd" (indicating the
+    /// start of the current year), "mtd" (indicating the start of the current
+    /// month), "today" (indicating the current date), or "forever" (indicating
+    /// the beginning of time). Movement is compared against the period of the
+    /// same length right before this one, so it is only shown if there is a
+    /// start date.
+    #[arg(long = "from", default_value = "mtd")]
+    from_date: String,
+    /// The end of the period to rank reps over. Valid options are a date of
+    /// the form "%Y-%m-%d", "today" (indicating the current date), or
+    /// "forever" (indicating the end of time).
+    #[arg(long = "to", default_value = "today")]
+    to_date: String,
+
+    /// The metrics to rank reps on.
+    #[arg(
+        long,
+        value_enum,
+        value_delimiter = ',',
+        default_value = "installs,revenue,appt-to-contract,time-to-contract"
+    )]
+    metrics: Vec<Metric>,
+
+    /// The minimum number of jobs that a rate or an average must be based on
+    /// for a rep to be ranked on it. For the appointment to contract rate,
+    /// this is the number of appointments; for the time to contract, this is
+    /// the number of contracts.
+    #[arg(long, default_value = "5")]
+    min_sample: usize,
+
+    /// The format in which to print the output.
+    #[arg(long, value_enum, default_value = "human")]
+    format: OutputFormat,
+
+    /// The file to write the output to. "-" or unspecified will write to
+    /// stdout. This option is ignored with `--format google-sheets`.
+    #[arg(short, long, default_value = None)]
+    output: Option<String>,
+
+    /// Only valid with `--format google-sheets`. Whether to update an existing
+    /// Google Sheet; if not specified, creates a new Google Sheet.
+    #[arg(long)]
+    update: bool,
+}
+
+#[derive(Debug, clap::ValueEnum, Clone, Copy, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+enum Metric {
+    /// The number of installs.
+    Installs,
+    /// The total contract value of the installed jobs.
+    Revenue,
+    /// The fraction of appointments that led to a signed contract.
+    ApptToContract,
+    /// The average time from appointment to signed contract (shorter ranks
+    /// higher).
+    TimeToContract,
+}
+
+impl Metric {
+    fn higher_is_better(self) -> bool {
+        match self {
+            Metric::Installs | Metric::Revenue | Metric::ApptToContract => true,
+            Metric::TimeToContract => false,
+        }
+    }
+
+    /// The value of this metric for a rep, or `None` if the rep does not
+    /// qualify to be ranked on it.
+    fn value(self, stats: &JobTrackerStats, min_sample: usize) -> Option<f64> {
+        match self {
+            Metric::Installs => Some(stats.install_count as f64),
+            Metric::Revenue => {
+                Some(installed_jobs(stats).map(|job| job.job.amt_contract as f64 / 100.0).sum())
+            }
+            Metric::ApptToContract => (stats.appt_count >= min_sample)
+                .then(|| stats.contract_count() as f64 / stats.appt_count as f64),
+            Metric::TimeToContract => {
+                let times: Vec<TimeDelta> = contracted_jobs(stats)
+                    .filter_map(|job| {
+                        let timestamps = &job.analysis.as_ref()?.timestamps;
+                        let appt = (*timestamps.get(Milestone::AppointmentMade.into_int())?)?;
+                        let contract = (*timestamps.get(Milestone::ContractSigned.into_int())?)?;
+                        Some(contract - appt)
+                    })
+                    .collect();
+                (times.len() >= min_sample.max(1)).then(|| {
+                    let total: TimeDelta = times.iter().sum();
+                    report::days(total) / times.len() as f64
+                })
+            }
+        }
+    }
+
+    /// The value of this metric as a report cell, with the right unit.
+    fn value_cell(self, value: f64) -> Cell {
+        match self {
+            Metric::Installs => Cell::Number(value),
+            Metric::Revenue => Cell::Currency(value),
+            Metric::ApptToContract => Cell::Percent(value),
+            Metric::TimeToContract => Cell::Days(value),
+        }
+    }
+}
+
+impl Display for Metric {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Metric::Installs => write!(f, "Installs"),
+            Metric::Revenue => write!(f, "Revenue"),
+            Metric::ApptToContract => write!(f, "Appt to Contract Rate"),
+            Metric::TimeToContract => write!(f, "Avg Time to Contract"),
+        }
+    }
+}
+
+/// The position of a rep in the ranking of one metric.
+#[derive(Debug, Clone, PartialEq)]
+struct Standing {
+    rep: String,
+    /// Reps with equal values share a rank.
+    rank: usize,
+    value: f64,
+    /// How many places the rep moved up since the previous period. `None` if
+    /// the rep was not ranked in the previous period.
+    movement: Option<isize>,
+}
+
+impl Standing {
+    fn badge(&self) -> &'static str {
+        match self.rank {
+            1 => "🥇",
+            2 => "🥈",
+            3 => "🥉",
+            _ => "",
+        }
+    }
+
+    fn movement_or_new(&self, has_previous: bool) -> String {
+        match self.movement {
+            _ if !has_previous => "".to_owned(),
+            None => "NEW".to_owned(),
+            Some(0) => "-".to_owned(),
+            Some(up) if up > 0 => format!("▲{}", up),
+            Some(down) => format!("▼{}", -down),
+        }
+    }
+}
+
+/// The rankings of all metrics, in the order that they were requested.
+struct Leaderboard {
+    rankings: Vec<(Metric, Vec<Standing>)>,
+    /// Whether there is a previous period to compare against.
+    has_previous: bool,
+}
+
+pub fn main(api_key: &str, config: &Config, args: Args) -> anyhow::Result<()> {
+    let Args { filter_filename, from_date, to_date, metrics, min_sample, format, output, update } =
+        args;
+    report::check_output_args(format, output.as_deref(), update);
+
+    let filter = if let Some(filter_filename) = filter_filename {
+        Some(std::fs::read_to_string(filter_filename)?)
+    } else {
+        None
+    };
+    let mut jobs =
+        job_nimbus::get_all_jobs_from_job_nimbus(api_key, filter.as_deref(), &config.job_fields)?;
+    reps::normalize_sales_reps(&mut jobs, config);
+
+    let from_date = utils::parse_from_date(&from_date)?;
+    let to_date = utils::parse_to_date(&to_date)?;
+
+    let leaderboard = leaderboard(&jobs, (from_date, to_date), metrics, min_sample, config);
+
+    report::output(
+        &report(&leaderboard),
+        format,
+        output.as_deref(),
+        update,
+        SheetNickname::Leaderboard,
+    )
+}
+
+/// Ranks the reps on each metric over the period, along with their movement
+/// since the previous period of the same length.
+fn leaderboard(
+    jobs: &[Job],
+    (from_date, to_date): (Option<Timestamp>, Option<Timestamp>),
+    metrics: Vec<Metric>,
+    min_sample: usize,
+    config: &Config,
+) -> Leaderboard {
+    let current = calculate_rep_stats(jobs, (from_date, to_date), config);
+    // the previous period has the same length and ends right before this one
+    // starts, since both ends of a date range are inclusive
+    let previous = from_date.map(|from_date| {
+        let length = to_date.unwrap_or_else(Utc::now) - from_date;
+        let previous_end = from_date - TimeDelta::nanoseconds(1);
+        calculate_rep_stats(jobs, (Some(from_date - length), Some(previous_end)), config)
+    });
+
+    let rankings = metrics
+        .into_iter()
+        .map(|metric| {
+            let values = |stats: &BTreeMap<String, JobTrackerStats>| {
+                stats
+                    .iter()
+                    .filter_map(|(rep, stats)| {
+                        Some((rep.clone(), metric.value(stats, min_sample)?))
+                    })
+                    .collect::<Vec<_>>()
+            };
+            let previous = previous.as_ref().map(|previous| rank(values(previous), metric));
+            let mut standings = rank(values(&current), metric);
+            for standing in &mut standings {
+                standing.movement = previous.as_ref().and_then(|previous| {
+                    let previous = previous.iter().find(|prev| prev.rep == standing.rep)?;
+                    Some(previous.rank as isize - standing.rank as isize)
+                });
+            }
+            (metric, standings)
+        })
+        .collect();
+    Leaderboard { rankings, has_previous: previous.is_some() }
+}
+
+/// Calculates the stats of each sales rep (excluding jobs without a sales rep)
+/// over the specified date range.
+fn calculate_rep_stats(
+    jobs: &[Job],
+    date_range: (Option<Timestamp>, Option<Timestamp>),
+    config: &Config,
+) -> BTreeMap<String, JobTrackerStats> {
+    let (trackers, _) =
+        processing::process_jobs(jobs.iter().cloned(), date_range, GroupBy::SalesRep, config);
+    trackers
+        .into_iter()
+        .filter_map(|(subject, tracker)| match subject {
+            KpiSubject::SalesRep(rep) => {
+                Some((rep, processing::calculate_job_tracker_stats(&tracker)))
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+/// Ranks the values of a metric, best first. Reps with equal values share a
+/// rank, and the next rank is skipped for each rep sharing it (e.g. 1, 1, 3).
+fn rank(mut values: Vec<(String, f64)>, metric: Metric) -> Vec<Standing> {
+    values.sort_by(|(rep_a, a), (rep_b, b)| {
+        let by_value = if metric.higher_is_better() { b.total_cmp(a) } else { a.total_cmp(b) };
+        by_value.then_with(|| rep_a.cmp(rep_b))
+    });
+    let mut standings: Vec<Standing> = Vec::with_capacity(values.len());
+    for (i, (rep, value)) in values.into_iter().enumerate() {
+        let rank = match standings.last() {
+            Some(last) if last.value == value => last.rank,
+            _ => i + 1,
+        };
+        standings.push(Standing { rep, rank, value, movement: None });
+    }
+    standings
+}
+
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Record<'a> {
+    Standing {
+        metric: Metric,
+        rank: usize,
+        sales_rep: &'a str,
+        /// Revenue is in dollars, rates are fractions, and times are in days.
+        value: f64,
+        /// How many places the rep moved up since the previous period. `null`
+        /// if the rep wasn't ranked in the previous period, or if there is no
+        /// previous period.
+        movement: Option<isize>,
+    },
+}
+
+/// The report has a table for each metric, in the order that they were
+/// requested.
+fn report(leaderboard: &Leaderboard) -> Report {
+    let mut report = Report::new(format!("Sales Leaderboard ({})", Utc::now()));
+    for (metric, standings) in &leaderboard.rankings {
+        for standing in standings {
+            report.record(Record::Standing {
+                metric: *metric,
+                rank: standing.rank,
+                sales_rep: &standing.rep,
+                value: standing.value,
+                movement: standing.movement,
+            });
+        }
+    }
+    let section = report.section("Leaderboard");
+    for (metric, standings) in &leaderboard.rankings {
+        let table = section
+            .table(["Rank", "", "Sales Rep", "Value", "Movement"])
+            .titled(metric.to_string());
+        for standing in standings {
+            table.row([
+                Cell::Number(standing.rank as f64),
+                Cell::text(standing.badge()),
+                Cell::text(standing.rep.clone()),
+                metric.value_cell(standing.value),
+                Cell::text(standing.movement_or_new(leaderboard.has_previous)),
+            ]);
+        }
+    }
+
+    let mut flat = Table::new(["Metric", "Rank", "Sales Rep", "Value", "Movement"]);
+    for (metric, standings) in &leaderboard.rankings {
+        for standing in standings {
+            flat.row([
+                Cell::text(metric.to_string()),
+                Cell::Number(standing.rank as f64),
+                Cell::text(standing.rep.clone()),
+                metric.value_cell(standing.value),
+                Cell::text(standing.movement_or_new(leaderboard.has_previous)),
+            ]);
+        }
+    }
+    report.flat = Some(flat);
+    report
+}
+
+fn installed_jobs(stats: &JobTrackerStats) -> impl Iterator<Item = &Rc<AnalyzedJob>> {
+    stats.install_insure_conv.achieved.iter().chain(&stats.install_retail_conv.achieved)
+}
+fn contracted_jobs(stats: &JobTrackerStats) -> impl Iterator<Item = &Rc<AnalyzedJob>> {
+    stats
+        .appt_contract_insure_conv
+        .achieved
+        .iter()
+        .chain(&stats.continge_contract_conv.achieved)
+        .chain(&stats.appt_contract_retail_conv.achieved)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::jobs::{test_util::day, MilestoneDates, Status};
+
+    fn installed_job(rep: &str, install_day: i64) -> Job {
+        Job {
+            milestone_dates: MilestoneDates {
+                appointment_date: day(install_day),
+                contract_date: day(install_day),
+                install_date: day(install_day),
+                ..MilestoneDates::default()
+            },
+            status: Status::JobCompleted,
+            sales_rep: Some(rep.to_owned()),
+            ..Job::test_default()
+        }
+    }
+
+    #[test]
+    fn rank_shares_ties() {
+        let values = vec![
+            ("A".to_owned(), 3.0),
+            ("B".to_owned(), 5.0),
+            ("C".to_owned(), 5.0),
+            ("D".to_owned(), 1.0),
+        ];
+        let ranks = |standings: Vec<Standing>| -> Vec<(String, usize)> {
+            standings.into_iter().map(|s| (s.rep, s.rank)).collect()
+        };
+
+        assert_eq!(
+            ranks(rank(values.clone(), Metric::Installs)),
+            [("B".to_owned(), 1), ("C".to_owned(), 1), ("A".to_owned(), 3), ("D".to_owned(), 4)]
+        );
+        // shorter times rank higher
+        assert_eq!(
+            ranks(rank(values, Metric::TimeToContract)),
+            [("D".to_owned(), 1), ("A".to_owned(), 2), ("B".to_owned(), 3), ("C".to_owned(), 3)]
+        );
+    }
+
+    #[test]
+    fn movement_since_the_previous_period() {
+        let jobs = vec![
+            installed_job("Alice", 3),
+            installed_job("Alice", 4),
+            installed_job("Alice", 15),
+            installed_job("Bob", 5),
+            // on the first day of the current period, so not in the previous one
+            installed_job("Bob", 10),
+            installed_job("Bob", 15),
+        ];
+        let leaderboard =
+            leaderboard(&jobs, (day(10), day(20)), vec![Metric::Installs], 0, &Config::default());
+        assert!(leaderboard.has_previous);
+        let (_, standings) = &leaderboard.rankings[0];
+        let movements: Vec<_> = standings
+            .iter()
+            .map(|standing| (standing.rep.as_str(), standing.rank, standing.movement))
+            .collect();
+        assert_eq!(movements, [("Bob", 1, Some(1)), ("Alice", 2, Some(-1))]);
+    }
+}
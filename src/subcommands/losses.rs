@@ -0,0 +1,321 @@
+use std::{collections::BTreeMap, rc::Rc};
+
+use chrono::Utc;
+use serde::Serialize;
+use tracing::info;
+
+use crate::{
+    apis::{google_sheets::SheetNickname, job_nimbus},
+    config::Config,
+    jobs::{self, AnalyzedJob, Job, JobKind, Milestone, TimeDelta, Timestamp},
+    report::{self, Cell, OutputFormat, Report},
+    reps,
+    subcommands::kpi::KpiSubject,
+    utils,
+};
+
+#[derive(clap::Args, Debug)]
+pub struct Args {
+    /// The filter to use when query JobNimbus for jobs, using ElasticSearch
+    /// syntax.
+    #[arg(short, long = "filter", default_value = None)]
+    filter_filename: Option<String>,
+
+    /// The minimum date to filter jobs by. The final report will only include
+    /// jobs that were lost after the minimum date. Valid options are a date of
+    /// the form "%Y-%m-%d", "ytd" (indicating the start of the current year),
+    /// "mtd" (indicating the start of the current month), "today" (indicating
+    /// the current date), or "forever" (indicating the beginning of time).
+    #[arg(long = "from", default_value = "forever")]
+    from_date: String,
+    /// The maximum date to filter jobs by. The final report will only include
+    /// jobs that were lost before the maximum date. Valid options are a date of
+    /// the form "%Y-%m-%d", "today" (indicating the current date), or "forever"
+    /// (indicating the end of time).
+    #[arg(long = "to", default_value = "today")]
+    to_date: String,
+
+    /// The format in which to print the output.
+    #[arg(long, value_enum, default_value = "human")]
+    format: OutputFormat,
+
+    /// The file to write the output to. "-" or unspecified will write to
+    /// stdout. This option is ignored with `--format google-sheets`.
+    #[arg(short, long, default_value = None)]
+    output: Option<String>,
+
+    /// Only valid with `--format google-sheets`. Whether to update an existing
+    /// Google Sheet; if not specified, creates a new Google Sheet.
+    #[arg(long)]
+    update: bool,
+}
+
+/// A job that was lost.
+struct Loss {
+    job: Job,
+    /// The last milestone that the job reached before it was lost.
+    milestone: Milestone,
+    /// The time between reaching the last milestone and being lost. `None` if
+    /// the date at which the last milestone was reached is unknown.
+    time_since_milestone: Option<TimeDelta>,
+}
+
+/// The losses of one subject, grouped by job kind, then by the last milestone
+/// reached, then by the reason for the loss.
+type LossBreakdown<'a> = BTreeMap<JobKind, BTreeMap<Milestone, BTreeMap<&'a str, Vec<&'a Loss>>>>;
+
+const NO_REASON: &str = "[No Reason Given]";
+
+pub fn main(api_key: &str, config: &Config, args: Args) -> anyhow::Result<()> {
+    let Args { filter_filename, from_date, to_date, format, output, update } = args;
+    report::check_output_args(format, output.as_deref(), update);
+
+    let filter = if let Some(filter_filename) = filter_filename {
+        Some(std::fs::read_to_string(filter_filename)?)
+    } else {
+        None
+    };
+    let mut jobs =
+        job_nimbus::get_all_jobs_from_job_nimbus(api_key, filter.as_deref(), &config.job_fields)?;
+    reps::normalize_sales_reps(&mut jobs, config);
+
+    let from_date = utils::parse_from_date(&from_date)?;
+    let to_date = utils::parse_to_date(&to_date)?;
+
+    let losses = find_losses(jobs, (from_date, to_date), config);
+    let breakdowns: BTreeMap<&KpiSubject, LossBreakdown> =
+        losses.iter().map(|(subject, losses)| (subject, break_down(losses))).collect();
+
+    report::output(&report(&breakdowns), format, output.as_deref(), update, SheetNickname::Losses)
+}
+
+/// Finds all jobs that were lost within the date range, grouped by sales rep.
+/// All losses are also included under [`KpiSubject::Global`].
+fn find_losses(
+    jobs: Vec<Job>,
+    (from_dt, to_dt): (Option<Timestamp>, Option<Timestamp>),
+    config: &Config,
+) -> BTreeMap<KpiSubject, Vec<(JobKind, Rc<Loss>)>> {
+    let mut losses: BTreeMap<KpiSubject, Vec<(JobKind, Rc<Loss>)>> = BTreeMap::new();
+    for job in jobs {
+        // jobs with errors are skipped here; they show up as red flags in the
+        // KPI report instead
+        let (AnalyzedJob { job, analysis: Some(analysis) }, _) =
+            jobs::analyze_job_with_strictness(job, config.strictness)
+        else {
+            continue;
+        };
+        let Some(loss_timestamp) = analysis.loss_timestamp else {
+            continue;
+        };
+        if from_dt.is_some_and(|from_dt| loss_timestamp < from_dt)
+            || to_dt.is_some_and(|to_dt| loss_timestamp > to_dt)
+        {
+            continue;
+        }
+
+        let time_since_milestone = analysis
+            .timestamps
+            .iter()
+            .rev()
+            .find_map(|&ts| ts)
+            .or(job.date_created)
+            .map(|date_reached| loss_timestamp - date_reached);
+        let rep = match job.sales_rep.clone() {
+            Some(name) => KpiSubject::SalesRep(name),
+            None => KpiSubject::UnknownSalesRep,
+        };
+        let loss =
+            Rc::new(Loss { milestone: analysis.current_milestone(), time_since_milestone, job });
+
+        losses.entry(rep).or_default().push((analysis.kind, loss));
+    }
+
+    let all_losses = losses.values().flatten().cloned().collect();
+    losses.insert(KpiSubject::Global, all_losses);
+
+    info!("Found {} lost jobs", losses[&KpiSubject::Global].len());
+    losses
+}
+
+fn break_down(losses: &[(JobKind, Rc<Loss>)]) -> LossBreakdown<'_> {
+    let mut breakdown: LossBreakdown = BTreeMap::new();
+    for (kind, loss) in losses {
+        breakdown
+            .entry(*kind)
+            .or_default()
+            .entry(loss.milestone)
+            .or_default()
+            .entry(loss.job.loss_reason.as_deref().unwrap_or(NO_REASON))
+            .or_default()
+            .push(loss.as_ref());
+    }
+    breakdown
+}
+
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Record<'a> {
+    /// The losses of a subject with the same job kind, last milestone, and
+    /// reason.
+    Losses {
+        subject: String,
+        job_kind: JobKind,
+        lost_after: Milestone,
+        reason: &'a str,
+        count: usize,
+        average_days_after_milestone: Option<f64>,
+        job_ids: Vec<&'a str>,
+    },
+}
+
+/// The report has a row for each subject, job kind, last milestone, and reason
+/// for the loss.
+fn report(breakdowns: &BTreeMap<&KpiSubject, LossBreakdown>) -> Report {
+    let mut report = Report::new(format!("Loss Report ({})", Utc::now()));
+    for (subject, breakdown) in breakdowns {
+        for (kind, by_milestone) in breakdown {
+            for (milestone, by_reason) in by_milestone {
+                for (reason, losses) in by_reason {
+                    report.record(Record::Losses {
+                        subject: subject.to_string(),
+                        job_kind: *kind,
+                        lost_after: *milestone,
+                        reason,
+                        count: losses.len(),
+                        average_days_after_milestone: average_time_since_milestone(losses)
+                            .map(report::days),
+                        job_ids: losses.iter().map(|loss| loss.job.jnid.as_str()).collect(),
+                    });
+                }
+            }
+        }
+    }
+
+    let table = report.section("Losses").table([
+        "Subject",
+        "Job Kind",
+        "Lost After",
+        "Reason",
+        "Count",
+        "Avg Days After Milestone",
+        "Jobs",
+    ]);
+    for (subject, breakdown) in breakdowns {
+        for (kind, by_milestone) in breakdown {
+            for (milestone, by_reason) in by_milestone {
+                for (reason, losses) in by_reason {
+                    table.row([
+                        Cell::text(subject.to_string()),
+                        Cell::text(kind.to_string()),
+                        Cell::text(milestone.to_string()),
+                        Cell::text(*reason),
+                        Cell::Number(losses.len() as f64),
+                        average_time_since_milestone(losses).map_or(Cell::Empty, Cell::days),
+                        job_links(losses),
+                    ]);
+                }
+            }
+        }
+    }
+    report
+}
+
+/// The average time between reaching the last milestone and being lost, among
+/// the losses for which it is known.
+fn average_time_since_milestone(losses: &[&Loss]) -> Option<TimeDelta> {
+    let times: Vec<_> = losses.iter().filter_map(|loss| loss.time_since_milestone).collect();
+    if times.is_empty() {
+        None
+    } else {
+        Some(times.iter().sum::<TimeDelta>() / times.len().try_into().unwrap())
+    }
+}
+
+/// The job numbers of the lost jobs, each linking to the job in JobNimbus.
+fn job_links(losses: &[&Loss]) -> Cell {
+    Cell::links(losses.iter().map(|loss| {
+        let number = loss.job.job_number.as_deref().unwrap_or(&loss.job.jnid);
+        (number, job_nimbus::job_url(&loss.job.jnid))
+    }))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::jobs::{test_util::day, MilestoneDates, Status};
+
+    fn job(sales_rep: &str, milestone_dates: MilestoneDates, loss_reason: Option<&str>) -> Job {
+        let status =
+            if milestone_dates.loss_date.is_some() { Status::Lost } else { Status::JobsInProgress };
+        Job {
+            milestone_dates,
+            status,
+            sales_rep: Some(sales_rep.to_owned()),
+            loss_reason: loss_reason.map(str::to_owned),
+            ..Job::test_default()
+        }
+    }
+
+    fn lost(sales_rep: &str, appointment: i64, lost: i64, reason: Option<&str>) -> Job {
+        let dates = MilestoneDates {
+            appointment_date: day(appointment),
+            loss_date: day(lost),
+            ..MilestoneDates::default()
+        };
+        job(sales_rep, dates, reason)
+    }
+
+    #[test]
+    fn losses_are_broken_down_by_subject_and_reason() {
+        let settled = MilestoneDates {
+            appointment_date: day(0),
+            contract_date: day(5),
+            install_date: day(10),
+            ..MilestoneDates::default()
+        };
+        let open = MilestoneDates { appointment_date: day(20), ..MilestoneDates::default() };
+        let jobs = vec![
+            lost("Jane Doe", 0, 4, Some("Price")),
+            lost("Jane Doe", 10, 12, Some("Price")),
+            lost("Jane Doe", 10, 20, None),
+            Job { jnid: "abc".to_owned(), ..lost("John Doe", 30, 31, Some("Price")) },
+            // lost before the date range
+            lost("John Doe", 0, 1, Some("Timing")),
+            job("Jane Doe", settled, None),
+            job("John Doe", open, None),
+        ];
+
+        let losses = find_losses(jobs, (day(2), day(100)), &Config::default());
+        let counts: Vec<_> =
+            losses.iter().map(|(subject, losses)| (subject, losses.len())).collect();
+        let jane = KpiSubject::SalesRep("Jane Doe".to_owned());
+        let john = KpiSubject::SalesRep("John Doe".to_owned());
+        assert_eq!(counts, [(&KpiSubject::Global, 4), (&jane, 3), (&john, 1)]);
+
+        let reasons = |subject: &KpiSubject| -> Vec<(String, usize, Option<f64>)> {
+            let breakdown = break_down(&losses[subject]);
+            breakdown[&JobKind::Retail][&Milestone::AppointmentMade]
+                .iter()
+                .map(|(reason, losses)| {
+                    let days = average_time_since_milestone(losses).map(report::days);
+                    (reason.to_string(), losses.len(), days)
+                })
+                .collect()
+        };
+        assert_eq!(
+            reasons(&jane),
+            [("Price".to_owned(), 2, Some(3.0)), (NO_REASON.to_owned(), 1, Some(10.0))]
+        );
+        assert_eq!(
+            reasons(&KpiSubject::Global),
+            [("Price".to_owned(), 3, Some(7.0 / 3.0)), (NO_REASON.to_owned(), 1, Some(10.0))]
+        );
+
+        // the lost jobs link to JobNimbus
+        let breakdowns: BTreeMap<_, _> = [(&john, break_down(&losses[&john]))].into();
+        let report = report(&breakdowns);
+        let row = &report.sections[0].tables[0].rows[0];
+        assert_eq!(row.last(), Some(&Cell::links([("abc", job_nimbus::job_url("abc"))])));
+    }
+}
@@ -0,0 +1,360 @@
+use std::{collections::BTreeMap, path::PathBuf};
+
+use anyhow::{anyhow, bail, Context as _};
+use clap::CommandFactory as _;
+use lettre::{
+    message::Mailbox, transport::smtp::authentication::Credentials, Message, SmtpTransport,
+    Transport as _,
+};
+use tracing::{error, info, warn};
+
+use crate::{
+    apis::job_nimbus,
+    config::{Config, SmtpConfig},
+    jobs::{self, Job, JobAnalysisError},
+    reps,
+    subcommands::kpi::KpiSubject,
+    CliArgs,
+};
+
+#[derive(clap::Args, Debug)]
+pub struct Args {
+    /// The filter to use when query JobNimbus for jobs, using ElasticSearch
+    /// syntax.
+    #[arg(short, long = "filter", default_value = None)]
+    filter_filename: Option<String>,
+
+    /// How to deliver the digests.
+    #[arg(long, value_enum, default_value = "email")]
+    via: Delivery,
+
+    /// Instead of sending the digests, write each of them to a file in the
+    /// specified directory.
+    #[arg(long, default_value = None)]
+    dry_run: Option<PathBuf>,
+
+    /// The password for the SMTP server, if it requires authentication.
+    #[arg(long, default_value = None, env)]
+    smtp_password: Option<String>,
+}
+
+#[derive(Debug, clap::ValueEnum, Clone, Copy, Eq, PartialEq)]
+enum Delivery {
+    /// Sends an email to each sales rep through the SMTP server in the
+    /// configuration.
+    Email,
+    /// Posts a message for each sales rep to the webhook in the configuration,
+    /// e.g. a Slack or Teams channel.
+    Webhook,
+}
+
+/// The red flags of one sales rep's jobs.
+#[derive(Debug, PartialEq)]
+struct Digest {
+    rep: KpiSubject,
+    /// Where the digest is emailed. `None` if there is nowhere to send it.
+    email: Option<String>,
+    subject: String,
+    body: String,
+}
+
+pub fn main(api_key: &str, config: &Config, args: Args) -> anyhow::Result<()> {
+    let Args { filter_filename, via, dry_run, smtp_password } = args;
+    if dry_run.is_none() {
+        let missing = match via {
+            Delivery::Email if config.notify.smtp.is_none() => Some("notify.smtp"),
+            Delivery::Webhook if config.notify.webhook_url.is_none() => Some("notify.webhook_url"),
+            _ => None,
+        };
+        if let Some(missing) = missing {
+            CliArgs::command()
+                .error(
+                    clap::error::ErrorKind::MissingRequiredArgument,
+                    format!("`{}` must be set in the configuration to send notifications", missing),
+                )
+                .exit();
+        }
+    }
+
+    let filter = if let Some(filter_filename) = filter_filename {
+        Some(std::fs::read_to_string(filter_filename)?)
+    } else {
+        None
+    };
+    let mut jobs =
+        job_nimbus::get_all_jobs_from_job_nimbus(api_key, filter.as_deref(), &config.job_fields)?;
+    reps::normalize_sales_reps(&mut jobs, config);
+
+    let red_flags = find_red_flags(jobs, config);
+    let digests = make_digests(&red_flags, config);
+    info!("Prepared digests for {} sales reps", digests.len());
+
+    if let Some(dir) = dry_run {
+        std::fs::create_dir_all(&dir)?;
+        for digest in &digests {
+            let (extension, contents) = match via {
+                Delivery::Email => {
+                    let from = config
+                        .notify
+                        .smtp
+                        .as_ref()
+                        .map(|smtp| smtp.from.as_str())
+                        .unwrap_or("ahitool@localhost");
+                    let Some(message) = email_message(digest, from)? else {
+                        warn!("Skipping the digest for {}, who has no email address", digest.rep);
+                        continue;
+                    };
+                    ("eml", message.formatted())
+                }
+                Delivery::Webhook => ("json", serde_json::to_vec_pretty(&webhook_payload(digest))?),
+            };
+            let path = dir.join(format!("{}.{}", file_name(&digest.rep), extension));
+            std::fs::write(&path, contents)?;
+            info!("Wrote the digest for {} to {}", digest.rep, path.display());
+        }
+        return Ok(());
+    }
+
+    match via {
+        Delivery::Email => {
+            let smtp = config.notify.smtp.as_ref().expect("checked above");
+            send_emails(&digests, smtp, smtp_password)?;
+        }
+        Delivery::Webhook => {
+            let url = config.notify.webhook_url.as_ref().expect("checked above");
+            let client = reqwest::blocking::Client::new();
+            let mut failed = Vec::new();
+            for digest in &digests {
+                let result = client
+                    .post(url)
+                    .json(&webhook_payload(digest))
+                    .send()
+                    .map_err(anyhow::Error::from)
+                    .and_then(|response| match response.status() {
+                        status if status.is_success() => Ok(()),
+                        status => Err(anyhow!("the webhook responded with status code {}", status)),
+                    });
+                match result {
+                    Ok(()) => info!("Posted the digest for {}", digest.rep),
+                    Err(e) => {
+                        error!("Failed to post the digest for {}: {:#}", digest.rep, e);
+                        failed.push(&digest.rep);
+                    }
+                }
+            }
+            check_delivered(digests.len(), &failed)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Finds the errors in each job, grouped by sales rep. Jobs without errors are
+/// left out.
+fn find_red_flags(
+    jobs: Vec<Job>,
+    config: &Config,
+) -> BTreeMap<KpiSubject, Vec<(Job, Vec<JobAnalysisError>)>> {
+    let mut red_flags: BTreeMap<KpiSubject, Vec<_>> = BTreeMap::new();
+    for job in jobs {
+        let (analyzed, errors) = jobs::analyze_job_with_strictness(job, config.strictness);
+        if errors.is_empty() {
+            continue;
+        }
+        let rep = match analyzed.job.sales_rep.clone() {
+            Some(name) => KpiSubject::SalesRep(name),
+            None => KpiSubject::UnknownSalesRep,
+        };
+        red_flags.entry(rep).or_default().push((analyzed.job, errors));
+    }
+    red_flags
+}
+
+fn make_digests(
+    red_flags: &BTreeMap<KpiSubject, Vec<(Job, Vec<JobAnalysisError>)>>,
+    config: &Config,
+) -> Vec<Digest> {
+    red_flags
+        .iter()
+        .map(|(rep, jobs)| {
+            let email = match rep {
+                KpiSubject::SalesRep(name) => {
+                    config.find_rep(name).and_then(|placement| placement.rep.email.clone())
+                }
+                _ => None,
+            }
+            .or_else(|| config.notify.fallback_email.clone());
+
+            let mut body = format!(
+                "The following {} job(s) for {} need to be fixed in JobNimbus:\n\n",
+                jobs.len(),
+                rep
+            );
+            for (job, errors) in jobs {
+                body += &format!(
+                    "Job {} ({}): {}\n",
+                    job.job_number.as_deref().unwrap_or(&job.jnid),
+                    job.job_name.as_deref().unwrap_or("unnamed"),
+                    job_nimbus::job_url(&job.jnid)
+                );
+                for error in errors {
+                    body += &format!("    - {}\n", error);
+                }
+            }
+
+            Digest {
+                rep: rep.clone(),
+                email,
+                subject: format!("{} job(s) with red flags for {}", jobs.len(), rep),
+                body,
+            }
+        })
+        .collect()
+}
+
+/// Builds the email for a digest. Returns `None` if there is nowhere to send
+/// the digest.
+fn email_message(digest: &Digest, from: &str) -> anyhow::Result<Option<Message>> {
+    let Some(to) = &digest.email else {
+        return Ok(None);
+    };
+    let message = Message::builder()
+        .from(from.parse::<Mailbox>().context("Invalid sender address")?)
+        .to(to.parse::<Mailbox>().with_context(|| format!("Invalid address {}", to))?)
+        .subject(&digest.subject)
+        .body(digest.body.clone())?;
+    Ok(Some(message))
+}
+
+fn send_emails(
+    digests: &[Digest],
+    smtp: &SmtpConfig,
+    password: Option<String>,
+) -> anyhow::Result<()> {
+    let mut builder = if smtp.tls {
+        SmtpTransport::starttls_relay(&smtp.host)?.port(smtp.port.unwrap_or(587))
+    } else {
+        SmtpTransport::builder_dangerous(&smtp.host).port(smtp.port.unwrap_or(25))
+    };
+    if let Some(username) = &smtp.username {
+        builder =
+            builder.credentials(Credentials::new(username.clone(), password.unwrap_or_default()));
+    }
+    let transport = builder.build();
+
+    let mut failed = Vec::new();
+    let mut num_sent = 0;
+    for digest in digests {
+        let Some(message) = email_message(digest, &smtp.from)? else {
+            warn!("Skipping the digest for {}, who has no email address", digest.rep);
+            continue;
+        };
+        num_sent += 1;
+        match transport.send(&message) {
+            Ok(_) => info!("Emailed the digest for {}", digest.rep),
+            Err(e) => {
+                error!("Failed to email the digest for {}: {}", digest.rep, e);
+                failed.push(&digest.rep);
+            }
+        }
+    }
+    check_delivered(num_sent, &failed)
+}
+
+/// Fails with the reps whose digests couldn't be delivered, if there are any,
+/// so that they can be notified another way. The other reps were notified.
+fn check_delivered(num_digests: usize, failed: &[&KpiSubject]) -> anyhow::Result<()> {
+    if failed.is_empty() {
+        return Ok(());
+    }
+    let failed: Vec<_> = failed.iter().map(|rep| rep.to_string()).collect();
+    bail!(
+        "Failed to notify {} of {} sales reps: {}. The other sales reps were notified.",
+        failed.len(),
+        num_digests,
+        failed.join(", ")
+    )
+}
+
+/// The JSON posted to a webhook. Both Slack and Teams incoming webhooks accept
+/// a message in the `text` field.
+fn webhook_payload(digest: &Digest) -> serde_json::Value {
+    serde_json::json!({ "text": format!("*{}*\n{}", digest.subject, digest.body) })
+}
+
+fn file_name(rep: &KpiSubject) -> String {
+    rep.to_string().chars().map(|c| if c.is_alphanumeric() { c } else { '_' }).collect()
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::DateTime;
+
+    use super::*;
+    use crate::{
+        config::{Branch, Rep, Team},
+        jobs::MilestoneDates,
+    };
+
+    #[test]
+    fn digests_group_red_flags_by_rep() {
+        let dt = |seconds| DateTime::from_timestamp(seconds, 0);
+        let job = |jnid: &str, sales_rep: Option<&str>| Job {
+            jnid: jnid.to_owned(),
+            milestone_dates: MilestoneDates {
+                appointment_date: dt(2),
+                contract_date: dt(1),
+                ..MilestoneDates::default()
+            },
+            sales_rep: sales_rep.map(str::to_owned),
+            job_number: Some(jnid.to_owned()),
+            ..Job::test_default()
+        };
+        let config = Config {
+            branches: vec![Branch {
+                name: "Main".to_owned(),
+                teams: vec![Team {
+                    name: "Team A".to_owned(),
+                    reps: vec![Rep {
+                        name: "Heinrich Asbury".to_owned(),
+                        aliases: vec![],
+                        email: Some("heinrich@example.com".to_owned()),
+                    }],
+                }],
+            }],
+            ..Default::default()
+        };
+        let jobs = vec![
+            job("1", Some("Heinrich Asbury")),
+            job("2", None),
+            Job {
+                milestone_dates: MilestoneDates {
+                    contract_date: None,
+                    ..job("", None).milestone_dates
+                },
+                ..job("3", Some("Heinrich Asbury"))
+            },
+        ];
+
+        let digests = make_digests(&find_red_flags(jobs, &config), &config);
+        assert_eq!(digests.len(), 2);
+        assert_eq!(digests[0].rep, KpiSubject::SalesRep("Heinrich Asbury".to_owned()));
+        assert_eq!(digests[0].email.as_deref(), Some("heinrich@example.com"));
+        assert!(digests[0].body.contains("https://app.jobnimbus.com/job/1"));
+        assert!(!digests[0].body.contains("job/3"));
+        assert_eq!(digests[1].rep, KpiSubject::UnknownSalesRep);
+        assert_eq!(digests[1].email, None);
+        assert!(email_message(&digests[0], "ahitool@example.com").unwrap().is_some());
+    }
+
+    #[test]
+    fn failed_deliveries_name_the_reps() {
+        assert!(check_delivered(2, &[]).is_ok());
+        let rep = KpiSubject::SalesRep("Heinrich Asbury".to_owned());
+        assert_eq!(
+            check_delivered(2, &[&rep]).unwrap_err().to_string(),
+            "Failed to notify 1 of 2 sales reps: Heinrich Asbury. \
+             The other sales reps were notified."
+        );
+    }
+}
@@ -0,0 +1,281 @@
+use std::{cmp::Reverse, collections::BTreeMap};
+
+use chrono::Utc;
+use serde::Serialize;
+use tracing::info;
+
+use crate::{
+    apis::{google_sheets::SheetNickname, job_nimbus},
+    config::Config,
+    jobs::{self, AnalyzedJob, Job, Milestone, TimeDelta, Timestamp},
+    report::{self, Cell, OutputFormat, Report},
+    reps,
+    subcommands::kpi::{processing, GroupBy, KpiSubject},
+};
+
+#[derive(clap::Args, Debug)]
+pub struct Args {
+    /// The filter to use when query JobNimbus for jobs, using ElasticSearch
+    /// syntax.
+    #[arg(short, long = "filter", default_value = None)]
+    filter_filename: Option<String>,
+
+    /// A job is flagged as stalled if it has been at its current milestone for
+    /// longer than this multiple of the historical average time it takes to
+    /// reach the next milestone.
+    #[arg(long, default_value = "2.0")]
+    stall_factor: f64,
+
+    /// The format in which to print the output.
+    #[arg(long, value_enum, default_value = "human")]
+    format: OutputFormat,
+
+    /// The file to write the output to. "-" or unspecified will write to
+    /// stdout. This option is ignored with `--format google-sheets`.
+    #[arg(short, long, default_value = None)]
+    output: Option<String>,
+
+    /// Only valid with `--format google-sheets`. Whether to update an existing
+    /// Google Sheet; if not specified, creates a new Google Sheet.
+    #[arg(long)]
+    update: bool,
+}
+
+/// A job that has not been settled yet.
+struct OpenJob {
+    job: Job,
+    /// The last milestone that the job has reached.
+    milestone: Milestone,
+    /// How long the job has been at its current milestone. `None` if the date
+    /// at which the job reached its current milestone is unknown.
+    time_at_stage: Option<TimeDelta>,
+    /// The historical average time that settled jobs of the same kind took to
+    /// get from this milestone to the next one. `None` if there is no
+    /// historical data for this transition.
+    expected_time: Option<TimeDelta>,
+    /// Whether the job has been at its current milestone for too long compared
+    /// to `expected_time`.
+    stalled: bool,
+}
+
+pub fn main(api_key: &str, config: &Config, args: Args) -> anyhow::Result<()> {
+    let Args { filter_filename, stall_factor, format, output, update } = args;
+    report::check_output_args(format, output.as_deref(), update);
+
+    let filter = if let Some(filter_filename) = filter_filename {
+        Some(std::fs::read_to_string(filter_filename)?)
+    } else {
+        None
+    };
+    let mut jobs =
+        job_nimbus::get_all_jobs_from_job_nimbus(api_key, filter.as_deref(), &config.job_fields)?;
+    reps::normalize_sales_reps(&mut jobs, config);
+
+    let open_jobs = find_open_jobs(jobs, stall_factor, config, Utc::now());
+
+    report::output(&report(&open_jobs), format, output.as_deref(), update, SheetNickname::Pipeline)
+}
+
+/// Finds all jobs that have not been settled yet, grouped by sales rep. The
+/// jobs of each sales rep are ordered by milestone, and then by the time spent
+/// at that milestone (longest first).
+fn find_open_jobs(
+    jobs: Vec<Job>,
+    stall_factor: f64,
+    config: &Config,
+    now: Timestamp,
+) -> BTreeMap<KpiSubject, Vec<OpenJob>> {
+    // the historical averages come from all settled jobs, regardless of rep
+    let (mut trackers, _) =
+        processing::process_jobs(jobs.iter().cloned(), (None, None), GroupBy::SalesRep, config);
+    let historical = trackers.remove(&KpiSubject::Global);
+
+    let mut open_jobs: BTreeMap<KpiSubject, Vec<OpenJob>> = BTreeMap::new();
+    for job in jobs {
+        // jobs with errors are skipped here; they show up as red flags in the
+        // KPI report instead
+        let (AnalyzedJob { job, analysis: Some(analysis) }, _) =
+            jobs::analyze_job_with_strictness(job, config.strictness)
+        else {
+            continue;
+        };
+        if analysis.date_settled().is_some() {
+            continue;
+        }
+
+        let milestone = analysis.current_milestone();
+        let time_at_stage = analysis
+            .timestamps
+            .iter()
+            .rev()
+            .find_map(|&ts| ts)
+            .or(job.date_created)
+            .map(|date_reached| now - date_reached);
+        let expected_time = historical.as_ref().and_then(|tracker| {
+            let kind = analysis.kind.into_int();
+            let next = tracker.next_milestone(kind, milestone.into_int())?;
+            tracker
+                .get_bucket(kind, next)?
+                .average_achieve_time()
+                // a zero average means that none of the historical jobs had
+                // dates for both milestones, so it is as good as no data
+                .filter(|avg| *avg > TimeDelta::zero())
+        });
+        let stalled = match (time_at_stage, expected_time) {
+            (Some(time_at_stage), Some(expected_time)) => {
+                time_at_stage.num_seconds() as f64
+                    > expected_time.num_seconds() as f64 * stall_factor
+            }
+            _ => false,
+        };
+
+        let rep = match job.sales_rep.clone() {
+            Some(name) => KpiSubject::SalesRep(name),
+            None => KpiSubject::UnknownSalesRep,
+        };
+        open_jobs.entry(rep).or_default().push(OpenJob {
+            job,
+            milestone,
+            time_at_stage,
+            expected_time,
+            stalled,
+        });
+    }
+
+    for jobs in open_jobs.values_mut() {
+        jobs.sort_by_key(|open_job| (open_job.milestone, Reverse(open_job.time_at_stage)));
+    }
+    info!("Found {} open jobs", open_jobs.values().map(|jobs| jobs.len()).sum::<usize>());
+
+    open_jobs
+}
+
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Record<'a> {
+    OpenJob {
+        sales_rep: String,
+        job_id: &'a str,
+        job_number: Option<&'a str>,
+        job_name: Option<&'a str>,
+        status: String,
+        milestone: Milestone,
+        days_at_stage: Option<f64>,
+        /// The historical average number of days to reach the next milestone.
+        expected_days: Option<f64>,
+        stalled: bool,
+    },
+}
+
+/// The report has a summary of the number of open jobs at each milestone,
+/// followed by the open jobs of every sales rep.
+fn report(open_jobs: &BTreeMap<KpiSubject, Vec<OpenJob>>) -> Report {
+    let mut report = Report::new(format!("Pipeline Report ({})", Utc::now()));
+    let all_jobs = || open_jobs.values().flatten();
+
+    for (rep, jobs) in open_jobs {
+        for open_job in jobs {
+            report.record(Record::OpenJob {
+                sales_rep: rep.to_string(),
+                job_id: &open_job.job.jnid,
+                job_number: open_job.job.job_number.as_deref(),
+                job_name: open_job.job.job_name.as_deref(),
+                status: open_job.job.status.to_string(),
+                milestone: open_job.milestone,
+                days_at_stage: open_job.time_at_stage.map(report::days),
+                expected_days: open_job.expected_time.map(report::days),
+                stalled: open_job.stalled,
+            });
+        }
+    }
+
+    let table = report.section("Summary").table(["Milestone", "Open", "Stalled"]);
+    let mut add_row = |name: String, jobs: Vec<&OpenJob>| {
+        let stalled = jobs.iter().filter(|open_job| open_job.stalled).count();
+        table.row([
+            Cell::text(name),
+            Cell::Number(jobs.len() as f64),
+            Cell::Number(stalled as f64),
+        ]);
+    };
+    add_row("All".to_string(), all_jobs().collect());
+    for milestone in Milestone::ordered_iter() {
+        let at_milestone: Vec<_> =
+            all_jobs().filter(|open_job| open_job.milestone == milestone).collect();
+        if !at_milestone.is_empty() {
+            add_row(milestone.to_string(), at_milestone);
+        }
+    }
+
+    let table = report.section("Pipeline").table([
+        "Sales Rep",
+        "Job Name",
+        "Job Number",
+        "Job Status",
+        "Milestone",
+        "Days At Stage",
+        "Avg Days To Next Milestone",
+        "Stalled",
+    ]);
+    for (rep, jobs) in open_jobs {
+        for open_job in jobs {
+            table.row([
+                Cell::text(rep.to_string()),
+                Cell::text(open_job.job.job_name.as_deref().unwrap_or("")),
+                Cell::link(
+                    open_job.job.job_number.as_deref().unwrap_or("Unknown Job Number"),
+                    job_nimbus::job_url(&open_job.job.jnid),
+                ),
+                Cell::text(open_job.job.status.to_string()),
+                Cell::text(open_job.milestone.to_string()),
+                open_job.time_at_stage.map_or(Cell::Empty, Cell::days),
+                open_job.expected_time.map_or(Cell::Empty, Cell::days),
+                Cell::Bool(open_job.stalled),
+            ]);
+        }
+    }
+    let flat = table.clone();
+    report.flat = Some(flat);
+
+    report
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::jobs::{test_util::day, MilestoneDates};
+
+    #[test]
+    fn jobs_far_past_the_average_time_to_the_next_milestone_are_stalled() {
+        let job = |jnid: &str, milestone_dates: MilestoneDates| Job {
+            jnid: jnid.to_owned(),
+            milestone_dates,
+            ..Job::test_default()
+        };
+        let appointment_on =
+            |date| MilestoneDates { appointment_date: date, ..MilestoneDates::default() };
+        let jobs = vec![
+            // settled retail jobs took 10 days from the appointment to the
+            // contract on average
+            job(
+                "settled",
+                MilestoneDates {
+                    contract_date: day(10),
+                    install_date: day(20),
+                    ..appointment_on(day(0))
+                },
+            ),
+            job("stalled", appointment_on(day(70))),
+            job("on track", appointment_on(day(90))),
+        ];
+
+        let open_jobs = find_open_jobs(jobs, 2.0, &Config::default(), day(100).unwrap());
+        let open_jobs = &open_jobs[&KpiSubject::UnknownSalesRep];
+        let stalled: Vec<_> = open_jobs
+            .iter()
+            .map(|open_job| (open_job.job.jnid.as_str(), open_job.stalled))
+            .collect();
+        assert_eq!(stalled, [("stalled", true), ("on track", false)]);
+        assert_eq!(open_jobs[0].expected_time, Some(TimeDelta::days(10)));
+    }
+}
@@ -0,0 +1,137 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use chrono::Utc;
+use serde::Serialize;
+
+use crate::{
+    apis::{google_sheets::SheetNickname, job_nimbus},
+    config::Config,
+    report::{self, Cell, OutputFormat, Report},
+    reps,
+};
+
+#[derive(clap::Args, Debug)]
+pub struct Args {
+    /// The filter to use when query JobNimbus for jobs, using ElasticSearch
+    /// syntax.
+    #[arg(short, long = "filter", default_value = None)]
+    filter_filename: Option<String>,
+
+    /// The format in which to print the output.
+    #[arg(long, value_enum, default_value = "human")]
+    format: OutputFormat,
+
+    /// The file to write the output to. "-" or unspecified will write to
+    /// stdout. This option is ignored with `--format google-sheets`.
+    #[arg(short, long, default_value = None)]
+    output: Option<String>,
+
+    /// Only valid with `--format google-sheets`. Whether to update an existing
+    /// Google Sheet; if not specified, creates a new Google Sheet.
+    #[arg(long)]
+    update: bool,
+}
+
+/// A sales rep as identified after normalizing names.
+#[derive(Debug, Default)]
+struct RepInfo {
+    num_jobs: usize,
+    /// The different spellings of the rep's name that appear in JobNimbus.
+    spellings: BTreeSet<String>,
+    /// The other reps that are probably the same as this rep.
+    probable_duplicates: Vec<(String, f64)>,
+}
+
+pub fn main(api_key: &str, config: &Config, args: Args) -> anyhow::Result<()> {
+    let Args { filter_filename, format, output, update } = args;
+    report::check_output_args(format, output.as_deref(), update);
+
+    let filter = if let Some(filter_filename) = filter_filename {
+        Some(std::fs::read_to_string(filter_filename)?)
+    } else {
+        None
+    };
+    let jobs =
+        job_nimbus::get_all_jobs_from_job_nimbus(api_key, filter.as_deref(), &config.job_fields)?;
+
+    let canonical_names =
+        reps::canonical_names(jobs.iter().filter_map(|job| job.sales_rep.as_deref()), config);
+    let mut rep_infos: BTreeMap<String, RepInfo> = BTreeMap::new();
+    for name in jobs.iter().filter_map(|job| job.sales_rep.as_deref()) {
+        let rep_info = rep_infos.entry(canonical_names[name].clone()).or_default();
+        rep_info.num_jobs += 1;
+        rep_info.spellings.insert(name.to_owned());
+    }
+    let names: Vec<_> = rep_infos.keys().map(String::as_str).collect();
+    for reps::ProbableDuplicate { names: (a, b), similarity } in
+        reps::find_probable_duplicates(&names, config)
+    {
+        rep_infos.get_mut(&a).unwrap().probable_duplicates.push((b.clone(), similarity));
+        rep_infos.get_mut(&b).unwrap().probable_duplicates.push((a, similarity));
+    }
+
+    report::output(&report(&rep_infos), format, output.as_deref(), update, SheetNickname::Reps)
+}
+
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Record<'a> {
+    Rep {
+        name: &'a str,
+        jobs: usize,
+        spellings: &'a BTreeSet<String>,
+        probable_duplicates: Vec<ProbableDuplicate<'a>>,
+    },
+}
+
+#[derive(Serialize)]
+struct ProbableDuplicate<'a> {
+    name: &'a str,
+    similarity: f64,
+}
+
+/// The report has a row for each sales rep, with the different spellings of
+/// their name and the reps that are probably the same.
+fn report(rep_infos: &BTreeMap<String, RepInfo>) -> Report {
+    let mut report = Report::new(format!("Sales Reps ({})", Utc::now()));
+    for (name, rep_info) in rep_infos {
+        report.record(Record::Rep {
+            name,
+            jobs: rep_info.num_jobs,
+            spellings: &rep_info.spellings,
+            probable_duplicates: rep_info
+                .probable_duplicates
+                .iter()
+                .map(|(other, similarity)| ProbableDuplicate {
+                    name: other,
+                    similarity: *similarity,
+                })
+                .collect(),
+        });
+    }
+
+    let table = report.section("Sales Reps").table([
+        "Sales Rep",
+        "Jobs",
+        "Spellings",
+        "Probable Duplicates",
+    ]);
+    for (name, rep_info) in rep_infos {
+        table.row([
+            Cell::text(name.clone()),
+            Cell::Number(rep_info.num_jobs as f64),
+            Cell::text(rep_info.spellings.iter().cloned().collect::<Vec<_>>().join("; ")),
+            Cell::text(
+                rep_info
+                    .probable_duplicates
+                    .iter()
+                    .map(|(other, similarity)| {
+                        format!("{} ({:.0}% similar)", other, similarity * 100.0)
+                    })
+                    .collect::<Vec<_>>()
+                    .join("; "),
+            ),
+        ]);
+    }
+    report
+}
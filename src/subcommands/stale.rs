@@ -0,0 +1,233 @@
+use std::{cmp::Reverse, collections::BTreeMap};
+
+use chrono::Utc;
+use serde::Serialize;
+use tracing::info;
+
+use crate::{
+    apis::{google_sheets::SheetNickname, job_nimbus},
+    config::Config,
+    jobs::{Job, Status, TimeDelta, Timestamp},
+    report::{self, Cell, OutputFormat, Report},
+    reps,
+    subcommands::kpi::KpiSubject,
+};
+
+#[derive(clap::Args, Debug)]
+pub struct Args {
+    /// The filter to use when query JobNimbus for jobs, using ElasticSearch
+    /// syntax.
+    #[arg(short, long = "filter", default_value = None)]
+    filter_filename: Option<String>,
+
+    /// The format in which to print the output.
+    #[arg(long, value_enum, default_value = "human")]
+    format: OutputFormat,
+
+    /// The file to write the output to. "-" or unspecified will write to
+    /// stdout. This option is ignored with `--format google-sheets`.
+    #[arg(short, long, default_value = None)]
+    output: Option<String>,
+
+    /// Only valid with `--format google-sheets`. Whether to update an existing
+    /// Google Sheet; if not specified, creates a new Google Sheet.
+    #[arg(long)]
+    update: bool,
+}
+
+/// The default number of days that a job can stay in each non-terminal status
+/// before it is stale. Statuses that aren't listed (including custom statuses)
+/// are only checked if they are given a threshold in the configuration.
+const DEFAULT_THRESHOLDS: [(Status, u32); 8] = [
+    (Status::IncomingLead, 3),
+    (Status::JobsInProgress, 30),
+    (Status::PendingPayments, 30),
+    (Status::PostInstallSupplementPending, 30),
+    (Status::FinalWalkAround, 7),
+    (Status::SubmitCoc, 7),
+    (Status::PunchList, 14),
+    (Status::Collections, 30),
+];
+
+/// A job that has been in its status for longer than the threshold.
+struct StaleJob {
+    job: Job,
+    time_in_status: TimeDelta,
+    /// The number of days that the job could have stayed in its status.
+    threshold_days: u32,
+}
+
+/// The stale jobs of each sales rep, grouped by status. The jobs of each status
+/// are ordered by the time spent in the status (longest first).
+type StaleJobs = BTreeMap<KpiSubject, BTreeMap<String, Vec<StaleJob>>>;
+
+pub fn main(api_key: &str, config: &Config, args: Args) -> anyhow::Result<()> {
+    let Args { filter_filename, format, output, update } = args;
+    report::check_output_args(format, output.as_deref(), update);
+
+    let filter = if let Some(filter_filename) = filter_filename {
+        Some(std::fs::read_to_string(filter_filename)?)
+    } else {
+        None
+    };
+    let mut jobs =
+        job_nimbus::get_all_jobs_from_job_nimbus(api_key, filter.as_deref(), &config.job_fields)?;
+    reps::normalize_sales_reps(&mut jobs, config);
+
+    let thresholds = thresholds(config);
+    let stale_jobs = find_stale_jobs(jobs, &thresholds, Utc::now());
+
+    report::output(&report(&stale_jobs), format, output.as_deref(), update, SheetNickname::Stale)
+}
+
+/// The threshold of each status in days, by the name of the status. The
+/// thresholds in the configuration override the defaults.
+fn thresholds(config: &Config) -> BTreeMap<String, u32> {
+    let mut thresholds: BTreeMap<String, u32> =
+        DEFAULT_THRESHOLDS.iter().map(|(status, days)| (status.to_string(), *days)).collect();
+    for (status, days) in &config.stale_thresholds {
+        match days {
+            Some(days) => thresholds.insert(status.clone(), *days),
+            None => thresholds.remove(status),
+        };
+    }
+    thresholds
+}
+
+fn find_stale_jobs(
+    jobs: Vec<Job>,
+    thresholds: &BTreeMap<String, u32>,
+    now: Timestamp,
+) -> StaleJobs {
+    let mut stale_jobs: StaleJobs = BTreeMap::new();
+    let mut num_stale = 0;
+    for job in jobs {
+        let status = job.status.to_string();
+        let Some(&threshold_days) = thresholds.get(&status) else {
+            continue;
+        };
+        let time_in_status = now - job.status_mod_date;
+        if time_in_status <= TimeDelta::days(threshold_days.into()) {
+            continue;
+        }
+
+        let rep = match job.sales_rep.clone() {
+            Some(name) => KpiSubject::SalesRep(name),
+            None => KpiSubject::UnknownSalesRep,
+        };
+        stale_jobs.entry(rep).or_default().entry(status).or_default().push(StaleJob {
+            job,
+            time_in_status,
+            threshold_days,
+        });
+        num_stale += 1;
+    }
+    for jobs in stale_jobs.values_mut().flat_map(BTreeMap::values_mut) {
+        jobs.sort_by_key(|stale| Reverse(stale.time_in_status));
+    }
+
+    info!("Found {} stale jobs", num_stale);
+    stale_jobs
+}
+
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Record<'a> {
+    StaleJob {
+        sales_rep: String,
+        status: &'a str,
+        job_id: &'a str,
+        job_number: Option<&'a str>,
+        job_name: Option<&'a str>,
+        days_in_status: f64,
+        threshold_days: u32,
+    },
+}
+
+/// The report has a row for each stale job, grouped by sales rep and status.
+fn report(stale_jobs: &StaleJobs) -> Report {
+    let mut report = Report::new(format!("Stale Job Report ({})", Utc::now()));
+    for (rep, by_status) in stale_jobs {
+        for (status, jobs) in by_status {
+            for stale in jobs {
+                report.record(Record::StaleJob {
+                    sales_rep: rep.to_string(),
+                    status,
+                    job_id: &stale.job.jnid,
+                    job_number: stale.job.job_number.as_deref(),
+                    job_name: stale.job.job_name.as_deref(),
+                    days_in_status: report::days(stale.time_in_status),
+                    threshold_days: stale.threshold_days,
+                });
+            }
+        }
+    }
+    let table = report.section("Stale Jobs").table([
+        "Sales Rep",
+        "Status",
+        "Job Number",
+        "Job Name",
+        "Days in Status",
+        "Threshold (Days)",
+    ]);
+    for (rep, by_status) in stale_jobs {
+        for (status, jobs) in by_status {
+            for stale in jobs {
+                table.row([
+                    Cell::text(rep.to_string()),
+                    Cell::text(status.clone()),
+                    Cell::link(
+                        stale.job.job_number.as_deref().unwrap_or(&stale.job.jnid),
+                        job_nimbus::job_url(&stale.job.jnid),
+                    ),
+                    Cell::text(stale.job.job_name.clone().unwrap_or_default()),
+                    Cell::days(stale.time_in_status),
+                    Cell::Number(stale.threshold_days.into()),
+                ]);
+            }
+        }
+    }
+    report
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::DateTime;
+
+    use super::*;
+
+    #[test]
+    fn jobs_past_their_status_threshold_are_stale() {
+        let now = DateTime::from_timestamp(100 * 86400, 0).unwrap();
+        let job = |jnid: &str, status: Status, days_in_status: i64| Job {
+            jnid: jnid.to_owned(),
+            status,
+            status_mod_date: now - TimeDelta::days(days_in_status),
+            ..Job::test_default()
+        };
+        let config = Config {
+            stale_thresholds: BTreeMap::from([
+                ("Collections".to_owned(), None),
+                ("Awaiting Permit".to_owned(), Some(5)),
+            ]),
+            ..Default::default()
+        };
+        let jobs = vec![
+            job("fresh lead", Status::IncomingLead, 2),
+            job("stale lead", Status::IncomingLead, 4),
+            job("staler lead", Status::IncomingLead, 10),
+            job("completed", Status::JobCompleted, 50),
+            job("collections", Status::Collections, 50),
+            job("custom", Status::Other("Awaiting Permit".to_owned()), 6),
+        ];
+
+        let stale_jobs = find_stale_jobs(jobs, &thresholds(&config), now);
+        let by_status = &stale_jobs[&KpiSubject::UnknownSalesRep];
+        let jnids = |status: &str| -> Vec<_> {
+            by_status[status].iter().map(|stale| stale.job.jnid.as_str()).collect()
+        };
+        assert_eq!(by_status.len(), 2);
+        assert_eq!(jnids("Incoming Lead"), ["staler lead", "stale lead"]);
+        assert_eq!(jnids("Awaiting Permit"), ["custom"]);
+    }
+}
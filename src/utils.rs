@@ -1,5 +1,9 @@
+use anyhow::Context as _;
+use chrono::{Datelike as _, NaiveDate, NaiveDateTime, NaiveTime, TimeZone as _, Utc};
 use tracing::{info, warn};
 
+use crate::jobs::Timestamp;
+
 pub fn open_url(url: &str) {
     match open::that(url) {
         Ok(()) => info!("Opened URL: {}", url),
@@ -9,3 +13,51 @@ pub fn open_url(url: &str) {
         }
     }
 }
+
+/// Parses the start of a date range. Valid options are a date of the form
+/// "%Y-%m-%d", "ytd" (indicating the start of the current year), "mtd"
+/// (indicating the start of the current month), "today" (indicating the current
+/// date), or "forever" (indicating the beginning of time, represented by
+/// `None`).
+pub fn parse_from_date(date: &str) -> anyhow::Result<Option<Timestamp>> {
+    Ok(match date {
+        "forever" => None,
+        "ytd" => Some(
+            Utc.from_utc_datetime(&NaiveDateTime::new(
+                NaiveDate::from_ymd_opt(Utc::now().year(), 1, 1)
+                    .expect("Jan 1 should always be valid in the current year."),
+                NaiveTime::MIN,
+            )),
+        ),
+        "mtd" => Some(
+            Utc.from_utc_datetime(&NaiveDateTime::new(
+                NaiveDate::from_ymd_opt(Utc::now().year(), Utc::now().month(), 1)
+                    .expect("the first of the month should always be valid"),
+                NaiveTime::MIN,
+            )),
+        ),
+        "today" => Some(Utc::now()),
+        date_string => Some(
+            NaiveDate::parse_from_str(date_string, "%Y-%m-%d")
+                .map(|date| Utc.from_utc_datetime(&NaiveDateTime::new(date, NaiveTime::MIN)))
+                .context(
+                    "Invalid date format. Use 'forever', 'ytd', 'mtd', 'today', or '%Y-%m-%d'.",
+                )?,
+        ),
+    })
+}
+
+/// Parses the end of a date range. Valid options are a date of the form
+/// "%Y-%m-%d", "today" (indicating the current date), or "forever" (indicating
+/// the end of time, represented by `None`).
+pub fn parse_to_date(date: &str) -> anyhow::Result<Option<Timestamp>> {
+    Ok(match date {
+        "forever" => None,
+        "today" => Some(Utc::now()),
+        date_string => Some(
+            NaiveDate::parse_from_str(date_string, "%Y-%m-%d")
+                .map(|date| Utc.from_utc_datetime(&NaiveDateTime::new(date, NaiveTime::MIN)))
+                .context("Invalid date format. Use 'forever', 'today', or '%Y-%m-%d'.")?,
+        ),
+    })
+}